@@ -0,0 +1 @@
+pub mod rete_neurale_mlp;
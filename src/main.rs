@@ -1,11 +1,9 @@
 
 
-use crate::rete_neurale_mlp::rete_neurale::*;
+use my_rust_project::rete_neurale_mlp::rete_neurale::*;
 use std::sync::{Arc, RwLock};
 use lazy_static::lazy_static;
 
-mod rete_neurale_mlp;
-
 
 // Uso di Arc<RwLock> per rendere il puntatore thread-safe
 lazy_static! {
@@ -38,7 +36,13 @@ fn main() {
         rete = ReteNeurale::nuova(strati, tasso_apprendimento);
     } else {
         println!("[*]-- TEST CARICA RETE ESISTENTE ----------- ");
-        rete = ReteNeurale::carica("rete_neurale.txt");
+        rete = match ReteNeurale::carica("rete_neurale.txt") {
+            Ok(rete) => rete,
+            Err(e) => {
+                eprintln!("[x] caricamento della rete non riuscito: {}", e);
+                return;
+            }
+        };
     }
 
     let dati_addestramento = [
@@ -69,11 +73,12 @@ fn main() {
         let uscita = rete.elabora(set.input.clone());
         println!("Input: {:?}, Previsto: {:?}, Uscita: {:?}", set.input, set.output, uscita);
     }
+    println!("Perdita media: {}", rete.perdita_media(&dati_addestramento));
 
     if TEST_ADDESTRA_NUOVA_RETE {
         let _risultato = rete.salva_pesi_txt("rete_neurale.txt");
         if let Err(e) = _risultato {
-            println!("{:?}", e);
+            eprintln!("[x] salvataggio della rete non riuscito: {}", e);
         }
     }
 
@@ -1 +1,4 @@
-pub mod rete_neurale;
\ No newline at end of file
+pub mod rete_neurale;
+pub mod registro;
+pub mod normalizzazione;
+pub mod precisione_singola;
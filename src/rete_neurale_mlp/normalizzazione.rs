@@ -0,0 +1,342 @@
+//! Normalizzazione dei dati di addestramento, indipendente dalla rete: le feature con scale molto diverse
+//! rallentano la convergenza, per cui input e output vengono riportati a un intervallo comune prima
+//! dell'addestramento e gli output della rete riportati alla scala originale dopo l'inferenza.
+
+use crate::rete_neurale_mlp::rete_neurale::{crea_file, InputAddestramento};
+use serde_json::{json, Value};
+use std::io::{Error, ErrorKind, Write};
+
+/// Minimo e massimo di ogni feature di un insieme di vettori.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Intervalli {
+    minimi: Vec<f64>,
+    massimi: Vec<f64>,
+}
+
+impl Intervalli {
+    fn da_valori<'a>(valori: impl Iterator<Item = &'a [f64]>) -> Self {
+        let mut intervalli = Intervalli::default();
+        for vettore in valori {
+            if intervalli.minimi.is_empty() {
+                intervalli.minimi = vettore.to_vec();
+                intervalli.massimi = vettore.to_vec();
+                continue;
+            }
+            for (i, &valore) in vettore.iter().enumerate() {
+                intervalli.minimi[i] = intervalli.minimi[i].min(valore);
+                intervalli.massimi[i] = intervalli.massimi[i].max(valore);
+            }
+        }
+        intervalli
+    }
+
+    /// Mappa ogni feature in [0, 1]; le feature costanti (min == max) diventano 0.
+    fn trasforma(&self, valori: &[f64]) -> Vec<f64> {
+        assert_eq!(valori.len(), self.minimi.len(), "numero di feature diverso da quello adattato");
+        valori.iter()
+            .zip(self.minimi.iter().zip(self.massimi.iter()))
+            .map(|(&valore, (&minimo, &massimo))| {
+                if massimo > minimo {
+                    (valore - minimo) / (massimo - minimo)
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    fn inverti(&self, valori: &[f64]) -> Vec<f64> {
+        assert_eq!(valori.len(), self.minimi.len(), "numero di feature diverso da quello adattato");
+        valori.iter()
+            .zip(self.minimi.iter().zip(self.massimi.iter()))
+            .map(|(&valore, (&minimo, &massimo))| minimo + valore * (massimo - minimo))
+            .collect()
+    }
+}
+
+/// Normalizzazione min-max: ogni feature di input e di output viene mappata in [0, 1]
+/// con il minimo e il massimo osservati nel set di addestramento.
+///
+/// # Esempio
+/// ```
+/// let mut normalizzatore = Normalizzatore::default();
+/// normalizzatore.adatta(&dati_addestramento);
+/// rete.addestra_epoche(&normalizzatore.trasforma_dati(&dati_addestramento), 1000, opzioni)?;
+///
+/// let uscita = rete.elabora(normalizzatore.trasforma(&input));
+/// let previsione = normalizzatore.inverti(&uscita);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Normalizzatore {
+    input: Intervalli,
+    output: Intervalli,
+}
+
+impl Normalizzatore {
+    /// Calcola minimo e massimo di ogni feature di input e di output dal set di addestramento.
+    pub fn adatta(&mut self, dati: &[InputAddestramento]) {
+        self.input = Intervalli::da_valori(dati.iter().map(|set| set.input.as_slice()));
+        self.output = Intervalli::da_valori(dati.iter().map(|set| set.output.as_slice()));
+    }
+
+    /// Normalizza un input in [0, 1] per feature. Le feature costanti nel set di addestramento diventano 0.
+    pub fn trasforma(&self, input: &[f64]) -> Vec<f64> {
+        self.input.trasforma(input)
+    }
+
+    /// Normalizza un output (target) in [0, 1] per feature.
+    pub fn trasforma_output(&self, output: &[f64]) -> Vec<f64> {
+        self.output.trasforma(output)
+    }
+
+    /// Riporta un output della rete, normalizzato, alla scala originale dei target.
+    pub fn inverti(&self, output: &[f64]) -> Vec<f64> {
+        self.output.inverti(output)
+    }
+
+    /// Normalizza input e output di un insieme di esempi, pronto per l'addestramento.
+    pub fn trasforma_dati(&self, dati: &[InputAddestramento]) -> Vec<InputAddestramento> {
+        dati.iter()
+            .map(|set| InputAddestramento {
+                input: self.trasforma(&set.input),
+                output: self.trasforma_output(&set.output),
+            })
+            .collect()
+    }
+}
+
+/// Media e deviazione standard di ogni feature di un insieme di vettori.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Statistiche {
+    medie: Vec<f64>,
+    deviazioni: Vec<f64>,
+}
+
+impl Statistiche {
+    fn da_valori(valori: &[&[f64]]) -> Self {
+        let Some(primo) = valori.first() else {
+            return Statistiche::default();
+        };
+        let n = valori.len() as f64;
+        // somma e poi divisione: sommare `valore / n` accumula arrotondamenti, e una feature costante
+        // finirebbe con una deviazione standard minuscola ma non nulla invece di essere mappata a 0
+        let mut medie = vec![0.0; primo.len()];
+        for vettore in valori.iter() {
+            for (media, &valore) in medie.iter_mut().zip(vettore.iter()) {
+                *media += valore;
+            }
+        }
+        for media in medie.iter_mut() {
+            *media /= n;
+        }
+        let mut varianze = vec![0.0; primo.len()];
+        for vettore in valori.iter() {
+            for ((varianza, &media), &valore) in varianze.iter_mut().zip(medie.iter()).zip(vettore.iter()) {
+                *varianza += (valore - media) * (valore - media) / n;
+            }
+        }
+        Statistiche { medie, deviazioni: varianze.into_iter().map(f64::sqrt).collect() }
+    }
+
+    /// Applica `(x - media) / std` a ogni feature; le feature costanti (std nulla) diventano 0.
+    fn trasforma(&self, valori: &[f64]) -> Vec<f64> {
+        assert_eq!(valori.len(), self.medie.len(), "numero di feature diverso da quello adattato");
+        valori.iter()
+            .zip(self.medie.iter().zip(self.deviazioni.iter()))
+            .map(|(&valore, (&media, &deviazione))| {
+                if deviazione > 0.0 {
+                    (valore - media) / deviazione
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    fn inverti(&self, valori: &[f64]) -> Vec<f64> {
+        assert_eq!(valori.len(), self.medie.len(), "numero di feature diverso da quello adattato");
+        valori.iter()
+            .zip(self.medie.iter().zip(self.deviazioni.iter()))
+            .map(|(&valore, (&media, &deviazione))| media + valore * deviazione)
+            .collect()
+    }
+
+    fn a_json(&self) -> Value {
+        json!({ "media": self.medie, "std": self.deviazioni })
+    }
+
+    fn da_json(valore: Option<&Value>, campo: &str) -> Result<Self, Error> {
+        let non_valido = || Error::new(ErrorKind::InvalidData, format!("campo '{}' mancante o non valido", campo));
+        let lista = |nome: &str| -> Result<Vec<f64>, Error> {
+            valore.and_then(|v| v.get(nome)).and_then(|v| v.as_array())
+                .ok_or_else(non_valido)?
+                .iter()
+                .map(|valore| valore.as_f64().ok_or_else(non_valido))
+                .collect()
+        };
+        let (medie, deviazioni) = (lista("media")?, lista("std")?);
+        if medie.len() != deviazioni.len() {
+            return Err(non_valido());
+        }
+        Ok(Statistiche { medie, deviazioni })
+    }
+}
+
+/// Standardizzazione z-score: ogni feature di input e di output viene trasformata con `(x - media) / std`,
+/// usando media e deviazione standard del set di addestramento. Rispetto al min-max tende a funzionare meglio
+/// con attivazioni centrate sullo zero come Tanh. I parametri si salvano con `salva`, per standardizzare
+/// allo stesso modo i nuovi dati in inferenza.
+///
+/// # Esempio
+/// ```
+/// let mut standardizzatore = Standardizzatore::default();
+/// standardizzatore.adatta(&dati_addestramento);
+/// standardizzatore.salva("standardizzatore.json")?;
+///
+/// let standardizzatore = Standardizzatore::carica("standardizzatore.json")?;
+/// let previsione = standardizzatore.inverti(&rete.elabora(standardizzatore.trasforma(&input)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Standardizzatore {
+    input: Statistiche,
+    output: Statistiche,
+}
+
+impl Standardizzatore {
+    /// Calcola media e deviazione standard di ogni feature di input e di output dal set di addestramento.
+    pub fn adatta(&mut self, dati: &[InputAddestramento]) {
+        self.input = Statistiche::da_valori(&dati.iter().map(|set| set.input.as_slice()).collect::<Vec<_>>());
+        self.output = Statistiche::da_valori(&dati.iter().map(|set| set.output.as_slice()).collect::<Vec<_>>());
+    }
+
+    /// Standardizza un input. Le feature costanti nel set di addestramento diventano 0.
+    pub fn trasforma(&self, input: &[f64]) -> Vec<f64> {
+        self.input.trasforma(input)
+    }
+
+    /// Standardizza un output (target).
+    pub fn trasforma_output(&self, output: &[f64]) -> Vec<f64> {
+        self.output.trasforma(output)
+    }
+
+    /// Riporta un output della rete, standardizzato, alla scala originale dei target.
+    pub fn inverti(&self, output: &[f64]) -> Vec<f64> {
+        self.output.inverti(output)
+    }
+
+    /// Standardizza input e output di un insieme di esempi, pronto per l'addestramento.
+    pub fn trasforma_dati(&self, dati: &[InputAddestramento]) -> Vec<InputAddestramento> {
+        dati.iter()
+            .map(|set| InputAddestramento {
+                input: self.trasforma(&set.input),
+                output: self.trasforma_output(&set.output),
+            })
+            .collect()
+    }
+
+    /// Salva medie e deviazioni standard in un file JSON, creando le directory intermedie mancanti.
+    pub fn salva(&self, file_path: &str) -> Result<(), Error> {
+        let documento = json!({ "input": self.input.a_json(), "output": self.output.a_json() });
+        let mut file = crea_file(file_path)?;
+        writeln!(file, "{}", serde_json::to_string_pretty(&documento)?)
+    }
+
+    /// Carica i parametri salvati con `salva`.
+    pub fn carica(file_path: &str) -> Result<Self, Error> {
+        let documento: Value = serde_json::from_str(&std::fs::read_to_string(file_path)?)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        Ok(Standardizzatore {
+            input: Statistiche::da_json(documento.get("input"), "input")?,
+            output: Statistiche::da_json(documento.get("output"), "output")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Esempi con feature su scale molto diverse, una delle quali costante.
+    fn dati() -> Vec<InputAddestramento> {
+        (0..20)
+            .map(|i| InputAddestramento {
+                input: vec![i as f64 * 100.0 - 500.0, (i as f64 * 0.37).sin() * 0.01, 3.0],
+                output: vec![i as f64 * 2.0 + 1.0],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn normalizzatore_porta_le_feature_in_zero_uno() {
+        let dati = dati();
+        let mut normalizzatore = Normalizzatore::default();
+        normalizzatore.adatta(&dati);
+        let trasformati = normalizzatore.trasforma_dati(&dati);
+        for feature in 0..2 {
+            let valori: Vec<f64> = trasformati.iter().map(|set| set.input[feature]).collect();
+            assert!(valori.iter().all(|&v| (0.0..=1.0).contains(&v)));
+            assert!(valori.iter().any(|&v| v.abs() < 1e-12));
+            assert!(valori.iter().any(|&v| (v - 1.0).abs() < 1e-12));
+        }
+        assert!(trasformati.iter().all(|set| set.input[2] == 0.0));
+        assert!(trasformati.iter().all(|set| (0.0..=1.0).contains(&set.output[0])));
+        let ripristinato = normalizzatore.inverti(&trasformati[7].output);
+        assert!((ripristinato[0] - dati[7].output[0]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn standardizzatore_porta_media_zero_e_varianza_uno() {
+        let dati = dati();
+        let mut standardizzatore = Standardizzatore::default();
+        standardizzatore.adatta(&dati);
+        let trasformati = standardizzatore.trasforma_dati(&dati);
+        let n = trasformati.len() as f64;
+        for feature in 0..2 {
+            let valori: Vec<f64> = trasformati.iter().map(|set| set.input[feature]).collect();
+            let media = valori.iter().sum::<f64>() / n;
+            let varianza = valori.iter().map(|v| (v - media).powi(2)).sum::<f64>() / n;
+            assert!(media.abs() < 1e-9, "media {}", media);
+            assert!((varianza - 1.0).abs() < 1e-6, "varianza {}", varianza);
+        }
+        assert!(trasformati.iter().all(|set| set.input[2] == 0.0));
+        let ripristinato = standardizzatore.inverti(&trasformati[3].output);
+        assert!((ripristinato[0] - dati[3].output[0]).abs() < 1e-9);
+    }
+
+    /// Percorso di un file temporaneo, distinto per test e per processo.
+    fn file_temporaneo(nome: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("normalizzazione_{}_{}", std::process::id(), nome))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn standardizzatore_salvato_e_ricaricato_e_identico() {
+        let mut standardizzatore = Standardizzatore::default();
+        standardizzatore.adatta(&dati());
+        let file_path = file_temporaneo("sottocartella/standardizzatore.json");
+        standardizzatore.salva(&file_path).unwrap();
+        let caricato = Standardizzatore::carica(&file_path);
+        std::fs::remove_dir_all(std::path::Path::new(&file_path).parent().unwrap()).unwrap();
+        assert_eq!(caricato.unwrap(), standardizzatore);
+    }
+
+    #[test]
+    fn standardizzatore_rifiuta_json_malformati() {
+        let contenuti = [
+            "non è json",
+            r#"{"input": {"media": [0.0], "std": [1.0]}}"#,
+            r#"{"input": {"media": [0.0, 1.0], "std": [1.0]}, "output": {"media": [0.0], "std": [1.0]}}"#,
+            r#"{"input": {"media": ["zero"], "std": [1.0]}, "output": {"media": [0.0], "std": [1.0]}}"#,
+            r#"{"input": {"media": [0.0], "std": 1.0}, "output": {"media": [0.0], "std": [1.0]}}"#,
+        ];
+        let file_path = file_temporaneo("standardizzatore_malformato.json");
+        for contenuto in contenuti {
+            std::fs::write(&file_path, contenuto).unwrap();
+            let errore = Standardizzatore::carica(&file_path).unwrap_err();
+            assert_eq!(errore.kind(), ErrorKind::InvalidData, "{}", contenuto);
+        }
+        std::fs::remove_file(&file_path).unwrap();
+    }
+}
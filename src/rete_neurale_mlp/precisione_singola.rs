@@ -0,0 +1,198 @@
+//! Variante in precisione singola della rete: pesi e bias in `f32`, per dimezzare la memoria dei modelli grandi.
+//! Rendere `ReteNeurale` generica sul tipo scalare richiederebbe di riscrivere anche funzioni di attivazione,
+//! perdite e formati di salvataggio; qui si riusano quelli esistenti in `f64`, convertendo i vettori
+//! di uno strato alla volta durante il calcolo, mentre i parametri restano memorizzati in `f32`.
+
+use crate::rete_neurale_mlp::rete_neurale::{FunzioneAttivazione, FunzionePerdita, InputAddestramento, ReteNeurale};
+use nalgebra::{DMatrix, DVector};
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+/// Rete MLP con pesi e bias in `f32`, ottenuta da una `ReteNeurale` di cui copia topologia, funzioni di attivazione,
+/// funzione di perdita, pesi e tasso di apprendimento. Si addestra con la discesa del gradiente semplice,
+/// un esempio alla volta: ottimizzatori, batch normalization, regolarizzazioni e salvataggio restano
+/// prerogativa della rete in `f64`.
+///
+/// # Esempio
+/// ```
+/// let mut rete = ReteNeuraleF32::da_rete(&ReteNeurale::nuova(strati, 0.5))?;
+/// let perdite = rete.addestra_epoche(&dati_addestramento, 10000);
+/// println!("perdita finale {:?}, uscita {:?}", perdite.last(), rete.elabora(&[0.0, 1.0]));
+/// ```
+pub struct ReteNeuraleF32 {
+    strati: Vec<DMatrix<f32>>,
+    bias: Vec<DVector<f32>>,
+    funzioni_attivazione: Vec<Arc<dyn FunzioneAttivazione + Send + Sync>>,
+    perdita: Arc<dyn FunzionePerdita + Send + Sync>,
+    tasso_apprendimento: f32,
+}
+
+fn a_f64(v: &DVector<f32>) -> DVector<f64> {
+    v.map(f64::from)
+}
+
+fn a_f32(v: &DVector<f64>) -> DVector<f32> {
+    v.map(|x| x as f32)
+}
+
+impl ReteNeuraleF32 {
+    /// Converte una rete in precisione singola. Le reti con strati di batch normalization non sono supportate.
+    pub fn da_rete(rete: &ReteNeurale) -> Result<Self, Error> {
+        let strati = rete.matrici_pesi();
+        if (0..strati.len()).any(|indice| rete.batch_norm(indice).is_some()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "batch normalization non supportata in precisione singola"));
+        }
+        Ok(ReteNeuraleF32 {
+            strati: strati.iter().map(|pesi| pesi.map(|x| x as f32)).collect(),
+            bias: rete.matrici_bias().iter().map(a_f32).collect(),
+            funzioni_attivazione: rete.funzioni_attivazione().to_vec(),
+            perdita: rete.perdita(),
+            tasso_apprendimento: rete.tasso_apprendimento() as f32,
+        })
+    }
+
+    /// Elabora l'input e restituisce l'output della rete.
+    pub fn elabora(&self, input: &[f32]) -> Vec<f32> {
+        let (_, uscite) = self.propagazione_avanti(input);
+        uscite[uscite.len() - 1].iter().copied().collect()
+    }
+
+    /// Propagazione in avanti: pre-attivazioni e uscite di ogni strato (per l'input coincidono con l'input).
+    fn propagazione_avanti(&self, input: &[f32]) -> (Vec<DVector<f32>>, Vec<DVector<f32>>) {
+        let mut pre_attivazioni = vec![DVector::from_column_slice(input)];
+        let mut uscite = pre_attivazioni.clone();
+        for (k, (pesi, bias)) in self.strati.iter().zip(self.bias.iter()).enumerate() {
+            let pre_attivazione = pesi * &uscite[k] + bias;
+            uscite.push(a_f32(&self.funzioni_attivazione[k + 1].attiva_vettore(&a_f64(&pre_attivazione))));
+            pre_attivazioni.push(pre_attivazione);
+        }
+        (pre_attivazioni, uscite)
+    }
+
+    /// Addestra la rete su un singolo esempio con un passo di discesa del gradiente.
+    pub fn addestra(&mut self, input: &[f32], target: &[f32]) {
+        let (pre_attivazioni, uscite) = self.propagazione_avanti(input);
+        let ultimo = uscite.len() - 1;
+        let uscita = a_f64(&uscite[ultimo]);
+        let gradiente = self.perdita.gradiente(&uscita, &DVector::from_iterator(target.len(), target.iter().map(|&x| f64::from(x))));
+        // delta = -dL/dz dello strato corrente
+        let mut delta = a_f32(&self.funzioni_attivazione[ultimo].retropropaga_vettore(
+            &a_f64(&pre_attivazioni[ultimo]), &uscita, &-gradiente
+        ));
+        for i in (1..=ultimo).rev() {
+            let errore = self.strati[i - 1].transpose() * &delta;
+            self.strati[i - 1] += self.tasso_apprendimento * &delta * uscite[i - 1].transpose();
+            self.bias[i - 1] += self.tasso_apprendimento * &delta;
+            if i > 1 {
+                delta = a_f32(&self.funzioni_attivazione[i - 1].retropropaga_vettore(
+                    &a_f64(&pre_attivazioni[i - 1]), &a_f64(&uscite[i - 1]), &a_f64(&errore)
+                ));
+            }
+        }
+    }
+
+    /// Addestra la rete per un numero di epoche, visitando tutti gli esempi in ordine a ogni epoca.
+    ///
+    /// # Ritorna
+    ///
+    /// La perdita media sul set di addestramento alla fine di ogni epoca.
+    pub fn addestra_epoche(&mut self, dati: &[InputAddestramento], epoche: usize) -> Vec<f32> {
+        let dati: Vec<(Vec<f32>, Vec<f32>)> = dati.iter()
+            .map(|set| (set.input.iter().map(|&x| x as f32).collect(), set.output.iter().map(|&x| x as f32).collect()))
+            .collect();
+        let mut perdite = Vec::with_capacity(epoche);
+        for _ in 0..epoche {
+            for (input, target) in dati.iter() {
+                self.addestra(input, target);
+            }
+            let totale: f64 = dati.iter()
+                .map(|(input, target)| self.perdita_esempio(input, target))
+                .sum();
+            perdite.push((totale / dati.len().max(1) as f64) as f32);
+        }
+        perdite
+    }
+
+    /// Perdita media della rete su un insieme di esempi (0 se l'insieme è vuoto).
+    pub fn perdita_media(&self, dati: &[InputAddestramento]) -> f32 {
+        if dati.is_empty() {
+            return 0.0;
+        }
+        let totale: f64 = dati.iter()
+            .map(|set| {
+                let input: Vec<f32> = set.input.iter().map(|&x| x as f32).collect();
+                let target: Vec<f32> = set.output.iter().map(|&x| x as f32).collect();
+                self.perdita_esempio(&input, &target)
+            })
+            .sum();
+        (totale / dati.len() as f64) as f32
+    }
+
+    fn perdita_esempio(&self, input: &[f32], target: &[f32]) -> f64 {
+        let uscita = DVector::from_iterator(target.len(), self.elabora(input).into_iter().map(f64::from));
+        let target = DVector::from_iterator(target.len(), target.iter().map(|&x| f64::from(x)));
+        self.perdita.calcola(&uscita, &target)
+    }
+
+    /// Matrici dei pesi delle connessioni, dall'input verso l'output.
+    pub fn matrici_pesi(&self) -> &[DMatrix<f32>] {
+        &self.strati
+    }
+
+    /// Vettori dei bias di ogni strato dopo l'input.
+    pub fn matrici_bias(&self) -> &[DVector<f32>] {
+        &self.bias
+    }
+
+    /// Stima dei byte occupati da pesi e bias in `f32`, metà di quelli della rete in `f64` da cui deriva.
+    /// Considera solo i valori numerici, non le strutture di contorno.
+    pub fn memoria_stimata(&self) -> usize {
+        let valori = self.strati.iter().map(|pesi| pesi.len()).sum::<usize>()
+            + self.bias.iter().map(|bias| bias.len()).sum::<usize>();
+        valori * std::mem::size_of::<f32>()
+    }
+
+    /// Tasso di apprendimento.
+    pub fn tasso_apprendimento(&self) -> f32 {
+        self.tasso_apprendimento
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rete_neurale_mlp::rete_neurale::{Nessuna, Sigmoide, Strato};
+
+    fn rete_f64() -> ReteNeurale {
+        ReteNeurale::nuova_con_seme(vec![
+            Strato { neuroni: 2, funzione_attivazione: Arc::new(Nessuna) },
+            Strato { neuroni: 4, funzione_attivazione: Arc::new(Sigmoide) },
+            Strato { neuroni: 1, funzione_attivazione: Arc::new(Sigmoide) },
+        ], 0.5, 42)
+    }
+
+    #[test]
+    fn memoria_stimata_in_precisione_singola() {
+        // [2, 4, 1]: 2*4 + 4 + 4*1 + 1 = 17 parametri da 4 byte
+        let rete = ReteNeuraleF32::da_rete(&rete_f64()).unwrap();
+        assert_eq!(rete.memoria_stimata(), 17 * 4);
+    }
+
+    #[test]
+    fn addestra_lo_xor_in_precisione_singola() {
+        let dati: Vec<InputAddestramento> = [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 0.0)]
+            .into_iter()
+            .map(|(input, output)| InputAddestramento { input: input.to_vec(), output: vec![output] })
+            .collect();
+        let mut rete = ReteNeuraleF32::da_rete(&rete_f64()).unwrap();
+        let iniziale = rete.perdita_media(&dati);
+        let perdite = rete.addestra_epoche(&dati, 5000);
+        assert_eq!(perdite.len(), 5000);
+        assert!(perdite[4999] < iniziale * 0.1, "perdita da {} a {}", iniziale, perdite[4999]);
+        assert_eq!(rete.perdita_media(&dati), perdite[4999]);
+        for set in dati.iter() {
+            let uscita = rete.elabora(&[set.input[0] as f32, set.input[1] as f32]);
+            assert_eq!(uscita[0] >= 0.5, set.output[0] == 1.0, "input {:?}: {}", set.input, uscita[0]);
+        }
+    }
+}
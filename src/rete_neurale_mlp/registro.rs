@@ -0,0 +1,143 @@
+use crate::rete_neurale_mlp::rete_neurale::ReteNeurale;
+use serde_json::{json, Map, Value};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Iperparametri e metriche finali di un'esecuzione di addestramento.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunEsperimento {
+    /// Dimensioni degli strati, dall'input all'output.
+    pub architettura: Vec<usize>,
+    /// Sigle delle funzioni di attivazione per strato.
+    pub attivazioni: Vec<String>,
+    pub tasso_apprendimento: f64,
+    pub ottimizzatore: String,
+    pub seme: Option<u64>,
+    /// Metriche finali come coppie nome-valore (es. `("perdita", 0.01)`).
+    pub metriche: Vec<(String, f64)>,
+}
+
+impl RunEsperimento {
+    /// Raccoglie gli iperparametri di una rete addestrata, a cui associare le metriche finali.
+    ///
+    /// # Esempio
+    /// ```
+    /// let perdite = rete.addestra_epoche(&dati_addestramento, 1000, opzioni)?;
+    /// let run = RunEsperimento::da_rete(&rete, Some(42), vec![
+    ///     ("perdita".to_string(), rete.perdita_media(&dati_addestramento)),
+    ///     ("epoche".to_string(), perdite.len() as f64),
+    /// ]);
+    /// registro.registra(&run)?;
+    /// ```
+    pub fn da_rete(rete: &ReteNeurale, seme: Option<u64>, metriche: Vec<(String, f64)>) -> Self {
+        RunEsperimento {
+            architettura: rete.strati(),
+            attivazioni: rete.lista_funzioni_attivazioni(),
+            tasso_apprendimento: rete.tasso_apprendimento(),
+            ottimizzatore: rete.ottimizzatore().sigla().to_string(),
+            seme,
+            metriche,
+        }
+    }
+
+    fn a_json(&self) -> Value {
+        json!({
+            "architettura": self.architettura,
+            "attivazioni": self.attivazioni,
+            "tasso_apprendimento": self.tasso_apprendimento,
+            "ottimizzatore": self.ottimizzatore,
+            "seme": self.seme,
+            "metriche": self.metriche.iter().map(|(nome, valore)| (nome.clone(), json!(valore))).collect::<Map<String, Value>>(),
+        })
+    }
+
+    fn da_json(valore: &Value) -> Result<Self, Error> {
+        let mancante = |campo: &str| Error::new(ErrorKind::InvalidData, format!("campo '{}' mancante o non valido", campo));
+        let architettura = valore.get("architettura").and_then(|v| v.as_array())
+            .ok_or_else(|| mancante("architettura"))?
+            .iter()
+            .map(|n| n.as_u64().map(|n| n as usize).ok_or_else(|| mancante("architettura")))
+            .collect::<Result<Vec<usize>, Error>>()?;
+        let attivazioni = valore.get("attivazioni").and_then(|v| v.as_array())
+            .ok_or_else(|| mancante("attivazioni"))?
+            .iter()
+            .map(|a| a.as_str().map(str::to_string).ok_or_else(|| mancante("attivazioni")))
+            .collect::<Result<Vec<String>, Error>>()?;
+        let tasso_apprendimento = valore.get("tasso_apprendimento").and_then(|v| v.as_f64())
+            .ok_or_else(|| mancante("tasso_apprendimento"))?;
+        let ottimizzatore = valore.get("ottimizzatore").and_then(|v| v.as_str())
+            .ok_or_else(|| mancante("ottimizzatore"))?
+            .to_string();
+        let seme = valore.get("seme").and_then(|v| v.as_u64());
+        let metriche = valore.get("metriche").and_then(|v| v.as_object())
+            .ok_or_else(|| mancante("metriche"))?
+            .iter()
+            .map(|(nome, v)| (nome.clone(), v.as_f64().unwrap_or(f64::NAN)))
+            .collect();
+        Ok(RunEsperimento { architettura, attivazioni, tasso_apprendimento, ottimizzatore, seme, metriche })
+    }
+}
+
+/// Registro degli esperimenti su file in formato JSON lines: una riga JSON per ogni run,
+/// con gli iperparametri e le metriche finali, per confrontare gli addestramenti nel tempo.
+pub struct RegistroEsperimenti {
+    percorso: PathBuf,
+}
+
+impl RegistroEsperimenti {
+    /// Crea un registro associato al file indicato (creato alla prima registrazione).
+    pub fn nuovo<P: AsRef<Path>>(percorso: P) -> Self {
+        RegistroEsperimenti { percorso: percorso.as_ref().to_path_buf() }
+    }
+
+    /// Aggiunge un run in coda al registro.
+    pub fn registra(&self, run: &RunEsperimento) -> Result<(), Error> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.percorso)?;
+        writeln!(file, "{}", run.a_json())
+    }
+
+    /// Rilegge tutti i run registrati, nell'ordine di registrazione.
+    pub fn leggi(&self) -> Result<Vec<RunEsperimento>, Error> {
+        let reader = BufReader::new(File::open(&self.percorso)?);
+        let mut runs = Vec::new();
+        for line in reader.lines() {
+            let linea = line?;
+            if !linea.trim().is_empty() {
+                let valore: Value = serde_json::from_str(&linea).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                runs.push(RunEsperimento::da_json(&valore)?);
+            }
+        }
+        Ok(runs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(seme: Option<u64>, perdita: f64) -> RunEsperimento {
+        RunEsperimento {
+            architettura: vec![2, 4, 1],
+            attivazioni: vec!["N".to_string(), "S".to_string(), "S".to_string()],
+            tasso_apprendimento: 0.5,
+            ottimizzatore: "SGD".to_string(),
+            seme,
+            metriche: vec![("epoche".to_string(), 100.0), ("perdita".to_string(), perdita)],
+        }
+    }
+
+    #[test]
+    fn registra_e_rilegge_due_run() {
+        let percorso = std::env::temp_dir().join(format!("rete_neurale_mlp_{}_registro.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&percorso);
+        let registro = RegistroEsperimenti::nuovo(&percorso);
+        let primo = run(Some(42), 0.125);
+        let secondo = run(None, 0.0625);
+        registro.registra(&primo).unwrap();
+        registro.registra(&secondo).unwrap();
+        let letti = registro.leggi().unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        assert_eq!(letti, vec![primo, secondo]);
+    }
+}
@@ -1,14 +1,39 @@
 use nalgebra::{DMatrix, DVector};
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rayon::prelude::*;
+use serde_json::{json, Map, Value};
 use std::fmt::{Display,Debug, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 const _FILE_INFO_RETE :          &str = "[#] ";
 const _FILE_INFO_APPRENDIMENTO:  &str = "[+] ";
 const _FILE_INFO_ATTIVAZIONE:    &str = "[*] ";
 const _FILE_STRATO:              &str = "---";
+const _FILE_BIAS:                &str = "[b] ";
+const _FILE_PERDITA:             &str = "[l] ";
+const _FILE_OTTIMIZZATORE:       &str = "[o] ";
+const _FILE_BATCH_NORM:          &str = "[n] ";
+
+/// Ampiezza del rumore aggiunto all'inizializzazione `InizializzazionePesi::Identita`.
+pub const RUMORE_IDENTITA:       f64 = 0.01;
+
+/// Margine della perdita contrastiva: distanza minima desiderata tra gli embedding di coppie dissimili.
+pub const MARGINE_CONTRASTIVO:   f64 = 1.0;
+
+/// Numero di epoche finali dello storico considerate da `diagnostica_convergenza`.
+pub const FINESTRA_DIAGNOSTICA:  usize = 10;
+
+/// Variazione relativa della perdita sotto la quale, in `diagnostica_convergenza`, l'addestramento è in plateau.
+pub const SOGLIA_PLATEAU:        f64 = 1e-3;
+
+const _BIN_INTESTAZIONE:         &[u8] = b"MLPB";
+const _BIN_VERSIONE:             u8 = 3;    // versione 2: bias dopo i pesi di ogni strato; 3: batch normalization dopo i bias
 
 #[derive(Clone)]
 /// Coppia di input-output del Set di Addestramento di una Rete Neurale.
@@ -16,12 +41,845 @@ pub struct InputAddestramento {
     pub input: Vec<f64>,
     pub output: Vec<f64>
 }
+
+impl InputAddestramento {
+    /// Crea un esempio di classificazione con output one-hot a partire dall'etichetta intera della classe.
+    ///
+    /// # Argomenti
+    ///
+    /// * `input` - Vettore di input.
+    /// * `classe` - Indice della classe, deve essere minore di `num_classi`.
+    /// * `num_classi` - Numero di classi, cioè la dimensione dell'output.
+    ///
+    /// # Esempio
+    /// ```
+    /// let set = InputAddestramento::da_etichetta(vec![0.2, 0.7], 2, 3)?; // output: [0.0, 0.0, 1.0]
+    /// ```
+    pub fn da_etichetta(input: Vec<f64>, classe: usize, num_classi: usize) -> Result<Self, Error> {
+        if classe >= num_classi {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("classe {} fuori intervallo per {} classi", classe, num_classi)
+            ));
+        }
+        let mut output = vec![0.0; num_classi];
+        output[classe] = 1.0;
+        Ok(InputAddestramento { input, output })
+    }
+}
+/// Tipi che possono essere dati in input alla rete (vedi `ReteNeurale::elabora_tramite`), ad esempio
+/// struct di feature dell'applicazione, senza convertirli a mano in `Vec<f64>` a ogni chiamata.
+///
+/// # Esempio
+/// ```
+/// struct Misura { temperatura: f64, umidita: f64 }
+///
+/// impl ConvertibileInInput for Misura {
+///     fn in_input(&self) -> Vec<f64> {
+///         vec![self.temperatura, self.umidita]
+///     }
+/// }
+///
+/// let uscita = rete.elabora_tramite(&Misura { temperatura: 21.5, umidita: 0.4 });
+/// ```
+pub trait ConvertibileInInput {
+    /// Converte il valore nel vettore di input della rete; la lunghezza deve coincidere con i neuroni di input.
+    fn in_input(&self) -> Vec<f64>;
+}
+
+impl ConvertibileInInput for Vec<f64> {
+    fn in_input(&self) -> Vec<f64> {
+        self.clone()
+    }
+}
+
+impl<const N: usize> ConvertibileInInput for [f64; N] {
+    fn in_input(&self) -> Vec<f64> {
+        self.to_vec()
+    }
+}
+
 /// Informazioni relative al numero di neuroni e alla funzione di ativazione di uno strato.
 pub struct Strato {
     pub neuroni: usize,
     pub funzione_attivazione: Arc<dyn FunzioneAttivazione + Send + Sync>
 }
 
+/// Tipo di uno strato per `ReteNeurale::nuova_tipizzata`.
+pub enum TipoStrato {
+    /// Strato denso (completamente connesso al precedente), con numero di neuroni e funzione di attivazione.
+    Denso(Strato),
+    /// Batch normalization della pre-attivazione dello strato denso precedente, prima della sua attivazione.
+    BatchNorm,
+}
+
+/// Batch normalization della pre-attivazione di uno strato denso. In addestramento ogni neurone è normalizzato
+/// con media e varianza del batch, `ẑ = (z - μ) / sqrt(σ² + ε)`, e riscalato con i parametri appresi
+/// `y = γ·ẑ + β`; in inferenza si usano invece la media e la varianza mobili accumulate in addestramento,
+/// per cui l'output di un esempio non dipende dagli altri.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchNorm {
+    gamma: DVector<f64>,
+    beta: DVector<f64>,
+    media_mobile: DVector<f64>,
+    varianza_mobile: DVector<f64>,
+}
+
+impl BatchNorm {
+    /// Peso delle statistiche mobili precedenti nell'aggiornamento con quelle di un nuovo batch.
+    pub const MOMENTO: f64 = 0.9;
+    /// Costante sommata alla varianza per evitare divisioni per zero.
+    pub const EPSILON: f64 = 1e-5;
+
+    /// Normalizzazione per `neuroni` neuroni con `γ = 1`, `β = 0` e statistiche mobili iniziali `μ = 0`, `σ² = 1`.
+    pub fn nuova(neuroni: usize) -> Self {
+        BatchNorm {
+            gamma: DVector::from_element(neuroni, 1.0),
+            beta: DVector::zeros(neuroni),
+            media_mobile: DVector::zeros(neuroni),
+            varianza_mobile: DVector::from_element(neuroni, 1.0),
+        }
+    }
+
+    pub fn neuroni(&self) -> usize {
+        self.gamma.len()
+    }
+
+    pub fn gamma(&self) -> &DVector<f64> {
+        &self.gamma
+    }
+
+    pub fn beta(&self) -> &DVector<f64> {
+        &self.beta
+    }
+
+    pub fn media_mobile(&self) -> &DVector<f64> {
+        &self.media_mobile
+    }
+
+    pub fn varianza_mobile(&self) -> &DVector<f64> {
+        &self.varianza_mobile
+    }
+
+    /// Normalizzazione in addestramento di un batch di pre-attivazioni (`neuroni × campioni`, un campione per colonna)
+    /// con le statistiche del batch, aggiornando media e varianza mobili. Prima di `γ` e `β` ogni riga
+    /// del risultato ha media 0 e varianza 1 (a meno di `EPSILON`).
+    pub fn normalizza_batch(&mut self, pre_attivazioni: &DMatrix<f64>) -> DMatrix<f64> {
+        self.avanti_batch(pre_attivazioni).0
+    }
+
+    /// Normalizzazione in inferenza di una pre-attivazione, con media e varianza mobili.
+    pub fn normalizza(&self, pre_attivazione: &DVector<f64>) -> DVector<f64> {
+        let mut normalizzata = pre_attivazione.clone();
+        self.normalizza_sul_posto(&mut normalizzata);
+        normalizzata
+    }
+
+    fn normalizza_sul_posto(&self, pre_attivazione: &mut DVector<f64>) {
+        let scala = self.scala();
+        for j in 0..pre_attivazione.len() {
+            pre_attivazione[j] = scala[j] * (pre_attivazione[j] - self.media_mobile[j]) + self.beta[j];
+        }
+    }
+
+    /// Derivata dell'uscita rispetto alla pre-attivazione in inferenza, `γ / sqrt(σ² + ε)` per neurone.
+    fn scala(&self) -> DVector<f64> {
+        self.gamma.zip_map(&self.varianza_mobile, |gamma, varianza| gamma / (varianza + Self::EPSILON).sqrt())
+    }
+
+    /// Passo in avanti di addestramento: restituisce l'uscita, le pre-attivazioni normalizzate `ẑ`
+    /// e `1 / sqrt(σ² + ε)` per neurone, che servono alla retropropagazione.
+    fn avanti_batch(&mut self, pre_attivazioni: &DMatrix<f64>) -> (DMatrix<f64>, DMatrix<f64>, DVector<f64>) {
+        let (neuroni, campioni) = pre_attivazioni.shape();
+        let n = campioni as f64;
+        let mut normalizzate = pre_attivazioni.clone();
+        let mut inverse = DVector::zeros(neuroni);
+        for j in 0..neuroni {
+            let riga = pre_attivazioni.row(j);
+            let media = riga.sum() / n;
+            let varianza = riga.iter().map(|z| (z - media) * (z - media)).sum::<f64>() / n;
+            inverse[j] = 1.0 / (varianza + Self::EPSILON).sqrt();
+            for c in 0..campioni {
+                normalizzate[(j, c)] = (pre_attivazioni[(j, c)] - media) * inverse[j];
+            }
+            // varianza mobile non distorta, come stima della varianza della popolazione
+            let varianza_campionaria = if campioni > 1 { varianza * n / (n - 1.0) } else { varianza };
+            self.media_mobile[j] = Self::MOMENTO * self.media_mobile[j] + (1.0 - Self::MOMENTO) * media;
+            self.varianza_mobile[j] = Self::MOMENTO * self.varianza_mobile[j] + (1.0 - Self::MOMENTO) * varianza_campionaria;
+        }
+        let mut uscite = normalizzate.clone();
+        for j in 0..neuroni {
+            for c in 0..campioni {
+                uscite[(j, c)] = self.gamma[j] * normalizzate[(j, c)] + self.beta[j];
+            }
+        }
+        (uscite, normalizzate, inverse)
+    }
+
+    /// Retropropagazione di addestramento: dal gradiente della perdita rispetto alle uscite calcola quelli
+    /// rispetto alle pre-attivazioni (tenendo conto che media e varianza dipendono da tutto il batch), a `γ` e a `β`.
+    fn indietro_batch(
+        &self,
+        gradiente_uscite: &DMatrix<f64>,
+        normalizzate: &DMatrix<f64>,
+        inverse: &DVector<f64>
+    ) -> (DMatrix<f64>, DVector<f64>, DVector<f64>) {
+        let (neuroni, campioni) = gradiente_uscite.shape();
+        let n = campioni as f64;
+        let mut gradiente_pre_attivazioni = DMatrix::zeros(neuroni, campioni);
+        let mut gradiente_gamma = DVector::zeros(neuroni);
+        let mut gradiente_beta = DVector::zeros(neuroni);
+        for j in 0..neuroni {
+            let mut somma = 0.0;
+            let mut somma_pesata = 0.0;
+            for c in 0..campioni {
+                gradiente_beta[j] += gradiente_uscite[(j, c)];
+                gradiente_gamma[j] += gradiente_uscite[(j, c)] * normalizzate[(j, c)];
+                let gradiente_normalizzata = gradiente_uscite[(j, c)] * self.gamma[j];
+                somma += gradiente_normalizzata;
+                somma_pesata += gradiente_normalizzata * normalizzate[(j, c)];
+            }
+            for c in 0..campioni {
+                let gradiente_normalizzata = gradiente_uscite[(j, c)] * self.gamma[j];
+                gradiente_pre_attivazioni[(j, c)] = inverse[j] / n
+                    * (n * gradiente_normalizzata - somma - normalizzate[(j, c)] * somma_pesata);
+            }
+        }
+        (gradiente_pre_attivazioni, gradiente_gamma, gradiente_beta)
+    }
+
+    /// Parametri come un'unica lista: `γ`, `β`, media e varianza mobili, per i formati di testo.
+    fn parametri(&self) -> Vec<f64> {
+        self.gamma.iter()
+            .chain(self.beta.iter())
+            .chain(self.media_mobile.iter())
+            .chain(self.varianza_mobile.iter())
+            .copied()
+            .collect()
+    }
+
+    /// Ricostruisce la normalizzazione dalla lista di `parametri`; `None` se la lunghezza non è un multiplo di 4.
+    fn da_parametri(parametri: &[f64]) -> Option<Self> {
+        if !parametri.len().is_multiple_of(4) {
+            return None;
+        }
+        let neuroni = parametri.len() / 4;
+        let blocco = |i: usize| DVector::from_column_slice(&parametri[i * neuroni..(i + 1) * neuroni]);
+        Some(BatchNorm { gamma: blocco(0), beta: blocco(1), media_mobile: blocco(2), varianza_mobile: blocco(3) })
+    }
+}
+
+/// Strategia di inizializzazione dei pesi delle connessioni.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InizializzazionePesi {
+    /// Pesi casuali uniformi in [-1, 1) (default).
+    #[default]
+    Uniforme,
+    /// Matrice identità con un piccolo rumore (±`RUMORE_IDENTITA`) per gli strati quadrati,
+    /// così che lo strato parta vicino alla trasformazione identità (utile nelle reti profonde).
+    /// Gli strati non quadrati ricadono sull'inizializzazione uniforme.
+    Identita,
+    /// Xavier/Glorot: pesi uniformi con deviazione standard `1/sqrt(fan_in)`, dove `fan_in` è il numero
+    /// di neuroni dello strato precedente. Adatta a Sigmoide e Tanh.
+    Xavier,
+    /// He: pesi uniformi con deviazione standard `sqrt(2/fan_in)`. Adatta a ReLU e varianti.
+    He,
+}
+
+/// Regola di aggiornamento dei parametri a partire dai gradienti.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Ottimizzatore {
+    /// Discesa del gradiente pura: `θ -= tasso * gradiente` (default).
+    #[default]
+    SGD,
+    /// Momentum classico: `v = beta * v + tasso * gradiente`, `θ -= v`.
+    Momentum { beta: f64 },
+    /// Adam: medie mobili del gradiente (momento primo) e del suo quadrato (momento secondo), con la correzione
+    /// del bias iniziale, `θ -= tasso * m̂ / (sqrt(v̂) + epsilon)`.
+    Adam { beta1: f64, beta2: f64, epsilon: f64 },
+}
+
+impl Ottimizzatore {
+    /// Adam con i parametri consigliati in letteratura (`beta1` 0.9, `beta2` 0.999, `epsilon` 1e-8).
+    pub fn adam() -> Self {
+        Ottimizzatore::Adam { beta1: 0.9, beta2: 0.999, epsilon: 1e-8 }
+    }
+
+    /// Nome abbreviato dell'ottimizzatore (es. per il registro degli esperimenti).
+    pub fn sigla(&self) -> &str {
+        match self {
+            Ottimizzatore::SGD => "SGD",
+            Ottimizzatore::Momentum { .. } => "Momentum",
+            Ottimizzatore::Adam { .. } => "Adam",
+        }
+    }
+
+    /// Parametri numerici dell'ottimizzatore nell'ordine della dichiarazione (nessuno per SGD).
+    pub fn parametri(&self) -> Vec<f64> {
+        match *self {
+            Ottimizzatore::SGD => vec![],
+            Ottimizzatore::Momentum { beta } => vec![beta],
+            Ottimizzatore::Adam { beta1, beta2, epsilon } => vec![beta1, beta2, epsilon],
+        }
+    }
+
+    /// Ricostruisce l'ottimizzatore dalla sigla e dai parametri (vedi `sigla` e `parametri`).
+    pub fn da_sigla(sigla: &str, parametri: &[f64]) -> Option<Self> {
+        match (sigla, parametri) {
+            ("SGD", []) => Some(Ottimizzatore::SGD),
+            ("Momentum", &[beta]) => Some(Ottimizzatore::Momentum { beta }),
+            ("Adam", &[beta1, beta2, epsilon]) => Some(Ottimizzatore::Adam { beta1, beta2, epsilon }),
+            _ => None,
+        }
+    }
+
+    /// Calcola lo spostamento (da sottrarre) di un gruppo di parametri, aggiornando i momenti accumulati.
+    ///
+    /// # Argomenti
+    ///
+    /// * `tasso` - Il tasso di apprendimento.
+    /// * `passi` - Numero del passo corrente, da 1, per la correzione del bias di Adam.
+    /// * `gradiente` - Il gradiente della perdita rispetto ai parametri.
+    /// * `primo` - Velocità (Momentum) o momento primo (Adam).
+    /// * `secondo` - Momento secondo (solo Adam).
+    /// * `passo` - Lo spostamento calcolato.
+    fn passo(&self, tasso: f64, passi: i32, gradiente: &[f64], primo: &mut [f64], secondo: &mut [f64], passo: &mut [f64]) {
+        match *self {
+            Ottimizzatore::SGD => {
+                for (p, g) in passo.iter_mut().zip(gradiente) {
+                    *p = tasso * g;
+                }
+            }
+            Ottimizzatore::Momentum { beta } => {
+                for ((p, v), g) in passo.iter_mut().zip(primo.iter_mut()).zip(gradiente) {
+                    *v = beta * *v + tasso * g;
+                    *p = *v;
+                }
+            }
+            Ottimizzatore::Adam { beta1, beta2, epsilon } => {
+                let correzione1 = 1.0 - beta1.powi(passi);
+                let correzione2 = 1.0 - beta2.powi(passi);
+                for (((p, m), v), g) in passo.iter_mut().zip(primo.iter_mut()).zip(secondo.iter_mut()).zip(gradiente) {
+                    *m = beta1 * *m + (1.0 - beta1) * g;
+                    *v = beta2 * *v + (1.0 - beta2) * g * g;
+                    *p = tasso * (*m / correzione1) / ((*v / correzione2).sqrt() + epsilon);
+                }
+            }
+        }
+    }
+}
+
+/// Criterio con cui `ReteNeurale::addestra_multi_sorgente` ripartisce gli esempi di ogni epoca tra le sorgenti.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BilanciamentoSorgenti {
+    /// Ogni sorgente contribuisce lo stesso numero di esempi, indipendentemente dalla sua dimensione (default):
+    /// le sorgenti piccole vengono ripetute, quelle grandi sottocampionate.
+    #[default]
+    Uguale,
+    /// Ogni sorgente contribuisce in proporzione alla sua dimensione (tutti i suoi esempi una volta per epoca).
+    Proporzionale,
+}
+
+/// Opzioni dell'addestramento per epoche (vedi `ReteNeurale::addestra_epoche`).
+/// Tutte le opzioni sono disattivate di default.
+#[derive(Default)]
+pub struct OpzioniAddestramento {
+    /// Tempo massimo di addestramento: superato il limite si interrompe alla fine dell'epoca in corso.
+    pub durata_massima: Option<Duration>,
+    /// Seme del generatore casuale della rete: se impostato, tutte le scelte casuali dell'addestramento
+    /// derivano da questo seme e due esecuzioni a partire dalla stessa rete producono pesi identici.
+    pub seme: Option<u64>,
+    /// Salvataggio del modello migliore secondo una metrica calcolata alla fine di ogni epoca.
+    pub checkpoint: Option<Checkpoint>,
+    /// Annullamento delle epoche che peggiorano la perdita di addestramento, con riduzione del tasso di apprendimento.
+    /// Rende l'addestramento robusto a tassi troppo alti (una line-search grezza).
+    pub rollback: Option<Rollback>,
+    /// Scheduling del tasso di apprendimento: all'inizio di ogni epoca il tasso della rete viene sostituito
+    /// con quello dello scheduler per il numero di epoca (da 0), moltiplicato per le riduzioni accumulate dal rollback.
+    pub scheduler: Option<Box<dyn SchedulerApprendimento>>,
+    /// Mescola l'ordine degli esempi a ogni epoca con il generatore casuale della rete (riproducibile con `seme`),
+    /// evitando il bias dovuto a un ordine di visita fisso.
+    pub mescola: bool,
+    /// Callback di progresso chiamato alla fine di ogni epoca con il numero di epoca (da 0) e la perdita
+    /// di addestramento (dopo l'eventuale rollback), ad esempio per stampare una barra di avanzamento.
+    /// Se restituisce `false` l'addestramento si ferma dopo quell'epoca.
+    pub progresso: Option<Box<dyn FnMut(usize, f64) -> bool>>,
+    /// Flag di interruzione cooperativa, controllato all'inizio di ogni epoca: quando viene impostato a `true`
+    /// (da un altro thread o da un handler di segnale) l'addestramento si ferma prima di iniziare l'epoca successiva.
+    pub interruzione: Option<Arc<AtomicBool>>,
+    /// Intercetta Ctrl-C (SIGINT) durante l'addestramento: invece di terminare il processo,
+    /// l'addestramento si ferma in modo pulito al termine dell'epoca corrente. Lo stato raggiunto
+    /// (pesi, bias e ottimizzatore) resta nella rete, che il chiamante salva al ritorno, ad esempio
+    /// con `salva_pesi_txt`, per riprendere in seguito. L'handler originale viene ripristinato al termine.
+    #[cfg(all(unix, feature = "ctrlc"))]
+    pub interrompi_con_ctrl_c: bool
+}
+
+/// Checkpoint del modello migliore durante `ReteNeurale::addestra_epoche`: alla fine di ogni epoca
+/// viene calcolata la `metrica` (più alta = migliore) e, se migliora il massimo raggiunto finora,
+/// la rete viene salvata in `file_path` con `salva_pesi_txt`.
+///
+/// La metrica è una closure qualsiasi sulla rete, per cui può combinare più misure,
+/// ad esempio `0.7 * accuratezza - 0.3 * perdita` su un set di validazione.
+///
+/// # Esempio
+/// ```
+/// let checkpoint = Checkpoint {
+///     file_path: "migliore.txt".to_string(),
+///     metrica: Box::new(move |rete: &ReteNeurale| -rete.perdita_media(&dati_validazione)),
+/// };
+/// ```
+pub struct Checkpoint {
+    pub file_path: String,
+    pub metrica: Box<dyn FnMut(&ReteNeurale) -> f64>
+}
+
+/// Rollback di `ReteNeurale::addestra_epoche`: se alla fine di un'epoca la perdita sul set di addestramento
+/// supera quella dell'epoca precedente di oltre `tolleranza` (relativa, es. `0.01` = 1%), pesi, bias,
+/// batch normalization e stato dell'ottimizzatore tornano a quelli dell'epoca precedente e il tasso
+/// di apprendimento viene moltiplicato per `riduzione` per il resto dell'addestramento.
+///
+/// # Esempio
+/// ```
+/// let opzioni = OpzioniAddestramento {
+///     rollback: Some(Rollback { tolleranza: 0.01, ..Default::default() }),
+///     ..Default::default()
+/// };
+/// rete.addestra_epoche(&dati_addestramento, 500, opzioni)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rollback {
+    pub tolleranza: f64,
+    pub riduzione: f64
+}
+
+/// Nessuna tolleranza, tasso dimezzato a ogni rollback.
+impl Default for Rollback {
+    fn default() -> Self {
+        Rollback { tolleranza: 0.0, riduzione: 0.5 }
+    }
+}
+
+/// Tasso di apprendimento in funzione dell'epoca, per `OpzioniAddestramento::scheduler`:
+/// tipicamente un tasso alto nelle prime epoche e più basso per la convergenza fine.
+///
+/// # Esempio
+/// ```
+/// let opzioni = OpzioniAddestramento {
+///     scheduler: Some(Box::new(DecadimentoEsponenziale { iniziale: 0.1, gamma: 0.99 })),
+///     ..Default::default()
+/// };
+/// rete.addestra_epoche(&dati_addestramento, 500, opzioni)?;
+/// ```
+pub trait SchedulerApprendimento {
+    /// Il tasso di apprendimento dell'epoca indicata (da 0).
+    fn tasso(&self, epoca: usize) -> f64;
+}
+
+/// Tasso costante.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Costante {
+    pub tasso: f64
+}
+
+impl SchedulerApprendimento for Costante {
+    fn tasso(&self, _epoca: usize) -> f64 {
+        self.tasso
+    }
+}
+
+/// Decadimento esponenziale: `iniziale * gamma^epoca`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecadimentoEsponenziale {
+    pub iniziale: f64,
+    pub gamma: f64
+}
+
+impl SchedulerApprendimento for DecadimentoEsponenziale {
+    fn tasso(&self, epoca: usize) -> f64 {
+        self.iniziale * self.gamma.powf(epoca as f64)
+    }
+}
+
+/// Decadimento a gradini: il tasso si moltiplica per `fattore` ogni `step` epoche,
+/// `iniziale * fattore^(epoca / step)` (costante se `step` è 0).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecadimentoAGradini {
+    pub iniziale: f64,
+    pub step: usize,
+    pub fattore: f64
+}
+
+impl SchedulerApprendimento for DecadimentoAGradini {
+    fn tasso(&self, epoca: usize) -> f64 {
+        let gradini = epoca.checked_div(self.step).unwrap_or(0);
+        self.iniziale * self.fattore.powf(gradini as f64)
+    }
+}
+
+/// Gestione di Ctrl-C (SIGINT) per l'interruzione pulita dell'addestramento (feature `ctrlc`, solo Unix).
+#[cfg(all(unix, feature = "ctrlc"))]
+mod ctrl_c {
+    use std::io;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Impostato dall'handler del segnale, letto dal ciclo di addestramento tra un'epoca e l'altra.
+    static INTERROTTO: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn gestore(_segnale: libc::c_int) {
+        INTERROTTO.store(true, Ordering::SeqCst);
+    }
+
+    /// Handler di SIGINT installato per la durata dell'addestramento: `ripristina` rimette l'handler
+    /// precedente riportando l'eventuale errore, il drop lo fa comunque se non è già stato fatto.
+    pub struct GestoreCtrlC {
+        /// Handler sostituito all'installazione, `None` una volta ripristinato.
+        precedente: Option<libc::sigaction>
+    }
+
+    impl GestoreCtrlC {
+        /// Installa l'handler, oppure restituisce l'errore di sigaction lasciando invariato quello attuale.
+        pub fn installa() -> io::Result<Self> {
+            INTERROTTO.store(false, Ordering::SeqCst);
+            // SAFETY: le strutture sono inizializzate a zero come richiesto da sigaction
+            // e l'handler esegue solo una scrittura atomica, operazione sicura in un handler di segnale.
+            unsafe {
+                let mut nuova: libc::sigaction = std::mem::zeroed();
+                nuova.sa_sigaction = gestore as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                libc::sigemptyset(&mut nuova.sa_mask);
+                let mut precedente: libc::sigaction = std::mem::zeroed();
+                if libc::sigaction(libc::SIGINT, &nuova, &mut precedente) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(GestoreCtrlC { precedente: Some(precedente) })
+            }
+        }
+
+        pub fn interrotto(&self) -> bool {
+            INTERROTTO.load(Ordering::SeqCst)
+        }
+
+        /// Ripristina l'handler precedente, restituendo l'errore di sigaction se fallisce.
+        pub fn ripristina(mut self) -> io::Result<()> {
+            self.ripristina_precedente()
+        }
+
+        fn ripristina_precedente(&mut self) -> io::Result<()> {
+            if let Some(precedente) = self.precedente.take() {
+                // SAFETY: `precedente` è l'handler restituito da sigaction all'installazione.
+                if unsafe { libc::sigaction(libc::SIGINT, &precedente, std::ptr::null_mut()) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for GestoreCtrlC {
+        fn drop(&mut self) {
+            // nel drop (uscite anticipate dall'addestramento) non c'è modo di riportare l'errore
+            let _ = self.ripristina_precedente();
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use super::super::{InputAddestramento, Nessuna, OpzioniAddestramento, ReteNeurale, Sigmoide, Strato};
+        use std::sync::{Arc, Mutex};
+
+        /// L'handler di SIGINT è globale al processo: i test che lo installano non devono sovrapporsi.
+        static SIGINT_IN_USO: Mutex<()> = Mutex::new(());
+
+        #[test]
+        fn ctrl_c_ferma_l_addestramento_a_fine_epoca() {
+            let _sigint = SIGINT_IN_USO.lock().unwrap();
+            let mut rete = ReteNeurale::nuova_con_seme(vec![
+                Strato { neuroni: 2, funzione_attivazione: Arc::new(Nessuna) },
+                Strato { neuroni: 1, funzione_attivazione: Arc::new(Sigmoide) },
+            ], 0.5, 1);
+            let dati = [InputAddestramento { input: vec![1.0, 0.0], output: vec![1.0] }];
+            let opzioni = OpzioniAddestramento {
+                interrompi_con_ctrl_c: true,
+                // simula il segnale durante la terza epoca, come farebbe l'handler
+                progresso: Some(Box::new(|epoca, _| {
+                    if epoca == 2 {
+                        INTERROTTO.store(true, Ordering::SeqCst);
+                    }
+                    true
+                })),
+                ..Default::default()
+            };
+            assert_eq!(rete.addestra_epoche(&dati, 1000, opzioni).unwrap().len(), 3);
+
+            // un nuovo addestramento riparte con il flag azzerato
+            let opzioni = OpzioniAddestramento { interrompi_con_ctrl_c: true, ..Default::default() };
+            assert_eq!(rete.addestra_epoche(&dati, 5, opzioni).unwrap().len(), 5);
+        }
+
+        #[test]
+        fn ripristina_l_handler_precedente() {
+            let _sigint = SIGINT_IN_USO.lock().unwrap();
+            let leggi = || {
+                // SAFETY: con un'azione nulla sigaction si limita a leggere l'handler attuale.
+                unsafe {
+                    let mut attuale: libc::sigaction = std::mem::zeroed();
+                    assert_eq!(libc::sigaction(libc::SIGINT, std::ptr::null(), &mut attuale), 0);
+                    attuale.sa_sigaction
+                }
+            };
+            let originale = leggi();
+            let installato = GestoreCtrlC::installa().unwrap();
+            assert_eq!(leggi(), gestore as extern "C" fn(libc::c_int) as libc::sighandler_t);
+            installato.ripristina().unwrap();
+            assert_eq!(leggi(), originale);
+        }
+    }
+}
+
+/// Esito di `diagnostica_convergenza` sull'andamento della perdita nelle ultime epoche.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Diagnostica {
+    /// La perdita sta diminuendo.
+    Convergente,
+    /// La perdita sta aumentando oppure è diventata NaN/infinita.
+    Divergente,
+    /// La perdita sale e scende senza una tendenza netta (spesso tasso di apprendimento troppo alto).
+    Oscillante,
+    /// La perdita è praticamente costante.
+    Plateau,
+    /// Lo storico ha meno di due valori: non è possibile stabilire una tendenza.
+    DatiInsufficienti,
+}
+
+/// Statistiche dei gradienti di uno strato di connessioni per un singolo passo di retropropagazione.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatGrad {
+    /// Norma euclidea (di Frobenius) del gradiente.
+    pub norma: f64,
+    pub min: f64,
+    pub max: f64,
+    pub media: f64,
+}
+
+/// Metriche di `ReteNeurale::valuta` su un insieme di esempi, calcolate in un'unica passata.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Valutazione {
+    /// Perdita media con la funzione di perdita della rete.
+    pub perdita_media: f64,
+    /// Frazione di esempi classificati correttamente, come `ReteNeurale::accuratezza` con soglia 0.5.
+    pub accuratezza: f64,
+    pub numero_esempi: usize,
+}
+
+impl Display for Valutazione {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "esempi: {}, perdita media: {:.6}, accuratezza: {:.2}%",
+            self.numero_esempi, self.perdita_media, 100.0 * self.accuratezza)
+    }
+}
+
+/// Gradienti della perdita rispetto ai parametri della rete, per ogni strato di connessioni.
+#[derive(Debug, Clone)]
+struct Gradienti {
+    pesi: Vec<DMatrix<f64>>,
+    bias: Vec<DVector<f64>>
+}
+
+impl Gradienti {
+    /// Gradienti nulli con le dimensioni dei pesi e dei bias indicati.
+    fn zeri(strati: &[DMatrix<f64>], bias: &[DVector<f64>]) -> Self {
+        Gradienti {
+            pesi: strati.iter().map(|pesi| DMatrix::zeros(pesi.nrows(), pesi.ncols())).collect(),
+            bias: bias.iter().map(|bias| DVector::zeros(bias.len())).collect()
+        }
+    }
+
+    /// Somma in-place i gradienti di un altro passo (stesse dimensioni).
+    fn accumula(&mut self, altri: &Gradienti) {
+        for (gradiente, altro) in self.pesi.iter_mut().zip(altri.pesi.iter()) {
+            *gradiente += altro;
+        }
+        for (gradiente, altro) in self.bias.iter_mut().zip(altri.bias.iter()) {
+            *gradiente += altro;
+        }
+    }
+
+    /// Numero totale di valori, pesi e bias, contenuti nei gradienti.
+    fn numero_valori(&self) -> usize {
+        self.pesi.iter().map(|pesi| pesi.len()).sum::<usize>() + self.bias.iter().map(|bias| bias.len()).sum::<usize>()
+    }
+
+    /// Copia dei gradienti in cui, per ogni strato, la norma congiunta dei gradienti di pesi e bias
+    /// è limitata a `massimo` riscalandoli (la direzione non cambia).
+    fn limitati(&self, massimo: f64) -> Gradienti {
+        let mut limitati = self.clone();
+        for (pesi, bias) in limitati.pesi.iter_mut().zip(limitati.bias.iter_mut()) {
+            let norma = (pesi.norm_squared() + bias.norm_squared()).sqrt();
+            if norma > massimo {
+                *pesi *= massimo / norma;
+                *bias *= massimo / norma;
+            }
+        }
+        limitati
+    }
+
+    /// Moltiplica in-place tutti i gradienti per un fattore.
+    fn scala(&mut self, fattore: f64) {
+        for gradiente in self.pesi.iter_mut() {
+            *gradiente *= fattore;
+        }
+        for gradiente in self.bias.iter_mut() {
+            *gradiente *= fattore;
+        }
+    }
+}
+
+/// Valori di ogni strato di neuroni calcolati dalla propagazione in avanti, dall'input all'output:
+/// le pre-attivazioni `z = W·a + b` (dopo l'eventuale batch normalization) e le uscite `f(z)`.
+/// Per lo strato di input entrambe coincidono con l'input.
+struct Propagazione {
+    pre_attivazioni: Vec<DVector<f64>>,
+    uscite: Vec<DVector<f64>>,
+}
+
+/// Stato accumulato dall'ottimizzatore tra un passo e l'altro, con le dimensioni dei parametri della rete.
+#[derive(Debug, Clone)]
+struct StatoOttimizzatore {
+    primo: Gradienti,       // Velocità (Momentum) o momento primo (Adam)
+    secondo: Gradienti,     // Momento secondo (Adam)
+    primo_batch_norm: Vec<DVector<f64>>,    // Momento primo di `γ` e `β` (concatenati) per strato, vuoto senza batch normalization
+    secondo_batch_norm: Vec<DVector<f64>>,  // Momento secondo di `γ` e `β`
+    passi: i32              // Passi di aggiornamento eseguiti
+}
+
+/// Copia di tutto ciò che l'addestramento modifica nella rete (a parte il tasso e il generatore casuale),
+/// per annullare un'epoca con il rollback.
+struct StatoAddestrabile {
+    strati: Vec<DMatrix<f64>>,
+    bias: Vec<DVector<f64>>,
+    batch_norm: Vec<Option<BatchNorm>>,
+    stato_ottimizzatore: Option<StatoOttimizzatore>
+}
+
+/// Buffer preallocati per l'inferenza ripetuta con `ReteNeurale::elabora_con_contesto`, senza allocazioni
+/// a ogni chiamata. La rete non viene modificata dall'inferenza, per cui più thread possono condividerla
+/// usando ciascuno il proprio contesto.
+///
+/// # Esempio
+/// ```
+/// let mut contesto = ContestoInferenza::per_rete(&rete);
+/// for input in richieste.iter() {
+///     let uscita = rete.elabora_con_contesto(&mut contesto, input);
+///     println!("{:?}", uscita);
+/// }
+/// ```
+pub struct ContestoInferenza {
+    attivazioni: Vec<DVector<f64>>
+}
+
+impl ContestoInferenza {
+    /// Crea un contesto con un buffer per strato, dimensionato sugli strati della rete.
+    pub fn per_rete(rete: &ReteNeurale) -> Self {
+        ContestoInferenza {
+            attivazioni: rete.strati().iter().map(|&neuroni| DVector::zeros(neuroni)).collect()
+        }
+    }
+}
+
+/// Prima differenza trovata tra due reti con architetture incompatibili (vedi `ReteNeurale::compatibile_con`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncompatibilitaRete {
+    /// Le reti hanno un numero diverso di strati.
+    NumeroStrati { atteso: usize, trovato: usize },
+    /// Uno strato ha un numero diverso di neuroni.
+    DimensioneStrato { strato: usize, attesa: usize, trovata: usize },
+    /// Le reti hanno un numero diverso di funzioni di attivazione.
+    NumeroFunzioniAttivazione { atteso: usize, trovato: usize },
+    /// Una funzione di attivazione (o il suo parametro) è diversa.
+    FunzioneAttivazione { indice: usize, attesa: String, trovata: String },
+}
+
+impl Display for IncompatibilitaRete {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IncompatibilitaRete::NumeroStrati { atteso, trovato } =>
+                write!(f, "numero di strati diverso: attesi {}, trovati {}", atteso, trovato),
+            IncompatibilitaRete::DimensioneStrato { strato, attesa, trovata } =>
+                write!(f, "strato [{}] con numero di neuroni diverso: attesi {}, trovati {}", strato, attesa, trovata),
+            IncompatibilitaRete::NumeroFunzioniAttivazione { atteso, trovato } =>
+                write!(f, "numero di funzioni di attivazione diverso: attese {}, trovate {}", atteso, trovato),
+            IncompatibilitaRete::FunzioneAttivazione { indice, attesa, trovata } =>
+                write!(f, "funzione di attivazione [{}] diversa: attesa {}, trovata {}", indice, attesa, trovata),
+        }
+    }
+}
+
+impl std::error::Error for IncompatibilitaRete {}
+
+/// Errori delle operazioni sulla rete: lettura da file e addestramento.
+#[derive(Debug)]
+pub enum ErroreRete {
+    /// Errore di accesso al file.
+    Io(Error),
+    /// Riga che non rispetta il formato atteso.
+    RigaMalformata { riga: usize, contenuto: String },
+    /// Valore che non è un numero valido.
+    ValoreNonNumerico { riga: usize, valore: String },
+    /// Dati incoerenti con la struttura della rete (dimensioni, numero di pesi o di bias).
+    StrutturaIncoerente(String),
+    /// Matrice dei pesi con dimensioni (righe × colonne) diverse da quelle dello strato di connessioni.
+    DimensioniPesi { strato: usize, attese: (usize, usize), trovate: (usize, usize) },
+    /// Sigla sconosciuta di una funzione di attivazione, di perdita o di un ottimizzatore.
+    SiglaSconosciuta(String),
+    /// L'addestramento ha prodotto pesi o bias NaN o infiniti nello strato di connessioni indicato.
+    Divergenza { strato: usize },
+}
+
+impl Display for ErroreRete {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ErroreRete::Io(errore) => write!(f, "errore di accesso al file: {}", errore),
+            ErroreRete::RigaMalformata { riga, contenuto } =>
+                write!(f, "riga {} malformata: '{}'", riga, contenuto),
+            ErroreRete::ValoreNonNumerico { riga, valore } =>
+                write!(f, "riga {}: valore non numerico '{}'", riga, valore),
+            ErroreRete::StrutturaIncoerente(descrizione) => write!(f, "struttura incoerente: {}", descrizione),
+            ErroreRete::DimensioniPesi { strato, attese, trovate } =>
+                write!(f, "pesi dello strato [{}] di dimensioni {}x{}, attese {}x{}", strato, trovate.0, trovate.1, attese.0, attese.1),
+            ErroreRete::SiglaSconosciuta(sigla) => write!(f, "sigla sconosciuta: {}", sigla),
+            ErroreRete::Divergenza { strato } =>
+                write!(f, "addestramento divergente: pesi non finiti nello strato [{}] (tasso di apprendimento troppo alto?)", strato),
+        }
+    }
+}
+
+impl std::error::Error for ErroreRete {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ErroreRete::Io(errore) => Some(errore),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for ErroreRete {
+    fn from(errore: Error) -> Self {
+        ErroreRete::Io(errore)
+    }
+}
+
+/// Per usare `?` nelle funzioni che restituiscono `std::io::Error`.
+impl From<ErroreRete> for Error {
+    fn from(errore: ErroreRete) -> Self {
+        match errore {
+            ErroreRete::Io(errore) => errore,
+            altro => Error::new(ErrorKind::InvalidData, altro),
+        }
+    }
+}
+
 /// Trait per le funzioni di attivazione generiche.
 /// Le funzioni di attivazione devono implementare questi metodi.
 pub trait FunzioneAttivazione  {
@@ -47,11 +905,42 @@ pub trait FunzioneAttivazione  {
     /// Il valore della derivata della funzione di attivazione.
     fn derivata(&self, x: f64) -> f64;
 
+    /// Calcola l'attivazione di un intero strato. Di default applica `attiva` elemento per elemento;
+    /// le funzioni che dipendono da tutto il vettore (come `Softmax`) la ridefiniscono.
+    ///
+    /// # Argomenti
+    ///
+    /// * `v` - Le pre-attivazioni dello strato.
+    fn attiva_vettore(&self, v: &DVector<f64>) -> DVector<f64> {
+        v.map(|x| self.attiva(x))
+    }
+
+    /// Come `attiva_vettore`, sovrascrivendo il vettore senza allocarne uno nuovo.
+    /// Va ridefinita insieme ad `attiva_vettore`.
+    fn attiva_sul_posto(&self, v: &mut DVector<f64>) {
+        v.apply(|x| *x = self.attiva(*x));
+    }
+
+    /// Retropropaga l'errore attraverso l'attivazione di uno strato, moltiplicandolo per la Jacobiana.
+    /// Di default la Jacobiana è diagonale e il prodotto è l'errore moltiplicato elemento per elemento
+    /// per `derivata` calcolata sulle pre-attivazioni; le funzioni la cui derivata si esprime meglio
+    /// con le uscite (come `Softmax`) usano invece `uscita`.
+    ///
+    /// # Argomenti
+    ///
+    /// * `pre_attivazione` - Le pre-attivazioni dello strato (`W·a + b`).
+    /// * `uscita` - Le uscite (attivazioni) dello strato.
+    /// * `errore` - L'errore rispetto alle uscite dello strato.
+    fn retropropaga_vettore(&self, pre_attivazione: &DVector<f64>, _uscita: &DVector<f64>, errore: &DVector<f64>) -> DVector<f64> {
+        errore.component_mul(&pre_attivazione.map(|x| self.derivata(x)))
+    }
+
     /// Metodo per ottenere il nome della funzione di attivazione
     fn nome(&self) -> &str;
     ///  Metodo per ottenere il nome della funzione di attivazione abbreviato
     fn sigla(&self) -> &str;
-    /// Parametro opzionale
+    /// Parametro opzionale (0 se la funzione non ne ha). Quando è diverso da zero i formati di salvataggio
+    /// lo scrivono accanto alla sigla (`Sigla_valore` nei file di testo) e `funzione_da_sigla` lo ripristina.
     fn alfa(&self) -> f64;
 }
 
@@ -159,6 +1048,11 @@ impl FunzioneAttivazione for Tanh {
         1.0 - x.tanh().powi(2)
     }
 
+    fn retropropaga_vettore(&self, _pre_attivazione: &DVector<f64>, uscita: &DVector<f64>, errore: &DVector<f64>) -> DVector<f64> {
+        // dalle uscite y = tanh(x) la derivata è 1 - y², senza ricalcolare tanh
+        errore.component_mul(&uscita.map(|y| 1.0 - y * y))
+    }
+
     fn nome(&self) -> &str {
         "Tangente Iperbolica"
     }
@@ -229,7 +1123,7 @@ impl FunzioneAttivazione for Nessuna {
         x
     }
 
-    fn derivata(&self, x: f64) -> f64 {
+    fn derivata(&self, _x: f64) -> f64 {
         0.0
     }
     fn nome(&self) -> &str {
@@ -254,7 +1148,7 @@ impl FunzioneAttivazione for Lineare {
         x
     }
 
-    fn derivata(&self, x: f64) -> f64 {
+    fn derivata(&self, _x: f64) -> f64 {
         1.0
     }
 
@@ -269,485 +1163,6412 @@ impl FunzioneAttivazione for Lineare {
     }
 }
 
-/*
-    +---------------------------------------------------------------------------------------+
-    |                               Classe Rete Neurale                                     |
-    +---------------------------------------------------------------------------------------+
- */
+/// Ricostruisce una funzione di attivazione dalla sua sigla e dal parametro opzionale `alfa`,
+/// come salvati nei file della rete. Restituisce `None` se la sigla non è riconosciuta.
+fn funzione_da_sigla(sigla: &str, alfa: f64) -> Option<Arc<dyn FunzioneAttivazione + Send + Sync>> {
+    let funzione: Arc<dyn FunzioneAttivazione + Send + Sync> = match sigla {
+        "Sigmoide"  => Arc::new(Sigmoide),
+        "ReLU"      => Arc::new(ReLU),
+        "LeakyReLU" => Arc::new(LeakyReLU { alpha: alfa }),
+        "Tanh"      => Arc::new(Tanh),
+        "Softplus"  => Arc::new(Softplus),
+        "Swish"     => Arc::new(Swish),
+        "Lineare"   => Arc::new(Lineare),
+        "ISRU"      => Arc::new(ISRU { alpha: alfa }),
+        "ELU"       => Arc::new(ELU { alpha: alfa }),
+        "GELU"      => Arc::new(GELU),
+        "Mish"      => Arc::new(Mish),
+        "SELU"      => Arc::new(SELU),
+        "Softmax"   => Arc::new(Softmax),
+        "Null"      => Arc::new(Nessuna),
+        _           => return None,
+    };
+    Some(funzione)
+}
 
-/// Struttura della rete neurale generica che supporta più strati nascosti.
+/// Implementazione della funzione ISRU (Inverse Square Root Unit).
+/// La ISRU `x / sqrt(1 + alpha*x²)` è un'alternativa liscia e limitata alla tanh, economica da calcolare:
+/// mappa i valori nell'intervallo (-1/sqrt(alpha), 1/sqrt(alpha)).
 #[derive(Clone)]
-pub struct ReteNeurale {
-    strati: Vec<DMatrix<f64>>,          // I pesi di ogni strato (organizzati come connessioni tra i livelli)
-    funzioni_attivazione: Vec<Arc<dyn FunzioneAttivazione + Send + Sync>>,  // Le funzioni di attivazione in ordine per strati
-    tasso_apprendimento: f64 ,           // Il tasso di apprendimentox,
-    dimensioni_strati:Vec<usize>
+#[allow(clippy::upper_case_acronyms)]
+pub struct ISRU {
+    /// Parametro alpha che controlla l'ampiezza dell'intervallo di uscita.
+    pub alpha: f64,
 }
 
-/// Permette la stampa della rete
-/// 
-/// Esempio:
-/// let rete: ReteNeurale<ReLU>;
-/// .....
-/// println!("{rete}");
-impl Display for ReteNeurale {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        let mut testo = String::new();
-        testo += "Pesi Rete Neurale MLP\n";
-        let mut nomi_funz_attivazione = String::new();
-        
-        for funzione_attivazione in self.funzioni_attivazione.clone().into_iter()  {
-            nomi_funz_attivazione += &(funzione_attivazione.nome().to_string()+ "; ");
+impl FunzioneAttivazione for ISRU {
+    fn attiva(&self, x: f64) -> f64 {
+        x / (1.0 + self.alpha * x * x).sqrt()
+    }
+
+    fn derivata(&self, x: f64) -> f64 {
+        (1.0 / (1.0 + self.alpha * x * x).sqrt()).powi(3)
+    }
+
+    fn nome(&self) -> &str {
+        "Inverse Square Root Unit"
+    }
+    fn sigla(&self) -> &str {
+        "ISRU"
+    }
+    fn alfa(&self) -> f64 {
+        self.alpha
+    }
+}
+
+/// Implementazione della funzione ELU (Exponential Linear Unit).
+/// La ELU restituisce `x` per valori positivi e `alpha * (e^x - 1)` altrimenti: a differenza della ReLU
+/// ammette uscite negative (fino a `-alpha`), riducendo lo spostamento della media delle attivazioni.
+#[derive(Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct ELU {
+    /// Parametro alpha, valore di saturazione per i valori negativi (tipicamente 1).
+    pub alpha: f64,
+}
+
+impl FunzioneAttivazione for ELU {
+    fn attiva(&self, x: f64) -> f64 {
+        if x > 0.0 {
+            x
+        } else {
+            self.alpha * x.exp_m1()
         }
-        testo += format!("Funzioni di attivazione per livello: {:?}\n", nomi_funz_attivazione).as_str(); // Stampa il nome della funzione di attivazione
+    }
 
-        let mut i = 0;
-        for strato in &self.strati {
-            testo += format!("\nConnessioni livello [{}] - [{}]\n", i, i + 1).as_str();
-            for riga in strato.row_iter() {
-                let riga_str = riga.iter()
-                    .map(|valore| valore.to_string())
-                    .collect::<Vec<String>>()
-                    .join("\t");
-                testo += riga_str.as_str();
-                testo += "\n";
-            }
-            i += 1;
+    fn derivata(&self, x: f64) -> f64 {
+        if x > 0.0 {
+            1.0
+        } else {
+            self.alpha * x.exp()
         }
-        write!(f, "{}\n", testo)
+    }
+
+    fn nome(&self) -> &str {
+        "Exponential Linear Unit"
+    }
+    fn sigla(&self) -> &str {
+        "ELU"
+    }
+    fn alfa(&self) -> f64 {
+        self.alpha
     }
 }
-impl ReteNeurale {
-    /// Crea una nuova rete neurale con il numero di livelli nascosti specificato.
-    ///
-    /// # Argomenti
-    ///
-    /// * `dimensioni_strati` - Un vettore che specifica il numero di neuroni in ogni strato, incluso input e output.
-    /// 
-    ///     Esempio:
-    ///       let dimensioni_strati = vec![2, 3, 2, 1];  // Input, due livelli nascosti, output
-    /// 
-    /// * `tasso_apprendimento` - Il tasso di apprendimento per l'algoritmo di backpropagation.
-    /// * `funzione_attivazione` - La funzione di attivazione da utilizzare nella rete.
-    pub fn nuova_rete_uniforme(
-        dimensioni_strati: Vec<usize>,
-        tasso_apprendimento: f64,
-        funzione_attivazione:Arc<dyn FunzioneAttivazione + Send + Sync>
-    ) -> Self {
-        let mut rng = rand::thread_rng();
-        let mut strati = Vec::with_capacity(dimensioni_strati.len() - 1);
 
-        for i in 0..dimensioni_strati.len() - 1 {
-            let pesi = DMatrix::from_fn(dimensioni_strati[i + 1], dimensioni_strati[i], |_, _| rng.gen_range(-1.0..1.0));
-            strati.push(pesi);
+/// Implementazione della funzione GELU (Gaussian Error Linear Unit), con l'approssimazione tanh
+/// `0.5 * x * (1 + tanh(sqrt(2/π) * (x + 0.044715 * x³)))`: pesa l'input con la probabilità gaussiana
+/// che sia positivo, una versione liscia della ReLU usata nei modelli moderni.
+#[derive(Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct GELU;
+
+impl GELU {
+    const COEFFICIENTE_CUBICO: f64 = 0.044715;
+
+    /// Argomento della tanh nell'approssimazione.
+    fn argomento(x: f64) -> f64 {
+        (2.0 / std::f64::consts::PI).sqrt() * (x + Self::COEFFICIENTE_CUBICO * x * x * x)
+    }
+}
+
+impl FunzioneAttivazione for GELU {
+    fn attiva(&self, x: f64) -> f64 {
+        0.5 * x * (1.0 + Self::argomento(x).tanh())
+    }
+
+    fn derivata(&self, x: f64) -> f64 {
+        let t = Self::argomento(x).tanh();
+        let derivata_argomento = (2.0 / std::f64::consts::PI).sqrt() * (1.0 + 3.0 * Self::COEFFICIENTE_CUBICO * x * x);
+        0.5 * (1.0 + t) + 0.5 * x * (1.0 - t * t) * derivata_argomento
+    }
+
+    fn nome(&self) -> &str {
+        "Gaussian Error Linear Unit"
+    }
+    fn sigla(&self) -> &str {
+        "GELU"
+    }
+    fn alfa(&self) -> f64 {
+        0.0
+    }
+}
+
+/// Implementazione della funzione Mish `x * tanh(softplus(x))`: liscia e non monotona,
+/// spesso più efficace della ReLU. Il softplus è calcolato in forma stabile, per cui anche
+/// per `x` molto negativi la funzione tende a 0 senza overflow.
+#[derive(Clone)]
+pub struct Mish;
+
+impl Mish {
+    /// `ln(1 + e^x)` senza overflow per `x` grandi né perdita di precisione per `x` molto negativi.
+    fn softplus(x: f64) -> f64 {
+        x.max(0.0) + (-x.abs()).exp().ln_1p()
+    }
+}
+
+impl FunzioneAttivazione for Mish {
+    fn attiva(&self, x: f64) -> f64 {
+        x * Self::softplus(x).tanh()
+    }
+
+    fn derivata(&self, x: f64) -> f64 {
+        let t = Self::softplus(x).tanh();
+        let sigmoide = 1.0 / (1.0 + (-x).exp());
+        t + x * (1.0 - t * t) * sigmoide
+    }
+
+    fn nome(&self) -> &str {
+        "Mish"
+    }
+    fn sigla(&self) -> &str {
+        "Mish"
+    }
+    fn alfa(&self) -> f64 {
+        0.0
+    }
+}
+
+/// Implementazione della funzione SELU (Scaled Exponential Linear Unit), per reti auto-normalizzanti:
+/// `lambda * x` per valori positivi e `lambda * alpha * (e^x - 1)` altrimenti, con le costanti standard
+/// che mantengono media 0 e varianza 1 delle attivazioni attraverso gli strati.
+///
+/// L'auto-normalizzazione vale solo con pesi di varianza `1/fan_in` (inizializzazione LeCun):
+/// in questa libreria corrisponde a `InizializzazionePesi::Xavier`, da usare con
+/// `ReteNeurale::nuova_con_inizializzazione`. Con l'inizializzazione uniforme di default le attivazioni
+/// non restano normalizzate.
+#[derive(Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct SELU;
+
+impl SELU {
+    pub const LAMBDA: f64 = 1.050_700_987_355_480_5;
+    pub const ALPHA: f64 = 1.673_263_242_354_377_3;
+}
+
+impl FunzioneAttivazione for SELU {
+    fn attiva(&self, x: f64) -> f64 {
+        if x > 0.0 {
+            Self::LAMBDA * x
+        } else {
+            Self::LAMBDA * Self::ALPHA * x.exp_m1()
         }
-        let funzioni_attivazione = vec![funzione_attivazione];
-        ReteNeurale {
-            strati,
-            funzioni_attivazione,
-            tasso_apprendimento,
-            dimensioni_strati
+    }
+
+    fn derivata(&self, x: f64) -> f64 {
+        if x > 0.0 {
+            Self::LAMBDA
+        } else {
+            Self::LAMBDA * Self::ALPHA * x.exp()
         }
     }
 
-    /// Crea una nuova rete neurale con il numero di livelli nascosti specificato.
+    fn nome(&self) -> &str {
+        "Scaled Exponential Linear Unit"
+    }
+    fn sigla(&self) -> &str {
+        "SELU"
+    }
+    fn alfa(&self) -> f64 {
+        0.0
+    }
+}
+
+/// Implementazione della funzione Softmax, per lo strato di output nella classificazione multi-classe:
+/// `e^(x_i) / Σ e^(x_j)` trasforma l'intero vettore in una distribuzione di probabilità (valori in (0, 1)
+/// con somma 1). Il calcolo sottrae il massimo prima dell'esponenziale, per cui è stabile anche con input grandi.
+///
+/// Dipende da tutto lo strato, per cui è implementata in `attiva_vettore`. Sul valore isolato `attiva` e `derivata`
+/// trattano la pre-attivazione come un vettore di un elemento, la cui softmax è la costante 1 con derivata 0:
+/// la rete non le usa mai, perché la Jacobiana di uno strato non è diagonale. Nella retropropagazione il prodotto
+/// per la Jacobiana `diag(p) - p pᵀ` è calcolato senza costruirla, e con l'entropia incrociata si riduce a `p - target`.
+#[derive(Clone)]
+pub struct Softmax;
+
+impl FunzioneAttivazione for Softmax {
+    fn attiva(&self, _x: f64) -> f64 {
+        1.0
+    }
+
+    fn derivata(&self, _x: f64) -> f64 {
+        0.0
+    }
+
+    fn attiva_vettore(&self, v: &DVector<f64>) -> DVector<f64> {
+        let mut p = v.clone();
+        self.attiva_sul_posto(&mut p);
+        p
+    }
+
+    fn attiva_sul_posto(&self, v: &mut DVector<f64>) {
+        let massimo = v.max();
+        v.apply(|x| *x = (*x - massimo).exp());
+        let somma = v.sum();
+        *v /= somma;
+    }
+
+    fn retropropaga_vettore(&self, _pre_attivazione: &DVector<f64>, uscita: &DVector<f64>, errore: &DVector<f64>) -> DVector<f64> {
+        // (diag(p) - p pᵀ) e = p ⊙ (e - p·e)
+        let prodotto = uscita.dot(errore);
+        uscita.component_mul(&errore.add_scalar(-prodotto))
+    }
+
+    fn nome(&self) -> &str {
+        "Softmax"
+    }
+    fn sigla(&self) -> &str {
+        "Softmax"
+    }
+    fn alfa(&self) -> f64 {
+        0.0
+    }
+}
+
+/*
+    +---------------------------------------------------------------------------------------+
+    |                               Funzioni di Perdita                                     |
+    +---------------------------------------------------------------------------------------+
+ */
+
+/// Trait per le funzioni di perdita usate in retropropagazione.
+pub trait FunzionePerdita {
+    /// Calcola il valore della perdita.
     ///
     /// # Argomenti
     ///
-    /// * `dimensioni_strati` - Un vettore che specifica il numero di neuroni in ogni strato, incluso input e output.
-    /// 
-    ///     Esempio:
-    ///       let dimensioni_strati = vec![2, 3, 2, 1];  // Input, due livelli nascosti, output
-    /// 
-    /// * `tasso_apprendimento` - Il tasso di apprendimento per l'algoritmo di backpropagation.
-    /// * `funzioni_attivazione` - Lista delle funzioni di attivazione per singoli strati.
-    /// 
-    pub fn nuova( info_strati: Vec<Strato>, tasso_apprendimento: f64 ) -> Self {
-        let mut funzioni_attivazione:Vec<Arc<dyn FunzioneAttivazione + Send + Sync>> = Vec::new();
-        let mut dimensioni_strati= Vec::new();
-        let mut primo_strato = true;
-        for info_strato in info_strati.into_iter() {
-            dimensioni_strati.push(info_strato.neuroni);
-            if primo_strato {
-                primo_strato = false;
-                funzioni_attivazione.push(Arc::new(Nessuna));
-            }else{
-                funzioni_attivazione.push(info_strato.funzione_attivazione);
-            }
+    /// * `previsto` - Il vettore di output della rete.
+    /// * `target` - Il vettore dei valori attesi.
+    ///
+    /// # Ritorna
+    ///
+    /// Il valore della perdita per l'esempio.
+    fn calcola(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> f64;
+
+    /// Calcola il gradiente della perdita rispetto all'output della rete.
+    ///
+    /// # Argomenti
+    ///
+    /// * `previsto` - Il vettore di output della rete.
+    /// * `target` - Il vettore dei valori attesi.
+    ///
+    /// # Ritorna
+    ///
+    /// Un vettore con la stessa dimensione di `previsto`.
+    fn gradiente(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> DVector<f64>;
+
+    /// Metodo per ottenere il nome della funzione di perdita
+    fn nome(&self) -> &str;
+    ///  Metodo per ottenere il nome della funzione di perdita abbreviato
+    fn sigla(&self) -> &str;
+    /// Parametro della funzione di perdita (es. `delta` per Huber), 0 se la perdita non ha parametri
+    fn parametro(&self) -> f64 {
+        0.0
+    }
+}
+
+/// Errore quadratico medio (default della rete).
+/// Per ogni esempio vale `Σ(previsto - target)² / 2n`, con `n` numero di output:
+/// il fattore ½ rende il gradiente `(previsto - target) / n`,
+/// che per un singolo output coincide con la regola di aggiornamento originale.
+#[derive(Clone)]
+pub struct ErroreQuadraticoMedio;
+
+impl FunzionePerdita for ErroreQuadraticoMedio {
+    fn calcola(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> f64 {
+        (previsto - target).norm_squared() / (2.0 * previsto.len() as f64)
+    }
+
+    fn gradiente(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> DVector<f64> {
+        (previsto - target) / previsto.len() as f64
+    }
+    fn nome(&self) -> &str {
+        "Errore Quadratico Medio"
+    }
+    fn sigla(&self) -> &str {
+        "MSE"
+    }
+}
+
+/// Entropia incrociata, per la classificazione.
+/// Con più output (tipicamente dopo una `Softmax`) vale `-Σ target ln(previsto)`; con un solo output
+/// (tipicamente dopo una `Sigmoide`) è l'entropia incrociata binaria `-(t ln p + (1 - t) ln(1 - p))`.
+/// Le probabilità sono limitate a [`EPSILON_LOG`, 1 - `EPSILON_LOG`] per evitare logaritmi infiniti.
+///
+/// Il gradiente restituito è quello esatto rispetto alle probabilità; combinato con la Jacobiana
+/// della Softmax o con la derivata della Sigmoide dà il gradiente `previsto - target` sulle pre-attivazioni,
+/// che non satura quando la rete sbaglia con molta sicurezza.
+#[derive(Clone)]
+pub struct EntropiaIncrociata;
+
+/// Limite inferiore delle probabilità nei logaritmi di `EntropiaIncrociata`.
+pub const EPSILON_LOG: f64 = 1e-12;
+
+impl FunzionePerdita for EntropiaIncrociata {
+    fn calcola(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> f64 {
+        let limita = |p: f64| p.clamp(EPSILON_LOG, 1.0 - EPSILON_LOG);
+        if previsto.len() == 1 {
+            let (p, t) = (limita(previsto[0]), target[0]);
+            return -(t * p.ln() + (1.0 - t) * (1.0 - p).ln());
         }
-        let mut rng = rand::thread_rng();
-        let mut strati = Vec::with_capacity(dimensioni_strati.len() - 1);
+        -previsto.iter().zip(target.iter()).map(|(&p, &t)| t * limita(p).ln()).sum::<f64>()
+    }
 
-        for i in 0..dimensioni_strati.len() - 1 {
-            let pesi = DMatrix::from_fn(dimensioni_strati[i + 1], dimensioni_strati[i], |_, _| rng.gen_range(-1.0..1.0));
-            strati.push(pesi);
+    fn gradiente(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> DVector<f64> {
+        let limita = |p: f64| p.clamp(EPSILON_LOG, 1.0 - EPSILON_LOG);
+        if previsto.len() == 1 {
+            let (p, t) = (limita(previsto[0]), target[0]);
+            return DVector::from_element(1, (p - t) / (p * (1.0 - p)));
         }
-        
-        ReteNeurale {
-            strati,
-            funzioni_attivazione,
-            tasso_apprendimento,
-            dimensioni_strati
+        previsto.zip_map(target, |p, t| -t / limita(p))
+    }
+    fn nome(&self) -> &str {
+        "Entropia Incrociata"
+    }
+    fn sigla(&self) -> &str {
+        "EntropiaIncrociata"
+    }
+}
+
+/// Verosimiglianza gaussiana negativa, per regressione con stima dell'incertezza aleatoria.
+/// La rete deve avere due output per ogni target: la prima metà degli output è la media `μ`,
+/// la seconda metà il logaritmo della varianza `s = ln σ²` (usare `Lineare` sullo strato di output).
+/// Per ogni target la perdita vale `½ (s + (t - μ)² e^(-s))`, a meno di una costante.
+#[derive(Clone)]
+pub struct NLLGaussiana;
+
+impl FunzionePerdita for NLLGaussiana {
+    fn calcola(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> f64 {
+        let k = target.len();
+        let mut perdita = 0.0;
+        for j in 0..k {
+            let (media, log_varianza) = (previsto[j], previsto[k + j]);
+            perdita += 0.5 * (log_varianza + (target[j] - media).powi(2) * (-log_varianza).exp());
+        }
+        perdita / k as f64
+    }
+
+    fn gradiente(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> DVector<f64> {
+        let k = target.len();
+        let mut gradiente = DVector::zeros(previsto.len());
+        for j in 0..k {
+            let (media, log_varianza) = (previsto[j], previsto[k + j]);
+            let precisione = (-log_varianza).exp();
+            gradiente[j] = (media - target[j]) * precisione / k as f64;
+            gradiente[k + j] = 0.5 * (1.0 - (target[j] - media).powi(2) * precisione) / k as f64;
         }
+        gradiente
+    }
+    fn nome(&self) -> &str {
+        "Verosimiglianza Gaussiana Negativa"
+    }
+    fn sigla(&self) -> &str {
+        "NLLGaussiana"
+    }
+}
+
+/// Perdita di Huber, per regressione robusta agli outlier: per ogni output con errore `e = previsto - target`
+/// vale `½ e²` se `|e| <= delta` (come l'errore quadratico) e `delta (|e| - ½ delta)` altrimenti (come l'errore
+/// assoluto), mediata sul numero di output. Il gradiente `e`, limitato a `±delta`, è continuo al confine,
+/// e un errore grande pesa sull'aggiornamento al più quanto un errore pari a `delta`.
+#[derive(Clone)]
+pub struct Huber {
+    pub delta: f64
+}
+
+impl FunzionePerdita for Huber {
+    fn calcola(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> f64 {
+        let somma: f64 = (previsto - target).iter()
+            .map(|e| if e.abs() <= self.delta { 0.5 * e * e } else { self.delta * (e.abs() - 0.5 * self.delta) })
+            .sum();
+        somma / previsto.len() as f64
+    }
+
+    fn gradiente(&self, previsto: &DVector<f64>, target: &DVector<f64>) -> DVector<f64> {
+        (previsto - target).map(|e| e.clamp(-self.delta, self.delta)) / previsto.len() as f64
+    }
+    fn nome(&self) -> &str {
+        "Huber"
+    }
+    fn sigla(&self) -> &str {
+        "Huber"
+    }
+    fn parametro(&self) -> f64 {
+        self.delta
+    }
+}
+
+/// Ricostruisce una funzione di perdita dalla sua sigla e dal suo parametro (ignorato se la perdita non ne ha).
+/// Restituisce `None` se la sigla non è riconosciuta.
+fn perdita_da_sigla(sigla: &str, parametro: f64) -> Option<Arc<dyn FunzionePerdita + Send + Sync>> {
+    let perdita: Arc<dyn FunzionePerdita + Send + Sync> = match sigla {
+        "MSE"           => Arc::new(ErroreQuadraticoMedio),
+        "NLLGaussiana"  => Arc::new(NLLGaussiana),
+        "EntropiaIncrociata" => Arc::new(EntropiaIncrociata),
+        "Huber"         => Arc::new(Huber { delta: parametro }),
+        _               => return None,
+    };
+    Some(perdita)
+}
+
+/// Classe associata a un vettore di output: l'indice del valore massimo (argmax),
+/// oppure 0/1 con soglia 0.5 se l'output ha un solo neurone.
+fn classe_di(output: &[f64]) -> usize {
+    if output.len() == 1 {
+        return usize::from(output[0] >= 0.5);
+    }
+    output.iter()
+        .enumerate()
+        .fold((0, f64::NEG_INFINITY), |(i_max, v_max), (i, &v)| if v > v_max { (i, v) } else { (i_max, v_max) })
+        .0
+}
+
+/// Softmax con temperatura, numericamente stabile (sottrae il massimo prima dell'esponenziale).
+/// Temperature alte appiattiscono la distribuzione, temperature basse la rendono più netta.
+fn softmax(valori: &[f64], temperatura: f64) -> Vec<f64> {
+    let massimo = valori.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let esponenziali: Vec<f64> = valori.iter().map(|v| ((v - massimo) / temperatura).exp()).collect();
+    let somma: f64 = esponenziali.iter().sum();
+    esponenziali.iter().map(|e| e / somma).collect()
+}
+
+/// Conta quanti esempi del set appartengono a ciascuna classe (argmax dell'output atteso),
+/// utile per valutare lo sbilanciamento prima dell'addestramento e per calcolare i pesi per classe.
+///
+/// Il numero di classi è dedotto dalla dimensione dell'output del primo esempio:
+/// un output a singolo neurone è trattato come binario (2 classi, soglia 0.5).
+///
+/// # Esempio
+/// ```
+/// let conteggi = distribuzione_classi(&dati_addestramento);
+/// println!("Esempi per classe: {:?}", conteggi);
+/// ```
+pub fn distribuzione_classi(dati: &[InputAddestramento]) -> Vec<usize> {
+    let num_classi = match dati.first() {
+        Some(set) if set.output.len() == 1 => 2,
+        Some(set) => set.output.len(),
+        None => return vec![],
+    };
+    let mut conteggi = vec![0; num_classi];
+    for set in dati.iter() {
+        let classe = classe_di(&set.output);
+        if classe < num_classi {
+            conteggi[classe] += 1;
+        }
+    }
+    conteggi
+}
+
+/// Classifica l'andamento dell'addestramento a partire dallo storico delle perdite per epoca,
+/// guardando le ultime `FINESTRA_DIAGNOSTICA` epoche:
+/// * un valore NaN o infinito, o una crescita netta della perdita, indica divergenza;
+/// * una variazione complessiva entro `SOGLIA_PLATEAU` (relativa) indica un plateau;
+/// * frequenti cambi di direzione con una variazione netta piccola rispetto a quella totale indicano oscillazione;
+/// * altrimenti la perdita scende e l'addestramento converge.
+///
+/// # Esempio
+/// ```
+/// match diagnostica_convergenza(&storico_perdite) {
+///     Diagnostica::Oscillante => println!("Prova a ridurre il tasso di apprendimento"),
+///     Diagnostica::Plateau => println!("La perdita non migliora più"),
+///     esito => println!("{:?}", esito),
+/// }
+/// ```
+pub fn diagnostica_convergenza(storico: &[f64]) -> Diagnostica {
+    if storico.len() < 2 {
+        return Diagnostica::DatiInsufficienti;
+    }
+    let finestra = &storico[storico.len().saturating_sub(FINESTRA_DIAGNOSTICA)..];
+    if finestra.iter().any(|v| !v.is_finite()) {
+        return Diagnostica::Divergente;
+    }
+
+    let differenze: Vec<f64> = finestra.windows(2).map(|coppia| coppia[1] - coppia[0]).collect();
+    let variazione_netta = finestra[finestra.len() - 1] - finestra[0];
+    let variazione_totale: f64 = differenze.iter().map(|d| d.abs()).sum();
+    let scala = finestra.iter().map(|v| v.abs()).fold(0.0, f64::max).max(f64::EPSILON);
+
+    if variazione_totale / scala <= SOGLIA_PLATEAU {
+        return Diagnostica::Plateau;
+    }
+    let cambi_direzione = differenze.windows(2).filter(|coppia| coppia[0] * coppia[1] < 0.0).count();
+    if differenze.len() >= 2
+        && cambi_direzione * 2 >= differenze.len() - 1
+        && variazione_netta.abs() * 2.0 < variazione_totale
+    {
+        return Diagnostica::Oscillante;
+    }
+    if variazione_netta < 0.0 {
+        Diagnostica::Convergente
+    } else {
+        Diagnostica::Divergente
+    }
+}
+
+/// Lettura sequenziale di un buffer binario little-endian, con errore in caso di dati troncati.
+struct LettoreByte<'a> {
+    byte: &'a [u8],
+    posizione: usize
+}
+
+impl<'a> LettoreByte<'a> {
+    fn leggi(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let fine = self.posizione.checked_add(n)
+            .filter(|&fine| fine <= self.byte.len())
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "dati binari troncati"))?;
+        let letti = &self.byte[self.posizione..fine];
+        self.posizione = fine;
+        Ok(letti)
+    }
+
+    fn leggi_u16(&mut self) -> Result<u16, Error> {
+        let mut buffer = [0u8; 2];
+        buffer.copy_from_slice(self.leggi(2)?);
+        Ok(u16::from_le_bytes(buffer))
+    }
+
+    fn leggi_u32(&mut self) -> Result<u32, Error> {
+        let mut buffer = [0u8; 4];
+        buffer.copy_from_slice(self.leggi(4)?);
+        Ok(u32::from_le_bytes(buffer))
+    }
+
+    fn leggi_f64(&mut self) -> Result<f64, Error> {
+        let mut buffer = [0u8; 8];
+        buffer.copy_from_slice(self.leggi(8)?);
+        Ok(f64::from_le_bytes(buffer))
+    }
+
+    /// Legge `n` valori `f64` consecutivi, verificando prima che i dati siano sufficienti
+    /// (così una dimensione corrotta non provoca allocazioni enormi).
+    fn leggi_f64_multipli(&mut self, n: usize) -> Result<Vec<f64>, Error> {
+        let lunghezza = n.checked_mul(8)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "dati binari troncati"))?;
+        Ok(self.leggi(lunghezza)?
+            .chunks_exact(8)
+            .map(|valore| {
+                let mut buffer = [0u8; 8];
+                buffer.copy_from_slice(valore);
+                f64::from_le_bytes(buffer)
+            })
+            .collect())
+    }
+}
+
+/// Compressione delle sequenze di byte nulli: ogni sequenza diventa `0x00` seguito dalla lunghezza (1..=255),
+/// gli altri byte sono copiati invariati.
+fn comprimi_zeri(byte: &[u8]) -> Vec<u8> {
+    let mut compressi = Vec::with_capacity(byte.len());
+    let mut i = 0;
+    while i < byte.len() {
+        if byte[i] == 0 {
+            let mut lunghezza = 0;
+            while i < byte.len() && byte[i] == 0 && lunghezza < u8::MAX {
+                lunghezza += 1;
+                i += 1;
+            }
+            compressi.push(0);
+            compressi.push(lunghezza);
+        } else {
+            compressi.push(byte[i]);
+            i += 1;
+        }
+    }
+    compressi
+}
+
+/// Inverso di `comprimi_zeri`.
+fn decomprimi_zeri(compressi: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut byte = Vec::with_capacity(compressi.len());
+    let mut i = 0;
+    while i < compressi.len() {
+        if compressi[i] == 0 {
+            let lunghezza = *compressi.get(i + 1)
+                .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "dati compressi troncati"))?;
+            byte.resize(byte.len() + lunghezza as usize, 0);
+            i += 2;
+        } else {
+            byte.push(compressi[i]);
+            i += 1;
+        }
+    }
+    Ok(byte)
+}
+
+/// Crea (o tronca) il file `file_path`, creando prima le directory intermedie mancanti.
+pub(crate) fn crea_file(file_path: &str) -> Result<File, Error> {
+    let percorso = std::path::Path::new(file_path);
+    if percorso.is_dir() {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("{} è una directory, non un file", file_path)));
+    }
+    if let Some(directory) = percorso.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+        std::fs::create_dir_all(directory)?;
+    }
+    File::create(percorso)
+}
+
+/// Feature hashing: mappa un insieme di feature categoriche (anche ad alta cardinalità) su un vettore
+/// di dimensione fissa, senza bisogno di un vocabolario esplicito. Ogni valore è assegnato a una
+/// posizione tramite hash (FNV-1a, stabile tra esecuzioni e piattaforme) e contribuisce con segno ±1
+/// ricavato da un altro bit dell'hash, così che le collisioni tendano a compensarsi.
+///
+/// # Argomenti
+///
+/// * `valori_categorici` - Le feature categoriche dell'esempio (es. `"colore=rosso"`).
+/// * `dimensione` - Dimensione del vettore prodotto, pari al numero di neuroni dello strato di input.
+///
+/// # Esempio
+/// ```
+/// let input = hash_features(&["colore=rosso", "taglia=M"], 16);
+/// let uscita = rete.elabora(input);
+/// ```
+pub fn hash_features(valori_categorici: &[&str], dimensione: usize) -> Vec<f64> {
+    let mut vettore = vec![0.0; dimensione];
+    if dimensione == 0 {
+        return vettore;
+    }
+    for valore in valori_categorici.iter() {
+        let hash = valore.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        let indice = (hash % dimensione as u64) as usize;
+        let segno = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vettore[indice] += segno;
+    }
+    vettore
+}
+
+/*
+    +---------------------------------------------------------------------------------------+
+    |                               Classe Rete Neurale                                     |
+    +---------------------------------------------------------------------------------------+
+ */
+
+/// Struttura della rete neurale generica che supporta più strati nascosti.
+pub struct ReteNeurale {
+    strati: Vec<DMatrix<f64>>,          // I pesi di ogni strato (organizzati come connessioni tra i livelli)
+    bias: Vec<DVector<f64>>,            // I bias dei neuroni di ogni strato dopo l'input (uno per matrice dei pesi)
+    funzioni_attivazione: Vec<Arc<dyn FunzioneAttivazione + Send + Sync>>,  // Le funzioni di attivazione in ordine per strati
+    tasso_apprendimento: f64 ,           // Il tasso di apprendimentox,
+    dimensioni_strati:Vec<usize>,
+    strati_congelati: Vec<bool>,        // Strati (matrici dei pesi) esclusi dall'aggiornamento in retropropagazione
+    traccia: bool,                      // Se attivo la propagazione in avanti registra le statistiche di ogni strato
+    weight_norm: bool,                  // Aggiornamento dei pesi con weight normalization (direzione e magnitudine separate)
+    dropconnect: Vec<f64>,              // Probabilità di azzerare ogni peso in addestramento, per matrice dei pesi
+    ottimizzatore: Ottimizzatore,       // La regola di aggiornamento dei parametri
+    lambda_l1: f64,                     // Coefficiente della regolarizzazione L1 sui pesi (0 = disattivata)
+    clip_gradiente: Option<f64>,        // Norma massima del gradiente di ogni strato
+    stato_ottimizzatore: Option<StatoOttimizzatore>,    // Momenti dell'ottimizzatore, creati al primo passo
+    perdita: Arc<dyn FunzionePerdita + Send + Sync>,    // La funzione di perdita minimizzata in addestramento
+    rng: StdRng,                        // Generatore casuale usato per tutte le scelte casuali dell'addestramento
+    batch_riferimento: usize,           // Dimensione del batch a cui si riferisce il tasso di apprendimento
+    cifre_significative: Option<usize>, // Cifre significative dei valori nei file di testo (None = rappresentazione più breve)
+    batch_norm: Vec<Option<BatchNorm>>, // Batch normalization della pre-attivazione, per matrice dei pesi
+    registro_traccia: Mutex<Vec<String>>  // Righe di traccia in attesa di `preleva_traccia`, proprie di ogni copia
+}
+
+/// Copia profonda della rete. Il registro di traccia non è condiviso: la copia ne ha uno nuovo e vuoto,
+/// per cui la traccia della copia e quella dell'originale restano separate.
+impl Clone for ReteNeurale {
+    fn clone(&self) -> Self {
+        ReteNeurale {
+            strati: self.strati.clone(),
+            bias: self.bias.clone(),
+            funzioni_attivazione: self.funzioni_attivazione.clone(),
+            tasso_apprendimento: self.tasso_apprendimento,
+            dimensioni_strati: self.dimensioni_strati.clone(),
+            strati_congelati: self.strati_congelati.clone(),
+            traccia: self.traccia,
+            weight_norm: self.weight_norm,
+            dropconnect: self.dropconnect.clone(),
+            ottimizzatore: self.ottimizzatore,
+            lambda_l1: self.lambda_l1,
+            clip_gradiente: self.clip_gradiente,
+            stato_ottimizzatore: self.stato_ottimizzatore.clone(),
+            perdita: self.perdita.clone(),
+            rng: self.rng.clone(),
+            batch_riferimento: self.batch_riferimento,
+            cifre_significative: self.cifre_significative,
+            batch_norm: self.batch_norm.clone(),
+            registro_traccia: Mutex::new(Vec::new())
+        }
+    }
+}
+
+/// Permette la stampa della rete
+/// 
+/// Esempio:
+/// let rete: ReteNeurale<ReLU>;
+/// .....
+/// println!("{rete}");
+impl Display for ReteNeurale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let mut testo = String::new();
+        testo += "Pesi Rete Neurale MLP\n";
+        let mut nomi_funz_attivazione = String::new();
+        
+        for funzione_attivazione in self.funzioni_attivazione.clone().into_iter()  {
+            nomi_funz_attivazione += &(funzione_attivazione.nome().to_string()+ "; ");
+        }
+        testo += format!("Funzioni di attivazione per livello: {:?}\n", nomi_funz_attivazione).as_str(); // Stampa il nome della funzione di attivazione
+
+        for (i, strato) in self.strati.iter().enumerate() {
+            let normalizzazione = if self.batch_norm[i].is_some() { " + BatchNorm" } else { "" };
+            testo += format!(
+                "\nConnessioni livello [{}] - [{}] (attivazione livello [{}]: {}{})\n",
+                i, i + 1, i + 1, self.attivazione_strato(i + 1).nome(), normalizzazione
+            ).as_str();
+            for riga in strato.row_iter() {
+                let riga_str = riga.iter()
+                    .map(|valore| valore.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\t");
+                testo += riga_str.as_str();
+                testo += "\n";
+            }
+            testo += format!("Bias livello [{}]\n{}\n", i + 1, self.testo_bias(&self.bias[i]).replace(' ', "\t")).as_str();
+        }
+        writeln!(f, "{}", testo)
+    }
+}
+impl ReteNeurale {
+    /// Crea una nuova rete neurale con il numero di livelli nascosti specificato.
+    ///
+    /// # Argomenti
+    ///
+    /// * `dimensioni_strati` - Un vettore che specifica il numero di neuroni in ogni strato, incluso input e output.
+    /// 
+    ///     Esempio:
+    ///   let dimensioni_strati = vec![2, 3, 2, 1];  // Input, due livelli nascosti, output
+    /// 
+    /// * `tasso_apprendimento` - Il tasso di apprendimento per l'algoritmo di backpropagation.
+    /// * `funzione_attivazione` - La funzione di attivazione da utilizzare nella rete.
+    pub fn nuova_rete_uniforme(
+        dimensioni_strati: Vec<usize>,
+        tasso_apprendimento: f64,
+        funzione_attivazione:Arc<dyn FunzioneAttivazione + Send + Sync>
+    ) -> Self {
+        let mut rng = StdRng::from_entropy();
+        let strati = Self::genera_pesi(&dimensioni_strati, InizializzazionePesi::Uniforme, &mut rng);
+        let bias = Self::genera_bias(&dimensioni_strati, InizializzazionePesi::Uniforme, &mut rng);
+        let funzioni_attivazione = vec![funzione_attivazione];
+        let mut rete = Self::da_parti(strati, funzioni_attivazione, tasso_apprendimento, dimensioni_strati);
+        rete.bias = bias;
+        rete.rng = rng;
+        rete
+    }
+
+    /// Crea una nuova rete neurale con il numero di livelli nascosti specificato.
+    ///
+    /// # Argomenti
+    ///
+    /// * `dimensioni_strati` - Un vettore che specifica il numero di neuroni in ogni strato, incluso input e output.
+    /// 
+    ///     Esempio:
+    ///   let dimensioni_strati = vec![2, 3, 2, 1];  // Input, due livelli nascosti, output
+    /// 
+    /// * `tasso_apprendimento` - Il tasso di apprendimento per l'algoritmo di backpropagation.
+    /// * `funzioni_attivazione` - Lista delle funzioni di attivazione per singoli strati.
+    /// 
+    pub fn nuova( info_strati: Vec<Strato>, tasso_apprendimento: f64 ) -> Self {
+        Self::nuova_con_inizializzazione(info_strati, tasso_apprendimento, InizializzazionePesi::Uniforme)
+    }
+
+    /// Crea una nuova rete neurale da una lista di strati tipizzati: strati densi, ognuno eventualmente seguito
+    /// da una `TipoStrato::BatchNorm` che ne normalizza la pre-attivazione prima della funzione di attivazione.
+    /// Il primo strato è l'input (denso, senza normalizzazione). La batch normalization usa le statistiche
+    /// del batch solo in `addestra_batch` (e quindi `addestra_per_batch`) con almeno due esempi; negli altri
+    /// casi, inferenza compresa, usa media e varianza mobili e `γ`, `β` restano fissi.
+    /// I parametri della normalizzazione sono conservati da tutti i formati di salvataggio (testo, diff, JSON e binario).
+    ///
+    /// # Esempio
+    /// ```
+    /// let rete = ReteNeurale::nuova_tipizzata(vec![
+    ///     TipoStrato::Denso(Strato { neuroni: 2, funzione_attivazione: Arc::new(Nessuna) }),
+    ///     TipoStrato::Denso(Strato { neuroni: 16, funzione_attivazione: Arc::new(ReLU) }),
+    ///     TipoStrato::BatchNorm,
+    ///     TipoStrato::Denso(Strato { neuroni: 1, funzione_attivazione: Arc::new(Sigmoide) }),
+    /// ], 0.1)?;
+    /// ```
+    pub fn nuova_tipizzata(strati: Vec<TipoStrato>, tasso_apprendimento: f64) -> Result<Self, Error> {
+        let mut info_strati = Vec::new();
+        let mut normalizzati = Vec::new();
+        for strato in strati {
+            match strato {
+                TipoStrato::Denso(info) => {
+                    info_strati.push(info);
+                    normalizzati.push(false);
+                }
+                TipoStrato::BatchNorm => match normalizzati.last_mut() {
+                    Some(normalizzato) if info_strati.len() > 1 && !*normalizzato => *normalizzato = true,
+                    _ => return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "la batch normalization deve seguire uno strato denso dopo l'input"
+                    )),
+                },
+            }
+        }
+        if info_strati.len() < 2 {
+            return Err(Error::new(ErrorKind::InvalidInput, "la rete deve avere almeno due strati densi"));
+        }
+        let mut rete = Self::nuova(info_strati, tasso_apprendimento);
+        for (k, normalizzazione) in rete.batch_norm.iter_mut().enumerate() {
+            if normalizzati[k + 1] {
+                *normalizzazione = Some(BatchNorm::nuova(rete.dimensioni_strati[k + 1]));
+            }
+        }
+        Ok(rete)
+    }
+
+    /// Batch normalization dello strato di connessioni `indice`, se presente.
+    pub fn batch_norm(&self, indice: usize) -> Option<&BatchNorm> {
+        self.batch_norm.get(indice).and_then(Option::as_ref)
+    }
+
+    /// Crea una nuova rete neurale come `nuova`, scegliendo la strategia di inizializzazione dei pesi.
+    ///
+    /// # Argomenti
+    ///
+    /// * `info_strati` - Numero di neuroni e funzione di attivazione di ogni strato, incluso input e output.
+    /// * `tasso_apprendimento` - Il tasso di apprendimento per l'algoritmo di backpropagation.
+    /// * `inizializzazione` - La strategia di inizializzazione, applicata strato per strato.
+    ///
+    pub fn nuova_con_inizializzazione(
+        info_strati: Vec<Strato>,
+        tasso_apprendimento: f64,
+        inizializzazione: InizializzazionePesi
+    ) -> Self {
+        Self::da_info_strati(info_strati, tasso_apprendimento, inizializzazione, StdRng::from_entropy())
+    }
+
+    /// Crea una nuova rete neurale come `nuova`, con un generatore casuale deterministico inizializzato dal seme:
+    /// a parità di seme, strati e dati, due reti hanno gli stessi pesi iniziali e, addestrate allo stesso modo,
+    /// raggiungono lo stesso stato. Utile per test e debugging.
+    ///
+    /// # Argomenti
+    ///
+    /// * `info_strati` - Numero di neuroni e funzione di attivazione di ogni strato, incluso input e output.
+    /// * `tasso_apprendimento` - Il tasso di apprendimento per l'algoritmo di backpropagation.
+    /// * `seme` - Il seme del generatore casuale, usato per l'inizializzazione e per l'addestramento.
+    ///
+    /// # Esempio
+    /// ```
+    /// let a = ReteNeurale::nuova_con_seme(strati_a, 0.1, 42);
+    /// let b = ReteNeurale::nuova_con_seme(strati_b, 0.1, 42);
+    /// assert_eq!(a.pesi_connessioni(), b.pesi_connessioni());
+    /// ```
+    pub fn nuova_con_seme(info_strati: Vec<Strato>, tasso_apprendimento: f64, seme: u64) -> Self {
+        Self::da_info_strati(info_strati, tasso_apprendimento, InizializzazionePesi::Uniforme, StdRng::seed_from_u64(seme))
+    }
+
+    /// Costruisce la rete dalle informazioni sugli strati, generando pesi e bias con il generatore dato,
+    /// che resta poi alla rete per le scelte casuali dell'addestramento.
+    fn da_info_strati(
+        info_strati: Vec<Strato>,
+        tasso_apprendimento: f64,
+        inizializzazione: InizializzazionePesi,
+        mut rng: StdRng
+    ) -> Self {
+        let mut funzioni_attivazione:Vec<Arc<dyn FunzioneAttivazione + Send + Sync>> = Vec::new();
+        let mut dimensioni_strati= Vec::new();
+        let mut primo_strato = true;
+        for info_strato in info_strati.into_iter() {
+            dimensioni_strati.push(info_strato.neuroni);
+            if primo_strato {
+                primo_strato = false;
+                funzioni_attivazione.push(Arc::new(Nessuna));
+            }else{
+                funzioni_attivazione.push(info_strato.funzione_attivazione);
+            }
+        }
+        let strati = Self::genera_pesi(&dimensioni_strati, inizializzazione, &mut rng);
+        let bias = Self::genera_bias(&dimensioni_strati, inizializzazione, &mut rng);
+        
+        let mut rete = Self::da_parti(strati, funzioni_attivazione, tasso_apprendimento, dimensioni_strati);
+        rete.bias = bias;
+        rete.rng = rng; // l'inizializzazione e l'addestramento condividono lo stesso generatore
+        rete
+    }
+
+    /// Genera i pesi delle connessioni tra strati consecutivi secondo la strategia di inizializzazione.
+    fn genera_pesi(dimensioni_strati: &[usize], inizializzazione: InizializzazionePesi, rng: &mut StdRng) -> Vec<DMatrix<f64>> {
+        let mut strati = Vec::with_capacity(dimensioni_strati.len().saturating_sub(1));
+
+        for i in 0..dimensioni_strati.len().saturating_sub(1) {
+            let (righe, colonne) = (dimensioni_strati[i + 1], dimensioni_strati[i]);
+            let pesi = match inizializzazione {
+                InizializzazionePesi::Identita if righe == colonne => DMatrix::from_fn(righe, colonne, |r, c| {
+                    let rumore = rng.gen_range(-RUMORE_IDENTITA..RUMORE_IDENTITA);
+                    if r == c { 1.0 + rumore } else { rumore }
+                }),
+                InizializzazionePesi::Xavier | InizializzazionePesi::He => {
+                    let varianza = if inizializzazione == InizializzazionePesi::He { 2.0 } else { 1.0 } / colonne as f64;
+                    let limite = (3.0 * varianza).sqrt(); // un'uniforme in [-l, l) ha varianza l²/3
+                    DMatrix::from_fn(righe, colonne, |_, _| rng.gen_range(-limite..limite))
+                }
+                _ => DMatrix::from_fn(righe, colonne, |_, _| rng.gen_range(-1.0..1.0)),
+            };
+            strati.push(pesi);
+        }
+        strati
+    }
+
+    /// Genera i bias di ogni strato dopo l'input: casuali uniformi in [-1, 1) con l'inizializzazione uniforme,
+    /// nulli con le altre strategie (con `Identita` perché lo strato parta davvero vicino alla trasformazione
+    /// identità, con Xavier e He per non alterare la varianza calibrata delle pre-attivazioni).
+    fn genera_bias(dimensioni_strati: &[usize], inizializzazione: InizializzazionePesi, rng: &mut StdRng) -> Vec<DVector<f64>> {
+        dimensioni_strati.iter()
+            .skip(1)
+            .map(|&neuroni| match inizializzazione {
+                InizializzazionePesi::Uniforme => DVector::from_fn(neuroni, |_, _| rng.gen_range(-1.0..1.0)),
+                _ => DVector::zeros(neuroni),
+            })
+            .collect()
+    }
+
+    /// Assembla la rete a partire dai pesi e dalla configurazione, inizializzando lo stato accessorio
+    /// (bias nulli compresi).
+    fn da_parti(
+        strati: Vec<DMatrix<f64>>,
+        funzioni_attivazione: Vec<Arc<dyn FunzioneAttivazione + Send + Sync>>,
+        tasso_apprendimento: f64,
+        dimensioni_strati: Vec<usize>
+    ) -> Self {
+        let funzioni_attivazione = Self::attivazioni_per_strato(funzioni_attivazione, dimensioni_strati.len());
+        let strati_congelati = vec![false; strati.len()];
+        let dropconnect = vec![0.0; strati.len()];
+        let batch_norm = vec![None; strati.len()];
+        let bias = strati.iter().map(|pesi| DVector::zeros(pesi.nrows())).collect();
+        ReteNeurale {
+            strati,
+            bias,
+            funzioni_attivazione,
+            tasso_apprendimento,
+            dimensioni_strati,
+            strati_congelati,
+            traccia: false,
+            weight_norm: false,
+            dropconnect,
+            ottimizzatore: Ottimizzatore::SGD,
+            lambda_l1: 0.0,
+            clip_gradiente: None,
+            stato_ottimizzatore: None,
+            perdita: Arc::new(ErroreQuadraticoMedio),
+            rng: StdRng::from_entropy(),
+            batch_riferimento: 1,
+            cifre_significative: None,
+            batch_norm,
+            registro_traccia: Mutex::new(Vec::new())
+        }
+    }
+
+
+    /// Crea una rete da un file contiene i pesi e le informazioni della rete, da un file txt precedentemente creato.
+    ///
+    /// La rete caricata ha la stessa topologia, le stesse funzioni di attivazione (con i parametri), gli stessi pesi,
+    /// tasso di apprendimento, perdita e ottimizzatore della rete salvata, per cui l'addestramento può riprendere
+    /// da dove era arrivato. Lo stato interno dell'ottimizzatore (momenti di Momentum/Adam) non viene salvato
+    /// e riparte da zero.
+    ///
+    /// # Ritorna
+    ///
+    /// La rete caricata, oppure un `ErroreRete` se il file non è leggibile o non rispetta il formato
+    /// (vedi `carica_pesi_txt`).
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.addestra_epoche(&dati_addestramento, 100, OpzioniAddestramento::default())?;
+    /// rete.salva_pesi_txt("rete_neurale.txt")?;
+    ///
+    /// let mut ripresa = ReteNeurale::carica("rete_neurale.txt")?;
+    /// ripresa.addestra_epoche(&dati_addestramento, 100, OpzioniAddestramento::default())?;
+    /// ```
+    pub fn carica(file_txt: &str) -> Result<Self, ErroreRete> {
+        let mut rete = Self::nuova_rete_uniforme(vec![0], 0.0, Arc::new(Sigmoide));
+        rete.carica_pesi_txt(file_txt)?;
+        Ok(rete)
+    }
+
+    /// Propagazione in avanti attraverso la rete.
+    ///
+    /// # Argomenti
+    ///
+    /// * `input` - Vettore di input per la rete neurale.
+    ///
+    /// # Ritorna
+    ///
+    /// Un vettore di vettori contenenti le uscite di ogni strato.
+    fn propagazione_avanti(&self, input: &DVector<f64>) -> Vec<DVector<f64>> {
+        self.propagazione_completa(input).uscite
+    }
+
+    /// Come `propagazione_avanti`, conservando anche le pre-attivazioni di ogni strato,
+    /// su cui la retropropagazione valuta le derivate delle funzioni di attivazione.
+    fn propagazione_completa(&self, input: &DVector<f64>) -> Propagazione {
+        let mut uscite = Vec::with_capacity(self.strati.len() + 1);
+        let mut pre_attivazioni = Vec::with_capacity(self.strati.len() + 1);
+        let mut attivazione_corrente = input.clone();
+        uscite.push(attivazione_corrente.clone());
+        pre_attivazioni.push(attivazione_corrente.clone());
+        for (k, (pesi, bias)) in self.strati.iter().zip(self.bias.iter()).enumerate() {
+            let funzione = self.attivazione_strato(k + 1);
+            let mut input_strato = pesi * attivazione_corrente + bias;
+            if let Some(normalizzazione) = &self.batch_norm[k] {
+                normalizzazione.normalizza_sul_posto(&mut input_strato);
+            }
+            attivazione_corrente = funzione.attiva_vettore(&input_strato);
+            if self.traccia {
+                self.registra_traccia(k + 1, &input_strato, &attivazione_corrente);
+            }
+            uscite.push(attivazione_corrente.clone());
+            pre_attivazioni.push(input_strato);
+        }
+
+        Propagazione { pre_attivazioni, uscite }
+    }
+
+    /// Funzione di attivazione applicata allo strato di neuroni `strato` (1 = primo strato dopo l'input).
+    fn attivazione_strato(&self, strato: usize) -> &Arc<dyn FunzioneAttivazione + Send + Sync> {
+        // una funzione per strato, la prima (strato input) è quella nulla
+        &self.funzioni_attivazione[strato]
+    }
+
+    /// Un file salvato deve indicare una funzione di attivazione per strato, oppure una sola per tutti gli strati.
+    fn numero_attivazioni_valido(funzioni: usize, strati: usize) -> bool {
+        funzioni == 1 || funzioni == strati
+    }
+
+    /// Espande una sola funzione di attivazione a una per strato, con la funzione nulla per l'input,
+    /// così che la funzione dello strato `i` sia sempre `funzioni_attivazione[i]`.
+    fn attivazioni_per_strato(
+        mut funzioni: Vec<Arc<dyn FunzioneAttivazione + Send + Sync>>,
+        num_strati: usize
+    ) -> Vec<Arc<dyn FunzioneAttivazione + Send + Sync>> {
+        if funzioni.len() == 1 && num_strati > 1 {
+            let funzione = funzioni.remove(0);
+            funzioni.push(Arc::new(Nessuna));
+            funzioni.extend(std::iter::repeat_n(funzione, num_strati - 1));
+        }
+        funzioni
+    }
+
+    /// Registra una riga di traccia con min/max/media della pre-attivazione e dell'attivazione di uno strato.
+    fn registra_traccia(&self, strato: usize, pre_attivazione: &DVector<f64>, attivazione: &DVector<f64>) {
+        let riga = format!(
+            "strato [{}] pre-attivazione min={} max={} media={} | attivazione min={} max={} media={}",
+            strato,
+            pre_attivazione.min(), pre_attivazione.max(), pre_attivazione.mean(),
+            attivazione.min(), attivazione.max(), attivazione.mean()
+        );
+        if let Ok(mut registro) = self.registro_traccia.lock() {
+            registro.push(riga);
+        }
+    }
+
+    /// Metodo che interroga la Rete Neutale, elabora i dati di input
+    /// e restituisce in output il risultato.
+    /// 
+    /// # Argomenti
+    /// * `input` vettore dei dati in input
+    /// 
+    /// # Ritorna
+    ///  vettore output (risposta)
+    /// # Esempio
+    /// ```
+    ///  // usiamo gli stessi dati di addestramento per confronto ...
+    ///  let dati_addestramento = [
+    ///     InputAddestramento {input:vec![0.0, 0.0], output: vec![0.0]},
+    ///     InputAddestramento {input:vec![0.0, 1.0], output: vec![1.0]},
+    ///     InputAddestramento {input:vec![1.0, 0.0], output: vec![1.0]},
+    ///     InputAddestramento {input:vec![1.0, 1.0], output: vec![0.0]}
+    /// ];
+    /// for set in dati_addestramento.iter() {
+    ///     let uscita = rete.elabora(set.input.clone());
+    ///     println!("Input: {:?}, Previsto: {:?}, Uscita: {:?}", set.input, set.output, uscita);
+    /// }
+    /// ```
+    pub fn elabora(&self, input: Vec<f64>) -> Vec<f64> {
+        let uscite = self.propagazione_avanti(&DVector::from_vec(input));
+        uscite[uscite.len() - 1].data.as_vec().to_vec()
+    }
+
+    /// Come `elabora`, ma restituisce le attivazioni di ogni strato, dall'input (primo vettore)
+    /// all'output (ultimo vettore, uguale al risultato di `elabora`), per ispezionare cosa calcolano
+    /// gli strati nascosti.
+    ///
+    /// # Esempio
+    /// ```
+    /// let attivazioni = rete.elabora_con_attivazioni(vec![1.0, 0.0]);
+    /// println!("strato nascosto: {:?}", attivazioni[1]);
+    /// ```
+    pub fn elabora_con_attivazioni(&self, input: Vec<f64>) -> Vec<Vec<f64>> {
+        self.propagazione_avanti(&DVector::from_vec(input))
+            .into_iter()
+            .map(|attivazione| attivazione.data.into())
+            .collect()
+    }
+
+    /// Come `elabora`, ma scrive le attivazioni di ogni strato nei buffer preallocati del contesto
+    /// invece di allocare nuovi vettori, per l'inferenza ad alta frequenza. Se il contesto è stato creato
+    /// per una rete con strati diversi viene ridimensionato (allocando solo in quel caso).
+    ///
+    /// # Argomenti
+    /// * `contesto` contesto di inferenza, tipicamente uno per thread
+    /// * `input` dati in input
+    ///
+    /// # Ritorna
+    ///  l'output della rete, valido fino al successivo uso del contesto
+    ///
+    /// # Panics
+    ///
+    /// Se la lunghezza dell'input è diversa dal numero di neuroni di input.
+    pub fn elabora_con_contesto<'a>(&self, contesto: &'a mut ContestoInferenza, input: &[f64]) -> &'a [f64] {
+        assert_eq!(input.len(), self.dimensioni_strati[0], "dimensione dell'input diversa dai neuroni di input");
+        if contesto.attivazioni.iter().map(|attivazione| attivazione.len()).ne(self.dimensioni_strati.iter().copied()) {
+            *contesto = ContestoInferenza::per_rete(self);
+        }
+        contesto.attivazioni[0].copy_from_slice(input);
+        for (k, pesi) in self.strati.iter().enumerate() {
+            let funzione = self.attivazione_strato(k + 1);
+            let (precedenti, successivi) = contesto.attivazioni.split_at_mut(k + 1);
+            let uscita = &mut successivi[0];
+            uscita.copy_from(&self.bias[k]);
+            uscita.gemv(1.0, pesi, &precedenti[k], 1.0);
+            if let Some(normalizzazione) = &self.batch_norm[k] {
+                normalizzazione.normalizza_sul_posto(uscita);
+            }
+            funzione.attiva_sul_posto(uscita);
+        }
+        contesto.attivazioni[self.strati.len()].as_slice()
+    }
+
+    /// Come `elabora`, accettando qualsiasi tipo convertibile in input.
+    ///
+    /// # Panics
+    ///
+    /// Se la conversione produce un vettore di dimensione diversa dai neuroni di input.
+    pub fn elabora_tramite<T: ConvertibileInInput>(&self, x: &T) -> Vec<f64> {
+        let input = x.in_input();
+        assert_eq!(input.len(), self.dimensioni_strati[0], "dimensione dell'input diversa dai neuroni di input");
+        self.elabora(input)
+    }
+
+    /// Applica pigramente la rete a uno stream di input, producendo gli output uno alla volta
+    /// e nello stesso ordine, senza materializzare lo stream in memoria (anche flussi infiniti).
+    ///
+    /// # Esempio
+    /// ```
+    /// let sensori = std::iter::repeat_with(|| leggi_sensori());
+    /// for uscita in rete.elabora_stream(sensori).take(100) {
+    ///     println!("{:?}", uscita);
+    /// }
+    /// ```
+    pub fn elabora_stream<'a, I>(&'a self, input_iter: I) -> impl Iterator<Item = Vec<f64>> + 'a
+    where
+        I: Iterator<Item = Vec<f64>> + 'a,
+    {
+        input_iter.map(move |input| self.elabora(input))
+    }
+
+    /// Elabora più input con una sola chiamata, impacchettandoli come colonne di una matrice
+    /// (vedi `elabora_matrice`): gli output sono quelli di `elabora` su ogni input, a meno degli arrotondamenti
+    /// dovuti al diverso ordine delle somme, ma con una sola moltiplicazione matriciale per strato.
+    ///
+    /// # Argomenti
+    /// * `input` gli input, tutti lunghi quanto i neuroni di input
+    ///
+    /// # Ritorna
+    ///  gli output, nello stesso ordine degli input
+    ///
+    /// # Panics
+    ///
+    /// Se un input ha lunghezza diversa dal numero di neuroni di input.
+    ///
+    /// # Esempio
+    /// ```
+    /// let uscite = rete.elabora_batch(&[vec![0.0, 1.0], vec![1.0, 1.0]]);
+    /// ```
+    pub fn elabora_batch(&self, input: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        if input.is_empty() {
+            return Vec::new();
+        }
+        let neuroni_input = self.dimensioni_strati[0];
+        assert!(
+            input.iter().all(|campione| campione.len() == neuroni_input),
+            "dimensione dell'input diversa dai neuroni di input"
+        );
+        let colonne = DMatrix::from_fn(neuroni_input, input.len(), |r, c| input[c][r]);
+        self.elabora_matrice(&colonne)
+            .column_iter()
+            .map(|uscita| uscita.iter().copied().collect())
+            .collect()
+    }
+
+    /// Elabora un intero batch di input con una sola serie di moltiplicazioni matriciali per strato.
+    /// Ogni colonna della matrice è un campione: il batch può avere qualsiasi numero di colonne
+    /// (anche una sola) senza riallocare la rete, e il risultato ha la stessa larghezza dell'input.
+    ///
+    /// Questo metodo si chiamava in precedenza `elabora_batch`: il nome è passato alla variante
+    /// che accetta una slice di input, mentre chi lavora già con le matrici usa `elabora_matrice`.
+    ///
+    /// # Argomenti
+    /// * `input` matrice `neuroni di input × campioni`
+    ///
+    /// # Ritorna
+    ///  matrice `neuroni di output × campioni`, colonna per colonna uguale a `elabora` a meno degli arrotondamenti
+    pub fn elabora_matrice(&self, input: &DMatrix<f64>) -> DMatrix<f64> {
+        let mut attivazione_corrente = input.clone();
+        for k in 0..self.strati.len() {
+            let funzione = self.attivazione_strato(k + 1);
+            attivazione_corrente = Self::attiva_colonne(funzione.as_ref(), self.pre_attivazione_colonne(k, &attivazione_corrente));
+        }
+        attivazione_corrente
+    }
+
+    /// Applica la funzione di attivazione a ogni colonna (campione) di una matrice di pre-attivazioni.
+    fn attiva_colonne(funzione: &(dyn FunzioneAttivazione + Send + Sync), mut pre_attivazione: DMatrix<f64>) -> DMatrix<f64> {
+        for mut colonna in pre_attivazione.column_iter_mut() {
+            let attivata = funzione.attiva_vettore(&colonna.clone_owned());
+            colonna.copy_from(&attivata);
+        }
+        pre_attivazione
+    }
+
+    /// Pre-attivazione `W·A + b` dello strato di connessioni `k` per una matrice di campioni per colonne,
+    /// normalizzata come in inferenza se lo strato ha la batch normalization.
+    fn pre_attivazione_colonne(&self, k: usize, attivazioni: &DMatrix<f64>) -> DMatrix<f64> {
+        let mut pre_attivazione = self.pre_attivazione_lineare(k, attivazioni);
+        if let Some(normalizzazione) = &self.batch_norm[k] {
+            for mut colonna in pre_attivazione.column_iter_mut() {
+                let normalizzata = normalizzazione.normalizza(&colonna.clone_owned());
+                colonna.copy_from(&normalizzata);
+            }
+        }
+        pre_attivazione
+    }
+
+    /// Pre-attivazione `W·A + b` dello strato di connessioni `k`, senza normalizzazione.
+    fn pre_attivazione_lineare(&self, k: usize, attivazioni: &DMatrix<f64>) -> DMatrix<f64> {
+        let mut pre_attivazione = &self.strati[k] * attivazioni;
+        for mut colonna in pre_attivazione.column_iter_mut() {
+            colonna += &self.bias[k];
+        }
+        pre_attivazione
+    }
+
+    /// Attivazioni di uno strato per tutti gli input, impilate in una matrice con una riga per esempio,
+    /// come ponte verso strumenti di clustering o riduzione dimensionale che lavorano su matrici.
+    /// Gli input sono elaborati insieme, con una moltiplicazione matriciale per strato fino a quello richiesto.
+    ///
+    /// # Argomenti
+    /// * `dati` input da elaborare
+    /// * `strato` indice dello strato di neuroni (0 = input, `strati().len() - 1` = output)
+    ///
+    /// # Ritorna
+    ///  matrice `esempi × neuroni dello strato`, oppure un errore se lo strato non esiste
+    ///  o un input non ha la dimensione attesa
+    pub fn embedding(&self, dati: &[Vec<f64>], strato: usize) -> Result<DMatrix<f64>, Error> {
+        if strato > self.strati.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("strato [{}] inesistente", strato)));
+        }
+        let dimensione = self.dimensioni_strati[0];
+        if let Some(input) = dati.iter().find(|input| input.len() != dimensione) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("input di dimensione {} invece di {}", input.len(), dimensione)
+            ));
+        }
+        let mut attivazioni = DMatrix::from_fn(dimensione, dati.len(), |r, c| dati[c][r]);
+        for k in 0..strato {
+            let funzione = self.attivazione_strato(k + 1);
+            attivazioni = Self::attiva_colonne(funzione.as_ref(), self.pre_attivazione_colonne(k, &attivazioni));
+        }
+        Ok(attivazioni.transpose())
+    }
+
+    /// Campiona una classe dall'output trattato come distribuzione categorica, invece di prenderne l'argmax:
+    /// all'output viene applicata una softmax con temperatura e l'indice è estratto secondo le probabilità risultanti.
+    /// Utile nei task generativi dove si vuole diversità.
+    ///
+    /// # Argomenti
+    /// * `input` vettore dei dati in input
+    /// * `temperatura` temperatura della softmax (> 0): valori alti rendono il campionamento più uniforme
+    /// * `seme` seme del generatore casuale, per un campionamento riproducibile
+    ///
+    /// # Ritorna
+    ///  l'indice dell'output campionato, oppure un errore se la temperatura non è un numero positivo
+    pub fn campiona(&self, input: Vec<f64>, temperatura: f64, seme: u64) -> Result<usize, Error> {
+        if !(temperatura > 0.0 && temperatura.is_finite()) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("temperatura {} non valida: deve essere positiva", temperatura)));
+        }
+        let probabilita = softmax(&self.elabora(input), temperatura);
+        let mut rng = StdRng::seed_from_u64(seme);
+        let estratto: f64 = rng.gen();
+        let mut cumulata = 0.0;
+        for (i, p) in probabilita.iter().enumerate() {
+            cumulata += p;
+            if estratto < cumulata {
+                return Ok(i);
+            }
+        }
+        Ok(probabilita.len().saturating_sub(1))
+    }
+
+    /// Classificazione binaria (anche multi-etichetta): applica `elabora` e confronta ogni output con la soglia.
+    ///
+    /// # Argomenti
+    /// * `input` vettore dei dati in input
+    /// * `soglia` valore da cui un output è considerato positivo (`>= soglia`)
+    ///
+    /// # Ritorna
+    ///  per ogni neurone di output `true` se supera la soglia, oppure un errore se la rete non ha output
+    ///
+    /// # Esempio
+    /// ```
+    /// // rete addestrata su XOR
+    /// assert_eq!(rete.classifica(vec![0.0, 1.0], 0.5)?, vec![true]);
+    /// ```
+    pub fn classifica(&self, input: Vec<f64>, soglia: f64) -> Result<Vec<bool>, Error> {
+        let uscita = self.uscita_non_vuota(input)?;
+        Ok(uscita.iter().map(|&valore| valore >= soglia).collect())
+    }
+
+    /// Classificazione multi-classe: la classe è l'indice dell'output massimo (argmax),
+    /// oppure 0/1 con soglia 0.5 se l'output ha un solo neurone.
+    ///
+    /// # Argomenti
+    /// * `input` vettore dei dati in input
+    ///
+    /// # Ritorna
+    ///  la classe predetta, oppure un errore se la rete non ha output
+    pub fn classifica_classe(&self, input: Vec<f64>) -> Result<usize, Error> {
+        Ok(classe_di(&self.uscita_non_vuota(input)?))
+    }
+
+    /// Output di `elabora`, con un errore se è vuoto (rete senza neuroni di output).
+    fn uscita_non_vuota(&self, input: Vec<f64>) -> Result<Vec<f64>, Error> {
+        let uscita = self.elabora(input);
+        if uscita.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "la rete non ha neuroni di output da classificare"));
+        }
+        Ok(uscita)
+    }
+
+    /// Classifica l'input solo se la rete è abbastanza sicura (reject option): la confidenza è la probabilità
+    /// massima dopo aver normalizzato l'output, e se non supera la soglia la rete si astiene.
+    ///
+    /// Un output già normalizzato (valori in [0, 1] con somma 1) è usato così com'è, altrimenti viene
+    /// applicata una softmax. Con un solo neurone di output la classe è 0/1 con soglia 0.5 e la confidenza
+    /// è `max(p, 1 - p)`, con `p` l'output (passato per la sigmoide se non è già in [0, 1]).
+    ///
+    /// # Argomenti
+    /// * `input` vettore dei dati in input
+    /// * `soglia_confidenza` confidenza minima richiesta, in [0, 1]
+    ///
+    /// # Ritorna
+    ///  la classe predetta, oppure `None` se la confidenza non supera la soglia
+    pub fn classifica_con_rifiuto(&self, input: Vec<f64>, soglia_confidenza: f64) -> Option<usize> {
+        let uscita = self.elabora(input);
+        let probabilita = if uscita.len() == 1 {
+            let p = if (0.0..=1.0).contains(&uscita[0]) { uscita[0] } else { Sigmoide.attiva(uscita[0]) };
+            vec![1.0 - p, p]
+        } else if uscita.iter().all(|p| (0.0..=1.0).contains(p)) && (uscita.iter().sum::<f64>() - 1.0).abs() < 1e-6 {
+            uscita
+        } else {
+            softmax(&uscita, 1.0)
+        };
+        let classe = classe_di(&probabilita);
+        (probabilita[classe] > soglia_confidenza).then_some(classe)
+    }
+
+    /// Perdita media della rete su un set di esempi (ad esempio di validazione), calcolata con la
+    /// funzione di perdita della rete senza modificare i pesi. Restituisce 0.0 per un set vuoto.
+    /// Con la perdita di default è l'errore quadratico medio (`Σ(previsto - target)² / 2n` per esempio).
+    ///
+    /// # Argomenti
+    /// * `dati` set di esempi input-output
+    ///
+    /// # Esempio
+    /// ```
+    /// // curva di apprendimento: perdita di addestramento e di validazione per epoca
+    /// for epoca in 0..1000 {
+    ///     rete.addestra_epoche(&dati_addestramento, 1, OpzioniAddestramento::default())?;
+    ///     println!("{}\t{}\t{}", epoca, rete.perdita_media(&dati_addestramento), rete.perdita_media(&dati_validazione));
+    /// }
+    /// ```
+    pub fn perdita_media(&self, dati: &[InputAddestramento]) -> f64 {
+        if dati.is_empty() {
+            return 0.0;
+        }
+        let somma: f64 = dati.iter().map(|set| self.perdita_esempio(set)).sum();
+        somma / dati.len() as f64
+    }
+
+    /// Come `perdita_media`, ma ripartisce gli esempi tra i thread del pool di rayon: la propagazione
+    /// in avanti è in sola lettura e la rete è `Send + Sync`. Le somme parziali sono combinate in un ordine
+    /// che dipende dalla suddivisione del lavoro, per cui il risultato coincide con quello seriale
+    /// a meno degli arrotondamenti.
+    ///
+    /// # Argomenti
+    /// * `dati` set di esempi input-output
+    pub fn perdita_media_parallela(&self, dati: &[InputAddestramento]) -> f64 {
+        if dati.is_empty() {
+            return 0.0;
+        }
+        let somma: f64 = dati.par_iter().map(|set| self.perdita_esempio(set)).sum();
+        somma / dati.len() as f64
+    }
+
+    /// Frazione di esempi classificati correttamente, in [0, 1]: per output a singolo neurone
+    /// (classificazione binaria) confronta previsto e atteso rispetto alla `soglia`, altrimenti
+    /// (multi-classe, ad esempio dopo una `Softmax` con target one-hot) confronta gli argmax.
+    /// 0 se il set è vuoto.
+    ///
+    /// # Argomenti
+    /// * `dati` set di esempi input-output
+    /// * `soglia` soglia di decisione per l'output a singolo neurone, tipicamente 0.5 (ignorata con più output)
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.addestra_epoche(&dati_addestramento, 1000, OpzioniAddestramento::default())?;
+    /// println!("accuratezza: {:.1}%", 100.0 * rete.accuratezza(&dati_validazione, 0.5));
+    /// ```
+    pub fn accuratezza(&self, dati: &[InputAddestramento], soglia: f64) -> f64 {
+        if dati.is_empty() {
+            return 0.0;
+        }
+        let corretti = dati.iter()
+            .filter(|set| Self::classificato_correttamente(&self.elabora(set.input.clone()), &set.output, soglia))
+            .count();
+        corretti as f64 / dati.len() as f64
+    }
+
+    /// Criterio di `accuratezza`: soglia per l'output a singolo neurone, argmax con più output.
+    fn classificato_correttamente(uscita: &[f64], atteso: &[f64], soglia: f64) -> bool {
+        if uscita.len() == 1 {
+            (uscita[0] >= soglia) == (atteso[0] >= soglia)
+        } else {
+            classe_di(uscita) == classe_di(atteso)
+        }
+    }
+
+    /// Perdita media e accuratezza (soglia 0.5) su un insieme di esempi, con una sola propagazione
+    /// in avanti per esempio invece di una per ogni metrica. Con il set vuoto le metriche valgono 0.
+    ///
+    /// # Esempio
+    /// ```
+    /// let valutazione = rete.valuta(&dati_validazione);
+    /// println!("{}", valutazione);
+    /// ```
+    pub fn valuta(&self, dati: &[InputAddestramento]) -> Valutazione {
+        let mut somma_perdite = 0.0;
+        let mut corretti = 0;
+        for set in dati.iter() {
+            let uscite = self.propagazione_avanti(&DVector::from_column_slice(&set.input));
+            let uscita = &uscite[uscite.len() - 1];
+            somma_perdite += self.perdita.calcola(uscita, &DVector::from_column_slice(&set.output));
+            if Self::classificato_correttamente(uscita.as_slice(), &set.output, 0.5) {
+                corretti += 1;
+            }
+        }
+        let numero_esempi = dati.len();
+        let (perdita_media, accuratezza) = if numero_esempi == 0 {
+            (0.0, 0.0)
+        } else {
+            (somma_perdite / numero_esempi as f64, corretti as f64 / numero_esempi as f64)
+        };
+        Valutazione { perdita_media, accuratezza, numero_esempi }
+    }
+
+    /// Errore assoluto medio (MAE) per la regressione: media di `|previsto - atteso|` su tutti gli esempi
+    /// e tutti gli output. È espresso nelle unità dei target ed è meno sensibile dell'MSE ai valori anomali.
+    /// 0 se il set è vuoto.
+    pub fn errore_assoluto_medio(&self, dati: &[InputAddestramento]) -> f64 {
+        let mut somma = 0.0;
+        let mut conteggio = 0;
+        for set in dati.iter() {
+            let uscita = self.elabora(set.input.clone());
+            for (previsto, atteso) in uscita.iter().zip(set.output.iter()) {
+                somma += (previsto - atteso).abs();
+                conteggio += 1;
+            }
+        }
+        if conteggio == 0 { 0.0 } else { somma / conteggio as f64 }
+    }
+
+    /// Coefficiente di determinazione R² per la regressione: per ogni output `1 - SS_res / SS_tot`, dove `SS_res`
+    /// è la somma dei quadrati dei residui e `SS_tot` quella degli scarti dei target dalla loro media
+    /// (la previsione costante di riferimento); con più output è la media degli R² dei singoli output.
+    /// Vale 1 per previsioni perfette, 0 se la rete non fa meglio della media e può essere negativo.
+    /// Per un output con target costanti vale 1 se le previsioni sono esatte, 0 altrimenti. 0 se il set è vuoto.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.addestra_epoche(&dati_addestramento, 1000, OpzioniAddestramento::default())?;
+    /// println!("MAE: {}, R²: {}", rete.errore_assoluto_medio(&dati_validazione), rete.r_quadro(&dati_validazione));
+    /// ```
+    pub fn r_quadro(&self, dati: &[InputAddestramento]) -> f64 {
+        let Some(primo) = dati.first() else {
+            return 0.0;
+        };
+        let num_output = primo.output.len();
+        let mut medie = vec![0.0; num_output];
+        for set in dati.iter() {
+            for (media, atteso) in medie.iter_mut().zip(set.output.iter()) {
+                *media += atteso / dati.len() as f64;
+            }
+        }
+        let mut residui = vec![0.0; num_output];
+        let mut totali = vec![0.0; num_output];
+        for set in dati.iter() {
+            let uscita = self.elabora(set.input.clone());
+            for j in 0..num_output {
+                residui[j] += (set.output[j] - uscita[j]).powi(2);
+                totali[j] += (set.output[j] - medie[j]).powi(2);
+            }
+        }
+        let somma: f64 = residui.iter().zip(totali.iter())
+            .map(|(&residuo, &totale)| {
+                if totale > 0.0 {
+                    1.0 - residuo / totale
+                } else if residuo == 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .sum();
+        somma / num_output as f64
+    }
+
+    /// Matrice di confusione `num_classi × num_classi`: la cella `[vera][prevista]` conta gli esempi della classe
+    /// `vera` (argmax del target) classificati come `prevista` (argmax dell'output). Con un solo neurone di output
+    /// le classi sono 0/1 con soglia 0.5. Sulla diagonale ci sono le classificazioni corrette; da righe e colonne
+    /// si ricavano recall e precision di ogni classe. Gli esempi con classi oltre `num_classi` non vengono contati.
+    ///
+    /// # Esempio
+    /// ```
+    /// let matrice = rete.matrice_confusione(&dati_validazione, 3);
+    /// let precision_0 = matrice[0][0] as f64 / (0..3).map(|vera| matrice[vera][0]).sum::<usize>() as f64;
+    /// ```
+    pub fn matrice_confusione(&self, dati: &[InputAddestramento], num_classi: usize) -> Vec<Vec<usize>> {
+        let mut matrice = vec![vec![0; num_classi]; num_classi];
+        for set in dati.iter() {
+            let vera = classe_di(&set.output);
+            let prevista = classe_di(&self.elabora(set.input.clone()));
+            if vera < num_classi && prevista < num_classi {
+                matrice[vera][prevista] += 1;
+            }
+        }
+        matrice
+    }
+
+    /// Cerca il numero di neuroni dello strato nascosto che massimizza l'accuratezza di validazione
+    /// di una rete a singolo strato nascosto (Sigmoide su strato nascosto e output),
+    /// addestrando una rete per ogni larghezza della griglia `min..=max`.
+    ///
+    /// Il set viene mescolato e diviso internamente: la frazione `frazione_addestramento` è usata
+    /// per addestrare, il resto (holdout) per la validazione. A parità di accuratezza vince la rete più piccola.
+    ///
+    /// # Argomenti
+    ///
+    /// * `dati` - Il set di esempi; dimensioni di input e output della rete sono dedotte dal primo esempio.
+    /// * `frazione_addestramento` - Frazione del set usata per l'addestramento, nell'intervallo (0, 1).
+    /// * `min`, `max` - Estremi (inclusi) della griglia di larghezze candidate.
+    /// * `epoche` - Epoche di addestramento per ogni candidato.
+    /// * `tasso` - Tasso di apprendimento delle reti candidate.
+    /// * `seme` - Seme della divisione del set e dei pesi iniziali: a parità di seme la ricerca è riproducibile.
+    ///   La rete di larghezza `n` è inizializzata con il seme `seme + n`.
+    ///
+    /// # Ritorna
+    ///
+    /// La larghezza migliore trovata, oppure un errore se la frazione non è in (0, 1)
+    /// o se uno dei due sottoinsiemi risulterebbe vuoto.
+    pub fn cerca_larghezza(dati: &[InputAddestramento], frazione_addestramento: f64, min: usize, max: usize, epoche: usize, tasso: f64, seme: u64) -> Result<usize, Error> {
+        if !(frazione_addestramento > 0.0 && frazione_addestramento < 1.0) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("frazione di addestramento {} fuori da (0, 1)", frazione_addestramento)));
+        }
+        let num_addestramento = (dati.len() as f64 * frazione_addestramento).round() as usize;
+        if num_addestramento == 0 || num_addestramento == dati.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} esempi non bastano a dividere il set con frazione {}", dati.len(), frazione_addestramento),
+            ));
+        }
+        let mut esempi = dati.to_vec();
+        esempi.shuffle(&mut StdRng::seed_from_u64(seme));
+        let (addestramento, validazione) = esempi.split_at(num_addestramento);
+        let primo = &esempi[0];
+
+        let mut migliore = (min, f64::NEG_INFINITY);
+        for larghezza in min.max(1)..=max {
+            let mut rete = Self::nuova_con_seme(vec![
+                Strato { neuroni: primo.input.len(), funzione_attivazione: Arc::new(Nessuna) },
+                Strato { neuroni: larghezza, funzione_attivazione: Arc::new(Sigmoide) },
+                Strato { neuroni: primo.output.len(), funzione_attivazione: Arc::new(Sigmoide) },
+            ], tasso, seme.wrapping_add(larghezza as u64));
+            rete.addestra_epoche(addestramento, epoche, OpzioniAddestramento::default())?;
+            let accuratezza = rete.accuratezza(validazione, 0.5);
+            if accuratezza > migliore.1 {
+                migliore = (larghezza, accuratezza);
+            }
+        }
+        Ok(migliore.0)
+    }
+
+    /// Perdita della rete su un singolo esempio.
+    fn perdita_esempio(&self, set: &InputAddestramento) -> f64 {
+        let uscite = self.propagazione_avanti(&DVector::from_column_slice(&set.input));
+        self.perdita.calcola(&uscite[uscite.len() - 1], &DVector::from_column_slice(&set.output))
+    }
+
+    /// Interroga una rete addestrata con la perdita `NLLGaussiana` restituendo, per ogni target,
+    /// la media prevista e la deviazione standard (incertezza aleatoria), sempre positiva.
+    ///
+    /// # Argomenti
+    /// * `input` vettore dei dati in input
+    ///
+    /// # Ritorna
+    ///  vettore di coppie (media, deviazione standard), uno per target
+    pub fn elabora_con_incertezza(&self, input: Vec<f64>) -> Vec<(f64, f64)> {
+        let uscita = self.elabora(input);
+        let k = uscita.len() / 2;
+        (0..k).map(|j| (uscita[j], (0.5 * uscita[k + j]).exp())).collect()
+    }
+
+    /// Retropropagazione per aggiornare i pesi della rete neurale.
+    ///
+    /// # Argomenti
+    ///
+    /// * `propagazione` - Pre-attivazioni e uscite di ogni strato dalla propagazione in avanti.
+    /// * `target` - Il vettore dei valori target.
+    fn _retropropagazione(&mut self, propagazione: Propagazione, target: &DVector<f64>) {
+        let gradienti = self.gradienti(&propagazione, target);
+        self.applica_gradienti(&gradienti);
+    }
+
+    /// Calcola i gradienti della perdita rispetto ai pesi e ai bias di ogni strato, senza modificare la rete.
+    ///
+    /// # Argomenti
+    ///
+    /// * `propagazione` - Pre-attivazioni e uscite di ogni strato dalla propagazione in avanti.
+    /// * `target` - Il vettore dei valori target.
+    fn gradienti(&self, propagazione: &Propagazione, target: &DVector<f64>) -> Gradienti {
+        let uscite = &propagazione.uscite;
+        let gradiente_uscita = self.perdita.gradiente(&uscite[uscite.len() - 1], target);
+        self.gradienti_da_uscita(propagazione, &gradiente_uscita)
+    }
+
+    /// Come `gradienti`, partendo direttamente dal gradiente della perdita rispetto all'output della rete
+    /// (utile per perdite composte che non passano dalla funzione di perdita della rete).
+    fn gradienti_da_uscita(&self, propagazione: &Propagazione, gradiente_uscita: &DVector<f64>) -> Gradienti {
+        let ultimo = propagazione.uscite.len() - 1;
+        let funzione = self.attivazione_strato(ultimo);
+        let delta = funzione.retropropaga_vettore(
+            &propagazione.pre_attivazioni[ultimo], &propagazione.uscite[ultimo], &-gradiente_uscita
+        );
+        self.gradienti_da_delta(propagazione, delta, ultimo)
+    }
+
+    /// Retropropaga il `delta` dello strato di neuroni `strato` (errore moltiplicato per la derivata dell'attivazione)
+    /// verso l'input, restituendo il gradiente della perdita rispetto a ogni matrice dei pesi e vettore dei bias.
+    /// Gli strati dopo quello di partenza hanno gradiente nullo.
+    fn gradienti_da_delta(&self, propagazione: &Propagazione, mut delta: DVector<f64>, strato: usize) -> Gradienti {
+        let uscite = &propagazione.uscite;
+        let mut gradienti = Gradienti::zeri(&self.strati, &self.bias);
+        for (i, pesi) in self.strati[..strato].iter().enumerate().rev() {
+            if let Some(normalizzazione) = &self.batch_norm[i] {
+                // in inferenza la normalizzazione è una trasformazione affine fissa
+                delta.component_mul_assign(&normalizzazione.scala());
+            }
+            gradienti.pesi[i] = -(&delta * uscite[i].transpose());
+            gradienti.bias[i] = -&delta;
+
+            if i > 0 {
+                let funzione = self.attivazione_strato(i);
+                let errore = pesi.transpose() * &delta;
+                delta = funzione.retropropaga_vettore(&propagazione.pre_attivazioni[i], &uscite[i], &errore);
+            }
+        }
+        gradienti
+    }
+
+    /// Riassunto numerico dei gradienti di ogni strato di connessioni per un singolo esempio,
+    /// calcolati con la retropropagazione ma senza applicare l'aggiornamento dei pesi.
+    /// Serve a diagnosticare gradienti che svaniscono o esplodono strato per strato.
+    ///
+    /// # Argomenti
+    ///
+    /// * `input` - Vettore di input per la rete neurale.
+    /// * `target` - Vettore dei valori target.
+    ///
+    /// # Ritorna
+    ///
+    /// Le statistiche per ogni strato di connessioni, dall'input verso l'output.
+    pub fn statistiche_gradienti(&self, input: &[f64], target: &[f64]) -> Vec<StatGrad> {
+        let propagazione = self.propagazione_completa(&DVector::from_column_slice(input));
+        self.gradienti(&propagazione, &DVector::from_column_slice(target))
+            .pesi
+            .iter()
+            .map(|gradiente| StatGrad {
+                norma: gradiente.norm(),
+                min: gradiente.min(),
+                max: gradiente.max(),
+                media: gradiente.mean()
+            })
+            .collect()
+    }
+
+    /// Gradienti della perdita rispetto a ogni matrice dei pesi per un singolo esempio, calcolati con
+    /// propagazione in avanti e retropropagazione senza modificare la rete: separano il calcolo del gradiente
+    /// dall'aggiornamento, ad esempio per ottimizzatori esterni o per il controllo numerico dei gradienti.
+    ///
+    /// # Argomenti
+    ///
+    /// * `input` - Vettore di input per la rete neurale.
+    /// * `target` - Vettore dei valori target.
+    ///
+    /// # Ritorna
+    ///
+    /// Una matrice per strato di connessioni, con le stesse dimensioni dei pesi: un passo di discesa è `pesi -= tasso * gradiente`.
+    pub fn calcola_gradienti(&self, input: &[f64], target: &[f64]) -> Vec<DMatrix<f64>> {
+        let propagazione = self.propagazione_completa(&DVector::from_column_slice(input));
+        self.gradienti(&propagazione, &DVector::from_column_slice(target)).pesi
+    }
+
+    /// Confronta i gradienti analitici della retropropagazione con quelli stimati numericamente con la
+    /// differenza centrale `(L(θ + ε) - L(θ - ε)) / 2ε`, perturbando uno alla volta ogni peso e ogni bias.
+    /// È il controllo canonico della correttezza della retropropagazione dopo aver aggiunto nuove attivazioni
+    /// o perdite: per una rete corretta l'errore è molto piccolo (sotto 1e-5 con `epsilon` intorno a 1e-5).
+    ///
+    /// L'errore di ogni parametro è `|analitico - numerico| / max(|analitico| + |numerico|, 1)`: relativo per
+    /// gradienti grandi, assoluto per quelli piccoli, dove il rumore della differenza finita dominerebbe.
+    ///
+    /// # Argomenti
+    ///
+    /// * `input` - Vettore di input per la rete neurale.
+    /// * `target` - Vettore dei valori target.
+    /// * `epsilon` - Ampiezza della perturbazione.
+    ///
+    /// # Ritorna
+    ///
+    /// L'errore massimo su tutti i parametri della rete.
+    pub fn verifica_gradiente(&self, input: &[f64], target: &[f64], epsilon: f64) -> f64 {
+        let input = DVector::from_column_slice(input);
+        let target = DVector::from_column_slice(target);
+        let analitici = self.gradienti(&self.propagazione_completa(&input), &target);
+        let errore = |analitico: f64, numerico: f64| (analitico - numerico).abs() / (analitico.abs() + numerico.abs()).max(1.0);
+        let perdita = |rete: &ReteNeurale| {
+            let uscite = rete.propagazione_avanti(&input);
+            rete.perdita.calcola(&uscite[uscite.len() - 1], &target)
+        };
+
+        let mut copia = self.clone();
+        let mut massimo: f64 = 0.0;
+        for k in 0..self.strati.len() {
+            for indice in 0..self.strati[k].len() {
+                let originale = copia.strati[k][indice];
+                copia.strati[k][indice] = originale + epsilon;
+                let perdita_piu = perdita(&copia);
+                copia.strati[k][indice] = originale - epsilon;
+                let perdita_meno = perdita(&copia);
+                copia.strati[k][indice] = originale;
+                let numerico = (perdita_piu - perdita_meno) / (2.0 * epsilon);
+                massimo = massimo.max(errore(analitici.pesi[k][indice], numerico));
+            }
+            for indice in 0..self.bias[k].len() {
+                let originale = copia.bias[k][indice];
+                copia.bias[k][indice] = originale + epsilon;
+                let perdita_piu = perdita(&copia);
+                copia.bias[k][indice] = originale - epsilon;
+                let perdita_meno = perdita(&copia);
+                copia.bias[k][indice] = originale;
+                let numerico = (perdita_piu - perdita_meno) / (2.0 * epsilon);
+                massimo = massimo.max(errore(analitici.bias[k][indice], numerico));
+            }
+        }
+        massimo
+    }
+
+    /// Aggiorna pesi e bias con un passo dell'ottimizzatore della rete, saltando gli strati congelati.
+    /// Con il clipping impostato la norma del gradiente di ogni strato viene prima limitata.
+    /// Con la weight normalization la riparametrizzazione riguarda solo i pesi, non i bias.
+    fn applica_gradienti(&mut self, gradienti: &Gradienti) {
+        let limitati;
+        let gradienti = match self.clip_gradiente {
+            Some(massimo) => {
+                limitati = gradienti.limitati(massimo);
+                &limitati
+            }
+            None => gradienti,
+        };
+        let parametri_batch_norm = || self.batch_norm.iter().map(|batch_norm| batch_norm.as_ref().map_or(0, |batch_norm| 2 * batch_norm.neuroni()));
+        let compatibile = self.stato_ottimizzatore.as_ref().is_some_and(|stato| {
+            stato.primo.pesi.iter().map(|momento| momento.shape()).eq(self.strati.iter().map(|pesi| pesi.shape()))
+                && stato.primo_batch_norm.iter().map(|momento| momento.len()).eq(parametri_batch_norm())
+        });
+        if !compatibile {
+            self.stato_ottimizzatore = Some(StatoOttimizzatore {
+                primo: Gradienti::zeri(&self.strati, &self.bias),
+                secondo: Gradienti::zeri(&self.strati, &self.bias),
+                primo_batch_norm: parametri_batch_norm().map(DVector::zeros).collect(),
+                secondo_batch_norm: parametri_batch_norm().map(DVector::zeros).collect(),
+                passi: 0
+            });
+        }
+        let Some(stato) = self.stato_ottimizzatore.as_mut() else {
+            return;
+        };
+        stato.passi += 1;
+        for (i, (gradiente, gradiente_bias)) in gradienti.pesi.iter().zip(gradienti.bias.iter()).enumerate() {
+            if self.strati_congelati[i] {
+                continue;
+            }
+            let mut passo = DMatrix::zeros(gradiente.nrows(), gradiente.ncols());
+            self.ottimizzatore.passo(
+                self.tasso_apprendimento,
+                stato.passi,
+                gradiente.as_slice(),
+                stato.primo.pesi[i].as_mut_slice(),
+                stato.secondo.pesi[i].as_mut_slice(),
+                passo.as_mut_slice()
+            );
+            let mut passo_bias = DVector::zeros(gradiente_bias.len());
+            self.ottimizzatore.passo(
+                self.tasso_apprendimento,
+                stato.passi,
+                gradiente_bias.as_slice(),
+                stato.primo.bias[i].as_mut_slice(),
+                stato.secondo.bias[i].as_mut_slice(),
+                passo_bias.as_mut_slice()
+            );
+            if self.weight_norm {
+                Self::passo_weight_norm(&mut self.strati[i], &passo, 1.0);
+            } else {
+                self.strati[i] -= passo;
+            }
+            self.bias[i] -= passo_bias;
+            if self.lambda_l1 > 0.0 {
+                Self::passo_l1(&mut self.strati[i], self.tasso_apprendimento * self.lambda_l1);
+            }
+        }
+    }
+
+    /// Passo della regolarizzazione L1: avvicina ogni peso a zero di `soglia` (`tasso * lambda_l1 * sign(w)`),
+    /// senza oltrepassarlo. I pesi che arriverebbero dall'altra parte dello zero, o già nulli, restano a zero
+    /// invece di oscillare attorno allo zero.
+    fn passo_l1(pesi: &mut DMatrix<f64>, soglia: f64) {
+        for peso in pesi.iter_mut() {
+            *peso = peso.signum() * (peso.abs() - soglia).max(0.0);
+        }
+    }
+
+    /// Passo di discesa con weight normalization: ogni riga dei pesi (i pesi in ingresso a un neurone)
+    /// è riparametrizzata come `g * v` con `v` direzione di norma unitaria e `g` magnitudine,
+    /// e il gradiente viene scomposto e applicato separatamente alle due componenti.
+    fn passo_weight_norm(pesi: &mut DMatrix<f64>, gradiente: &DMatrix<f64>, tasso: f64) {
+        for (mut riga, riga_gradiente) in pesi.row_iter_mut().zip(gradiente.row_iter()) {
+            let g = riga.norm();
+            if g == 0.0 {
+                riga -= tasso * riga_gradiente;
+                continue;
+            }
+            let v = &riga / g;
+            let gradiente_g = riga_gradiente.dot(&v);
+            let gradiente_v = g * (riga_gradiente - gradiente_g * &v);
+
+            let nuova_g = g - tasso * gradiente_g;
+            let nuova_v = &v - tasso * gradiente_v;
+            let norma_v = nuova_v.norm();
+            if norma_v > 0.0 {
+                riga.copy_from(&(nuova_v * (nuova_g / norma_v)));
+            }
+        }
+    }
+
+    /// Addestra la rete neurale su un singolo esempio.
+    ///
+    /// # Argomenti
+    ///
+    /// * `input` - Vettore di input per la rete neurale.
+    /// * `target` - Vettore dei valori target per il training.
+    /// 
+    /// # Esempio
+    /// ```
+    ///  let dati_addestramento = [
+    ///     InputAddestramento {input:vec![0.0, 0.0], output: vec![0.0]},
+    ///     InputAddestramento {input:vec![0.0, 1.0], output: vec![1.0]},
+    ///     InputAddestramento {input:vec![1.0, 0.0], output: vec![1.0]},
+    ///     InputAddestramento {input:vec![1.0, 1.0], output: vec![0.0]}
+    /// ];
+    /// for _ in 0..1000000 {
+    ///     for set in dati_addestramento.iter() {
+    ///         rete.addestra(set.input.clone(), set.output.clone());
+    ///     }
+    /// }
+    /// ```
+    /// 
+    pub fn addestra(&mut self, input: Vec<f64>, target: Vec<f64>) {
+        
+        if self.dropconnect.iter().any(|&p| p > 0.0) {
+            let gradienti = self.gradienti_esempio(input, target);
+            return self.applica_gradienti(&gradienti);
+        }
+        let propagazione = self.propagazione_completa(&DVector::from_vec(input));
+        self._retropropagazione(propagazione, &DVector::from_vec(target));
+    }
+
+    /// Come `addestra`, ma dopo l'aggiornamento verifica che pesi e bias siano ancora finiti: se l'addestramento
+    /// diverge restituisce `ErroreRete::Divergenza` con il primo strato di connessioni con valori NaN o infiniti,
+    /// invece di propagare silenziosamente i NaN a tutte le previsioni successive.
+    ///
+    /// # Esempio
+    /// ```
+    /// for set in dati_addestramento.iter() {
+    ///     if let Err(errore) = rete.addestra_controllato(set.input.clone(), set.output.clone()) {
+    ///         eprintln!("{}", errore);
+    ///         break;
+    ///     }
+    /// }
+    /// ```
+    pub fn addestra_controllato(&mut self, input: Vec<f64>, target: Vec<f64>) -> Result<(), ErroreRete> {
+        self.addestra(input, target);
+        match self.strato_non_finito() {
+            Some(strato) => Err(ErroreRete::Divergenza { strato }),
+            None => Ok(()),
+        }
+    }
+
+    /// Il primo strato di connessioni con pesi o bias NaN o infiniti, se presente.
+    fn strato_non_finito(&self) -> Option<usize> {
+        self.strati.iter()
+            .zip(self.bias.iter())
+            .position(|(pesi, bias)| pesi.iter().chain(bias.iter()).any(|valore| !valore.is_finite()))
+    }
+
+    /// Addestra la rete su un mini-batch: i gradienti di tutti gli esempi vengono accumulati e i pesi
+    /// sono aggiornati una sola volta con il gradiente medio del batch. Con un batch di un solo esempio
+    /// il risultato è identico ad `addestra`. Per mantenere la stessa dinamica passando a batch più grandi
+    /// si può riscalare il tasso con `adatta_tasso_a_batch`.
+    ///
+    /// # Argomenti
+    ///
+    /// * `batch` - Gli esempi del mini-batch (un batch vuoto non modifica la rete).
+    pub fn addestra_batch(&mut self, batch: &[InputAddestramento]) {
+        if batch.len() > 1 && self.batch_norm.iter().any(Option::is_some) {
+            self.addestra_batch_norm(batch);
+            return;
+        }
+        let mut esempi = batch.iter();
+        let Some(primo) = esempi.next() else {
+            return;
+        };
+        let mut gradienti = self.gradienti_esempio(primo.input.clone(), primo.output.clone());
+        for set in esempi {
+            let gradienti_esempio = self.gradienti_esempio(set.input.clone(), set.output.clone());
+            gradienti.accumula(&gradienti_esempio);
+        }
+        gradienti.scala(1.0 / batch.len() as f64);
+        self.applica_gradienti(&gradienti);
+    }
+
+    /// Passo di `addestra_batch` con la batch normalization: il batch è propagato insieme, normalizzando
+    /// con le statistiche del batch, e la retropropagazione tiene conto della dipendenza di media e varianza
+    /// da tutti gli esempi. Pesi, bias, `γ` e `β` sono aggiornati con l'ottimizzatore della rete,
+    /// con momenti separati per ogni parametro. DropConnect non viene applicato.
+    fn addestra_batch_norm(&mut self, batch: &[InputAddestramento]) {
+        let campioni = batch.len();
+        let mut attivazioni = vec![DMatrix::from_fn(self.dimensioni_strati[0], campioni, |r, c| batch[c].input[r])];
+        let mut pre_attivazioni = vec![attivazioni[0].clone()];
+        let mut normalizzazioni = Vec::with_capacity(self.strati.len());
+        for k in 0..self.strati.len() {
+            let mut pre_attivazione = self.pre_attivazione_lineare(k, &attivazioni[k]);
+            let normalizzazione = match self.batch_norm[k].as_mut() {
+                Some(batch_norm) => {
+                    let (uscite, normalizzate, inverse) = batch_norm.avanti_batch(&pre_attivazione);
+                    pre_attivazione = uscite;
+                    Some((normalizzate, inverse))
+                }
+                None => None,
+            };
+            normalizzazioni.push(normalizzazione);
+            let funzione = self.attivazione_strato(k + 1);
+            attivazioni.push(Self::attiva_colonne(funzione.as_ref(), pre_attivazione.clone()));
+            pre_attivazioni.push(pre_attivazione);
+        }
+
+        // delta = -dL/d(pre-attivazione), già mediato sul batch
+        let ultimo = self.strati.len();
+        let mut delta = DMatrix::zeros(self.dimensioni_strati[ultimo], campioni);
+        for (c, set) in batch.iter().enumerate() {
+            let uscita = attivazioni[ultimo].column(c).clone_owned();
+            let gradiente = self.perdita.gradiente(&uscita, &DVector::from_column_slice(&set.output)) / campioni as f64;
+            let pre_attivazione = pre_attivazioni[ultimo].column(c).clone_owned();
+            delta.set_column(c, &self.attivazione_strato(ultimo).retropropaga_vettore(&pre_attivazione, &uscita, &-gradiente));
+        }
+        let mut gradienti = Gradienti::zeri(&self.strati, &self.bias);
+        let mut gradienti_normalizzazioni = vec![None; self.strati.len()];
+        for i in (0..self.strati.len()).rev() {
+            if let (Some(batch_norm), Some((normalizzate, inverse))) = (&self.batch_norm[i], &normalizzazioni[i]) {
+                let (gradiente_pre_attivazione, gradiente_gamma, gradiente_beta) =
+                    batch_norm.indietro_batch(&-&delta, normalizzate, inverse);
+                delta = -gradiente_pre_attivazione;
+                gradienti_normalizzazioni[i] = Some((gradiente_gamma, gradiente_beta));
+            }
+            gradienti.pesi[i] = -(&delta * attivazioni[i].transpose());
+            gradienti.bias[i] = -delta.column_sum();
+            if i > 0 {
+                let funzione = self.attivazione_strato(i);
+                let mut errore = self.strati[i].transpose() * &delta;
+                for c in 0..campioni {
+                    let delta_colonna = funzione.retropropaga_vettore(
+                        &pre_attivazioni[i].column(c).clone_owned(),
+                        &attivazioni[i].column(c).clone_owned(),
+                        &errore.column(c).clone_owned()
+                    );
+                    errore.set_column(c, &delta_colonna);
+                }
+                delta = errore;
+            }
+        }
+
+        self.applica_gradienti(&gradienti);
+        self.applica_gradienti_batch_norm(gradienti_normalizzazioni);
+    }
+
+    /// Aggiorna `γ` e `β` di ogni strato di batch normalization con l'ottimizzatore della rete.
+    /// Va chiamata dopo `applica_gradienti`, che crea lo stato dell'ottimizzatore e ne conta il passo.
+    fn applica_gradienti_batch_norm(&mut self, gradienti: Vec<Option<(DVector<f64>, DVector<f64>)>>) {
+        let Some(stato) = self.stato_ottimizzatore.as_mut() else {
+            return;
+        };
+        for (i, gradienti_normalizzazione) in gradienti.into_iter().enumerate() {
+            let (Some(batch_norm), Some((gradiente_gamma, gradiente_beta))) = (self.batch_norm[i].as_mut(), gradienti_normalizzazione) else {
+                continue;
+            };
+            if self.strati_congelati[i] {
+                continue;
+            }
+            let gradiente: Vec<f64> = gradiente_gamma.iter().chain(gradiente_beta.iter()).copied().collect();
+            let mut passo = vec![0.0; gradiente.len()];
+            self.ottimizzatore.passo(
+                self.tasso_apprendimento,
+                stato.passi,
+                &gradiente,
+                stato.primo_batch_norm[i].as_mut_slice(),
+                stato.secondo_batch_norm[i].as_mut_slice(),
+                &mut passo
+            );
+            let neuroni = batch_norm.neuroni();
+            batch_norm.gamma -= DVector::from_column_slice(&passo[..neuroni]);
+            batch_norm.beta -= DVector::from_column_slice(&passo[neuroni..]);
+        }
+    }
+
+    /// Un'epoca di addestramento a mini-batch: il set viene diviso, nell'ordine dato, in batch consecutivi
+    /// di `dimensione_batch` esempi (l'ultimo può essere più piccolo), ognuno addestrato con `addestra_batch`.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.adatta_tasso_a_batch(4);
+    /// for _ in 0..10000 {
+    ///     rete.addestra_per_batch(&dati_addestramento, 4);
+    /// }
+    /// ```
+    pub fn addestra_per_batch(&mut self, dati: &[InputAddestramento], dimensione_batch: usize) {
+        for batch in dati.chunks(dimensione_batch.max(1)) {
+            self.addestra_batch(batch);
+        }
+    }
+
+    /// Gradienti della perdita per un singolo esempio, come usati nell'addestramento
+    /// (con le maschere DropConnect se attivo).
+    fn gradienti_esempio(&mut self, input: Vec<f64>, target: Vec<f64>) -> Gradienti {
+        match self.maschere_dropconnect() {
+            Some(maschere) => self.gradienti_con_maschere(input, target, &maschere),
+            None => {
+                let propagazione = self.propagazione_completa(&DVector::from_vec(input));
+                self.gradienti(&propagazione, &DVector::from_vec(target))
+            }
+        }
+    }
+
+    /// Estrae una maschera DropConnect per ogni matrice dei pesi: ogni peso è azzerato con la probabilità
+    /// dello strato e i pesi mantenuti sono riscalati di `1 / (1 - p)`, così che l'input atteso
+    /// di ogni neurone resti quello dell'inferenza. `None` se DropConnect non è attivo su nessuno strato.
+    fn maschere_dropconnect(&mut self) -> Option<Vec<DMatrix<f64>>> {
+        if self.dropconnect.iter().all(|&p| p <= 0.0) {
+            return None;
+        }
+        let mut maschere = Vec::with_capacity(self.strati.len());
+        for (i, pesi) in self.strati.iter().enumerate() {
+            let p = self.dropconnect.get(i).copied().unwrap_or(0.0);
+            let rng = &mut self.rng;
+            maschere.push(DMatrix::from_fn(pesi.nrows(), pesi.ncols(), |_, _| {
+                if p > 0.0 && rng.gen::<f64>() < p { 0.0 } else { 1.0 / (1.0 - p) }
+            }));
+        }
+        Some(maschere)
+    }
+
+    /// Gradienti con i pesi mascherati da DropConnect: propagazione e retropropagazione usano
+    /// i pesi effettivi `W ⊙ M`, e per la regola della catena il gradiente rispetto a `W` è quello
+    /// rispetto ai pesi effettivi moltiplicato per la maschera (i pesi azzerati non vengono aggiornati).
+    fn gradienti_con_maschere(&mut self, input: Vec<f64>, target: Vec<f64>, maschere: &[DMatrix<f64>]) -> Gradienti {
+        let originali = self.strati.clone();
+        for (pesi, maschera) in self.strati.iter_mut().zip(maschere.iter()) {
+            pesi.component_mul_assign(maschera);
+        }
+        let propagazione = self.propagazione_completa(&DVector::from_vec(input));
+        let mut gradienti = self.gradienti(&propagazione, &DVector::from_vec(target));
+        for (gradiente, maschera) in gradienti.pesi.iter_mut().zip(maschere.iter()) {
+            gradiente.component_mul_assign(maschera);
+        }
+        self.strati = originali;
+        gradienti
+    }
+
+    /// Pre-addestramento (riscaldamento) su un sottoinsieme rappresentativo del set di addestramento.
+    /// Avvicina i pesi a una buona regione prima di proseguire sul dataset completo.
+    ///
+    /// Il sottoinsieme è campionato in modo stratificato: gli esempi sono raggruppati per classe
+    /// (argmax dell'output, o soglia 0.5 per output a singolo neurone) e da ogni classe
+    /// si estrae la stessa `frazione`, con almeno un esempio per classe.
+    ///
+    /// # Argomenti
+    ///
+    /// * `dati` - Il set di addestramento completo.
+    /// * `frazione` - Frazione del set da usare, nell'intervallo (0, 1].
+    /// * `epoche` - Numero di passaggi sul sottoinsieme.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.pre_addestra(&dati_addestramento, 0.25, 1000);
+    /// for _ in 0..1000000 {
+    ///     for set in dati_addestramento.iter() {
+    ///         rete.addestra(set.input.clone(), set.output.clone());
+    ///     }
+    /// }
+    /// ```
+    pub fn pre_addestra(&mut self, dati: &[InputAddestramento], frazione: f64, epoche: usize) {
+        let frazione = frazione.clamp(0.0, 1.0);
+        let mut classi: Vec<Vec<&InputAddestramento>> = Vec::new();
+        for set in dati.iter() {
+            let classe = classe_di(&set.output);
+            if classe >= classi.len() {
+                classi.resize(classe + 1, Vec::new());
+            }
+            classi[classe].push(set);
+        }
+
+        let mut sottoinsieme: Vec<&InputAddestramento> = Vec::new();
+        for esempi in classi.iter_mut().filter(|esempi| !esempi.is_empty()) {
+            esempi.shuffle(&mut self.rng);
+            let quanti = ((esempi.len() as f64 * frazione).ceil() as usize).max(1);
+            sottoinsieme.extend(esempi.iter().take(quanti));
+        }
+
+        for _ in 0..epoche {
+            for set in sottoinsieme.iter() {
+                self.addestra(set.input.clone(), set.output.clone());
+            }
+        }
+    }
+
+    /// Addestra la rete per un numero di epoche sull'intero set di addestramento,
+    /// visitando tutti gli esempi a ogni epoca.
+    ///
+    /// Con `opzioni.durata_massima` il ciclo si interrompe quando il tempo trascorso supera il limite;
+    /// l'interruzione avviene sempre tra due epoche, così la rete resta in uno stato consistente.
+    ///
+    /// Con `opzioni.seme` il generatore casuale della rete viene riseminato prima di iniziare:
+    /// a parità di rete di partenza, dati e seme l'addestramento è completamente deterministico.
+    ///
+    /// Con `opzioni.rollback` un'epoca che peggiora la perdita di addestramento oltre la tolleranza viene annullata
+    /// e il tasso di apprendimento ridotto, anche insieme a uno scheduler; il tasso ridotto resta impostato sulla rete al termine.
+    ///
+    /// Con `opzioni.checkpoint` la rete viene salvata su file ogni volta che la metrica del checkpoint
+    /// migliora; un errore di salvataggio interrompe l'addestramento al termine dell'epoca e viene restituito,
+    /// lasciando la rete nello stato raggiunto.
+    ///
+    /// Con la feature `ctrlc` (solo Unix) e `opzioni.interrompi_con_ctrl_c`, un Ctrl-C ferma l'addestramento
+    /// al termine dell'epoca corrente invece di terminare il processo; la rete resta nello stato raggiunto,
+    /// pronta per essere salvata dal chiamante.
+    ///
+    /// Con `opzioni.mescola` gli esempi vengono visitati in un ordine casuale diverso a ogni epoca.
+    ///
+    /// Con `opzioni.progresso` il callback riceve il numero e la perdita di ogni epoca, e può interrompere l'addestramento.
+    ///
+    /// Con `opzioni.interruzione` l'addestramento si ferma all'inizio della prima epoca in cui il flag è `true`,
+    /// restituendo le perdite delle epoche completate fino a quel momento.
+    ///
+    /// # Argomenti
+    ///
+    /// * `dati` - Il set di addestramento.
+    /// * `epoche` - Numero massimo di epoche.
+    /// * `opzioni` - Opzioni dell'addestramento.
+    ///
+    /// # Ritorna
+    ///
+    /// La perdita media sul set di addestramento alla fine di ogni epoca completata
+    /// (la sua lunghezza è il numero di epoche effettivamente completate),
+    /// oppure l'errore di salvataggio del checkpoint o di installazione/ripristino dell'handler di Ctrl-C.
+    ///
+    /// # Esempio
+    /// ```
+    /// let opzioni = OpzioniAddestramento {
+    ///     durata_massima: Some(Duration::from_secs(60)),
+    ///     mescola: true,
+    ///     ..Default::default()
+    /// };
+    /// let perdite = rete.addestra_epoche(&dati_addestramento, 1000000, opzioni)?;
+    /// println!("{} epoche, perdita finale {:?}", perdite.len(), perdite.last());
+    ///
+    /// let opzioni = OpzioniAddestramento {
+    ///     progresso: Some(Box::new(|epoca, perdita| {
+    ///         println!("epoca {}: perdita {}", epoca, perdita);
+    ///         perdita > 1e-4 // si ferma appena la perdita è abbastanza bassa
+    ///     })),
+    ///     ..Default::default()
+    /// };
+    /// rete.addestra_epoche(&dati_addestramento, 1000, opzioni)?;
+    ///
+    /// let interruzione = Arc::new(AtomicBool::new(false));
+    /// let opzioni = OpzioniAddestramento { interruzione: Some(interruzione.clone()), ..Default::default() };
+    /// // da un altro thread: interruzione.store(true, Ordering::Relaxed);
+    /// rete.addestra_epoche(&dati_addestramento, 1000000, opzioni)?;
+    /// ```
+    pub fn addestra_epoche(&mut self, dati: &[InputAddestramento], epoche: usize, opzioni: OpzioniAddestramento) -> Result<Vec<f64>, Error> {
+        if let Some(seme) = opzioni.seme {
+            self.imposta_seme(seme);
+        }
+        #[cfg(all(unix, feature = "ctrlc"))]
+        let gestore_ctrl_c = if opzioni.interrompi_con_ctrl_c { Some(ctrl_c::GestoreCtrlC::installa()?) } else { None };
+
+        let mut checkpoint = opzioni.checkpoint;
+        let mut progresso = opzioni.progresso;
+        let mut migliore = f64::NEG_INFINITY;
+        let mut precedente = opzioni.rollback.map(|_| (self.stato_addestrabile(), self.perdita_media(dati)));
+        let tasso_iniziale = self.tasso_apprendimento;
+        let mut fattore_rollback = 1.0;
+
+        let inizio = Instant::now();
+        let mut perdite = Vec::new();
+        let mut ordine: Vec<usize> = (0..dati.len()).collect();
+        for epoca in 0..epoche {
+            if opzioni.interruzione.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+                break;
+            }
+            let tasso = opzioni.scheduler.as_ref().map_or(tasso_iniziale, |scheduler| scheduler.tasso(epoca));
+            self.tasso_apprendimento = tasso * fattore_rollback;
+            if opzioni.mescola {
+                ordine.shuffle(&mut self.rng);
+            }
+            for &i in ordine.iter() {
+                self.addestra(dati[i].input.clone(), dati[i].output.clone());
+            }
+
+            let mut perdita = self.perdita_media(dati);
+            if let (Some(rollback), Some((stato_precedente, perdita_precedente))) = (opzioni.rollback, precedente.as_mut()) {
+                if perdita > *perdita_precedente * (1.0 + rollback.tolleranza) || !perdita.is_finite() {
+                    self.ripristina_stato_addestrabile(stato_precedente);
+                    fattore_rollback *= rollback.riduzione;
+                    self.tasso_apprendimento *= rollback.riduzione;
+                    perdita = *perdita_precedente;
+                } else {
+                    *stato_precedente = self.stato_addestrabile();
+                    *perdita_precedente = perdita;
+                }
+            }
+            perdite.push(perdita);
+
+            if let Some(checkpoint) = checkpoint.as_mut() {
+                let valore = (checkpoint.metrica)(self);
+                if valore > migliore {
+                    migliore = valore;
+                    if let Err(e) = self.salva_pesi_txt(&checkpoint.file_path) {
+                        return Err(Error::new(e.kind(), format!("checkpoint non salvato in {}: {}", checkpoint.file_path, e)));
+                    }
+                }
+            }
+
+            if let Some(progresso) = progresso.as_mut() {
+                if !progresso(epoca, perdita) {
+                    break;
+                }
+            }
+
+            #[cfg(all(unix, feature = "ctrlc"))]
+            if gestore_ctrl_c.as_ref().is_some_and(|gestore| gestore.interrotto()) {
+                break;
+            }
+
+            if let Some(durata_massima) = opzioni.durata_massima {
+                if inizio.elapsed() >= durata_massima {
+                    break;
+                }
+            }
+        }
+        #[cfg(all(unix, feature = "ctrlc"))]
+        if let Some(gestore) = gestore_ctrl_c {
+            gestore.ripristina()?;
+        }
+        Ok(perdite)
+    }
+
+    fn stato_addestrabile(&self) -> StatoAddestrabile {
+        StatoAddestrabile {
+            strati: self.strati.clone(),
+            bias: self.bias.clone(),
+            batch_norm: self.batch_norm.clone(),
+            stato_ottimizzatore: self.stato_ottimizzatore.clone()
+        }
+    }
+
+    fn ripristina_stato_addestrabile(&mut self, stato: &StatoAddestrabile) {
+        self.strati.clone_from(&stato.strati);
+        self.bias.clone_from(&stato.bias);
+        self.batch_norm.clone_from(&stato.batch_norm);
+        self.stato_ottimizzatore.clone_from(&stato.stato_ottimizzatore);
+    }
+
+    /// Addestra la rete con early stopping: dopo ogni epoca sul set di addestramento calcola la perdita
+    /// sul set di validazione e si ferma quando non migliora per `pazienza` epoche consecutive,
+    /// ripristinando i pesi e i bias migliori visti (anche quelli iniziali, se nessuna epoca li migliora).
+    ///
+    /// # Argomenti
+    ///
+    /// * `addestramento` - Il set di addestramento.
+    /// * `validazione` - Il set di validazione, usato solo per decidere quando fermarsi.
+    /// * `pazienza` - Epoche consecutive senza miglioramento tollerate prima di fermarsi.
+    /// * `max_epoche` - Numero massimo di epoche.
+    ///
+    /// # Ritorna
+    ///
+    /// L'epoca migliore (0 = rete iniziale) e la relativa perdita di validazione.
+    ///
+    /// # Esempio
+    /// ```
+    /// let (epoca, perdita) = rete.addestra_con_early_stopping(&dati_addestramento, &dati_validazione, 20, 10000);
+    /// println!("migliore all'epoca {}: perdita di validazione {}", epoca, perdita);
+    /// ```
+    pub fn addestra_con_early_stopping(
+        &mut self,
+        addestramento: &[InputAddestramento],
+        validazione: &[InputAddestramento],
+        pazienza: usize,
+        max_epoche: usize
+    ) -> (usize, f64) {
+        let mut migliore = (0, self.perdita_media(validazione));
+        let mut pesi_migliori = (self.strati.clone(), self.bias.clone());
+        for epoca in 1..=max_epoche {
+            for set in addestramento.iter() {
+                self.addestra(set.input.clone(), set.output.clone());
+            }
+            let perdita = self.perdita_media(validazione);
+            if perdita < migliore.1 {
+                migliore = (epoca, perdita);
+                pesi_migliori.0.clone_from(&self.strati);
+                pesi_migliori.1.clone_from(&self.bias);
+            } else if epoca - migliore.0 >= pazienza {
+                break;
+            }
+        }
+        self.strati = pesi_migliori.0;
+        self.bias = pesi_migliori.1;
+        migliore
+    }
+
+    /// Addestra la rete come autoencoder, usando ogni input anche come target (ricostruzione),
+    /// senza dover costruire a mano gli `InputAddestramento` con input uguale all'output.
+    ///
+    /// # Argomenti
+    ///
+    /// * `dati` - Gli input da ricostruire.
+    /// * `epoche` - Numero di passaggi sull'intero set.
+    ///
+    /// # Ritorna
+    ///
+    /// Un errore se lo strato di output ha dimensione diversa dall'input
+    /// o se un esempio non ha la dimensione dell'input.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut autoencoder = ReteNeurale::nuova(vec![
+    ///     Strato { neuroni: 4, funzione_attivazione: Arc::new(Nessuna) },
+    ///     Strato { neuroni: 2, funzione_attivazione: Arc::new(Tanh) },
+    ///     Strato { neuroni: 4, funzione_attivazione: Arc::new(Lineare) },
+    /// ], 0.05);
+    /// autoencoder.addestra_autoencoder(&campioni, 500)?;
+    /// println!("{:?}", autoencoder.elabora(campioni[0].clone())); // ~ campioni[0]
+    /// ```
+    pub fn addestra_autoencoder(&mut self, dati: &[Vec<f64>], epoche: usize) -> Result<(), Error> {
+        let dimensione = self.dimensioni_strati[0];
+        if self.dimensioni_strati.last() != Some(&dimensione) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("un autoencoder richiede output uguale all'input: {} neuroni di input, {:?} di output",
+                    dimensione, self.dimensioni_strati.last())
+            ));
+        }
+        if let Some(input) = dati.iter().find(|input| input.len() != dimensione) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("esempio di dimensione {} invece di {}", input.len(), dimensione)
+            ));
+        }
+        for _ in 0..epoche {
+            for input in dati.iter() {
+                self.addestra(input.clone(), input.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Addestra la rete su dati provenienti da più sorgenti (ad esempio domini diversi), campionando
+    /// a ogni epoca secondo il `bilanciamento` scelto così che la sorgente più grande non domini.
+    ///
+    /// Ogni epoca contiene tanti esempi quanti sono in totale nelle sorgenti: con `BilanciamentoSorgenti::Uguale`
+    /// sono divisi in parti uguali tra le sorgenti non vuote, scorrendo ogni sorgente in ordine casuale
+    /// e ricominciando quando è esaurita. Gli esempi delle varie sorgenti vengono poi mescolati tra loro.
+    ///
+    /// # Argomenti
+    ///
+    /// * `sorgenti` - I set di addestramento delle singole sorgenti.
+    /// * `epoche` - Numero di epoche.
+    /// * `bilanciamento` - Ripartizione degli esempi tra le sorgenti.
+    ///
+    /// # Ritorna
+    ///
+    /// Il numero di esempi usati da ciascuna sorgente, su tutte le epoche.
+    ///
+    /// # Esempio
+    /// ```
+    /// let usati = rete.addestra_multi_sorgente(&[&dati_reali, &dati_sintetici], 100, BilanciamentoSorgenti::Uguale);
+    /// println!("Esempi per sorgente: {:?}", usati);
+    /// ```
+    pub fn addestra_multi_sorgente(
+        &mut self,
+        sorgenti: &[&[InputAddestramento]],
+        epoche: usize,
+        bilanciamento: BilanciamentoSorgenti
+    ) -> Vec<usize> {
+        let totale: usize = sorgenti.iter().map(|sorgente| sorgente.len()).sum();
+        let non_vuote = sorgenti.iter().filter(|sorgente| !sorgente.is_empty()).count();
+        let mut usati = vec![0; sorgenti.len()];
+        if non_vuote == 0 {
+            return usati;
+        }
+
+        for _ in 0..epoche {
+            let mut epoca: Vec<(usize, usize)> = Vec::with_capacity(totale);
+            for (s, sorgente) in sorgenti.iter().enumerate().filter(|(_, sorgente)| !sorgente.is_empty()) {
+                let quota = match bilanciamento {
+                    BilanciamentoSorgenti::Uguale => totale / non_vuote,
+                    BilanciamentoSorgenti::Proporzionale => sorgente.len(),
+                };
+                let mut indici: Vec<usize> = Vec::new();
+                while indici.len() < quota {
+                    let mut giro: Vec<usize> = (0..sorgente.len()).collect();
+                    giro.shuffle(&mut self.rng);
+                    indici.extend(giro.into_iter().take(quota - indici.len()));
+                }
+                usati[s] += indici.len();
+                epoca.extend(indici.into_iter().map(|i| (s, i)));
+            }
+            epoca.shuffle(&mut self.rng);
+
+            for (s, i) in epoca {
+                let set = &sorgenti[s][i];
+                self.addestra(set.input.clone(), set.output.clone());
+            }
+        }
+        usati
+    }
+
+    /// Addestra la rete (allieva) con knowledge distillation da una rete insegnante, per comprimere
+    /// una rete grande in una più piccola. Per ogni esempio la perdita combina:
+    /// * con peso `alpha`, la perdita della rete rispetto alle etichette vere;
+    /// * con peso `1 - alpha`, la divergenza KL tra gli output soft dell'insegnante e dell'allieva,
+    ///   cioè la softmax con `temperatura` dei rispettivi output (trattati come logits), moltiplicata
+    ///   per `temperatura²` così che il suo gradiente resti confrontabile al variare della temperatura.
+    ///
+    /// Il termine soft ha senso per output a più neuroni (classificazione multi-classe):
+    /// con un solo neurone di output la softmax è costante e contribuisce solo la perdita sulle etichette.
+    ///
+    /// # Argomenti
+    ///
+    /// * `insegnante` - La rete già addestrata da imitare (non viene modificata).
+    /// * `dati` - Il set di addestramento, visitato una volta.
+    /// * `temperatura` - Temperatura della softmax (> 0): valori alti ammorbidiscono gli output.
+    /// * `alpha` - Peso della perdita sulle etichette vere, in [0, 1].
+    ///
+    /// # Ritorna
+    ///
+    /// La perdita combinata media sul set, prima di ogni aggiornamento.
+    ///
+    /// # Panics
+    ///
+    /// Se le due reti hanno un numero diverso di neuroni di output.
+    ///
+    /// # Esempio
+    /// ```
+    /// for _ in 0..1000 {
+    ///     allieva.addestra_distillazione(&insegnante, &dati_addestramento, 4.0, 0.3);
+    /// }
+    /// ```
+    pub fn addestra_distillazione(&mut self, insegnante: &ReteNeurale, dati: &[InputAddestramento], temperatura: f64, alpha: f64) -> f64 {
+        assert_eq!(
+            self.dimensioni_strati.last(), insegnante.dimensioni_strati.last(),
+            "allieva e insegnante devono avere lo stesso numero di neuroni di output"
+        );
+        let mut perdita_totale = 0.0;
+        for set in dati.iter() {
+            let propagazione = self.propagazione_completa(&DVector::from_vec(set.input.clone()));
+            let uscita = &propagazione.uscite[propagazione.uscite.len() - 1];
+            let target = DVector::from_vec(set.output.clone());
+            let soft_insegnante = softmax(&insegnante.elabora(set.input.clone()), temperatura);
+            let soft_allieva = softmax(uscita.as_slice(), temperatura);
+
+            let divergenza: f64 = soft_insegnante.iter().zip(soft_allieva.iter())
+                .filter(|(&p, _)| p > 0.0)
+                .map(|(&p, &q)| p * (p / q.max(f64::MIN_POSITIVE)).ln())
+                .sum();
+            perdita_totale += alpha * self.perdita.calcola(uscita, &target)
+                + (1.0 - alpha) * temperatura * temperatura * divergenza;
+
+            // d(T² KL)/dz = T (q - p)
+            let gradiente_soft = DVector::from_iterator(
+                soft_allieva.len(),
+                soft_allieva.iter().zip(soft_insegnante.iter()).map(|(q, p)| temperatura * (q - p))
+            );
+            let gradiente_uscita = alpha * self.perdita.gradiente(uscita, &target) + (1.0 - alpha) * gradiente_soft;
+            let gradienti = self.gradienti_da_uscita(&propagazione, &gradiente_uscita);
+            self.applica_gradienti(&gradienti);
+        }
+        if dati.is_empty() { 0.0 } else { perdita_totale / dati.len() as f64 }
+    }
+
+    /// Addestra la rete su una coppia di esempi con una perdita contrastiva (metric learning):
+    /// le rappresentazioni nell'ultimo strato nascosto (embedding) vengono avvicinate se la coppia è
+    /// simile, allontanate fino almeno al margine `MARGINE_CONTRASTIVO` se dissimile.
+    ///
+    /// La perdita vale `½ d²` per coppie simili e `½ max(0, margine - d)²` per coppie dissimili,
+    /// con `d` distanza euclidea tra gli embedding. Lo strato di output non viene aggiornato.
+    ///
+    /// # Argomenti
+    ///
+    /// * `a`, `b` - Input dei due esempi della coppia.
+    /// * `simili` - Se la coppia è etichettata come simile.
+    ///
+    /// # Ritorna
+    ///
+    /// La perdita contrastiva prima dell'aggiornamento, oppure un errore se la rete non ha strati nascosti
+    /// (senza embedding non ci sarebbe nulla da addestrare).
+    pub fn addestra_coppia(&mut self, a: Vec<f64>, b: Vec<f64>, simili: bool) -> Result<f64, Error> {
+        if self.strati.len() < 2 {
+            return Err(Error::new(ErrorKind::InvalidInput, "la perdita contrastiva richiede almeno uno strato nascosto"));
+        }
+        let strato = self.strati.len() - 1; // ultimo strato nascosto
+        let propagazione_a = self.propagazione_completa(&DVector::from_vec(a));
+        let propagazione_b = self.propagazione_completa(&DVector::from_vec(b));
+        let differenza = &propagazione_a.uscite[strato] - &propagazione_b.uscite[strato];
+        let distanza = differenza.norm();
+
+        let (perdita, gradiente_a) = if simili {
+            (0.5 * distanza * distanza, differenza)
+        } else if distanza < MARGINE_CONTRASTIVO && distanza > 0.0 {
+            let scarto = MARGINE_CONTRASTIVO - distanza;
+            (0.5 * scarto * scarto, differenza * (-scarto / distanza))
+        } else {
+            (0.5 * (MARGINE_CONTRASTIVO - distanza).max(0.0).powi(2), DVector::zeros(differenza.len()))
+        };
+
+        let funzione = self.attivazione_strato(strato).clone();
+        let delta_a = funzione.retropropaga_vettore(
+            &propagazione_a.pre_attivazioni[strato], &propagazione_a.uscite[strato], &-&gradiente_a
+        );
+        let delta_b = funzione.retropropaga_vettore(
+            &propagazione_b.pre_attivazioni[strato], &propagazione_b.uscite[strato], &gradiente_a
+        );
+        let mut gradienti = self.gradienti_da_delta(&propagazione_a, delta_a, strato);
+        gradienti.accumula(&self.gradienti_da_delta(&propagazione_b, delta_b, strato));
+        self.applica_gradienti(&gradienti);
+        Ok(perdita)
+    }
+
+    /// Salva i pesi della rete neurale in un file di testo.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file di testo in cui salvare i pesi; le directory intermedie
+    ///   mancanti vengono create.
+    ///
+    /// # Ritorna
+    ///
+    /// Un `Result` che indica se l'operazione ha avuto successo o meno (errore anche se il percorso è una directory).
+    pub fn salva_pesi_txt(&self, file_path: &str) -> Result<(), Error> {
+        let mut file = crea_file(file_path)?;
+        self.scrivi_intestazione_txt(&mut file)?;
+
+        for (strato_indice, (strato, bias)) in self.strati.iter().zip(self.bias.iter()).enumerate() {
+            
+            for riga in strato.row_iter() {
+                writeln!(file, "{}", self.testo_numeri(riga.iter()))?;
+            }
+            writeln!(file, "{}{}", _FILE_BIAS, self.testo_bias(bias))?; // Bias dei neuroni dello strato
+            if let Some(normalizzazione) = &self.batch_norm[strato_indice] {
+                writeln!(file, "{}{}", _FILE_BATCH_NORM, self.testo_numeri(normalizzazione.parametri().iter()))?;
+            }
+            writeln!(file, "{}", _FILE_STRATO )?; // Separatore di strato
+        }
+
+        Ok(())
+    }
+
+    /// Bias di uno strato come valori separati da spazi, per i formati di testo.
+    fn testo_bias(&self, bias: &DVector<f64>) -> String {
+        self.testo_numeri(bias.iter())
+    }
+
+    /// Valori separati da spazi con le cifre significative impostate con `imposta_cifre_significative`.
+    fn testo_numeri<'a>(&self, valori: impl Iterator<Item = &'a f64>) -> String {
+        valori
+            .map(|valore| match self.cifre_significative {
+                Some(cifre) => format!("{:.*e}", cifre - 1, valore),
+                None => valore.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Legge un valore numerico della riga `riga` di un file di testo.
+    fn numero_da_testo(testo: &str, riga: usize) -> Result<f64, ErroreRete> {
+        testo.parse::<f64>()
+            .map_err(|_| ErroreRete::ValoreNonNumerico { riga, valore: testo.to_string() })
+    }
+
+    /// Legge la funzione di perdita di una riga `[l] ` (sigla ed eventuale parametro).
+    fn perdita_da_testo(testo: &str, riga: usize) -> Result<Arc<dyn FunzionePerdita + Send + Sync>, ErroreRete> {
+        let mut parti = testo.split_whitespace();
+        let sigla = parti.next()
+            .ok_or_else(|| ErroreRete::RigaMalformata { riga, contenuto: testo.to_string() })?;
+        let parametro = match parti.next() {
+            Some(valore) => Self::numero_da_testo(valore, riga)?,
+            None => 0.0,
+        };
+        perdita_da_sigla(sigla, parametro).ok_or_else(|| ErroreRete::SiglaSconosciuta(sigla.to_string()))
+    }
+
+    /// Legge l'ottimizzatore di una riga `[o] ` (sigla e parametri).
+    fn ottimizzatore_da_testo(testo: &str, riga: usize) -> Result<Ottimizzatore, ErroreRete> {
+        let mut parti = testo.split_whitespace();
+        let sigla = parti.next()
+            .ok_or_else(|| ErroreRete::RigaMalformata { riga, contenuto: testo.to_string() })?;
+        let parametri = parti.map(|valore| Self::numero_da_testo(valore, riga)).collect::<Result<Vec<f64>, _>>()?;
+        Ottimizzatore::da_sigla(sigla, &parametri).ok_or_else(|| ErroreRete::SiglaSconosciuta(testo.trim().to_string()))
+    }
+
+    /// Legge i valori numerici separati da spazi della riga `riga` di un file di testo.
+    fn numeri_da_testo(testo: &str, riga: usize) -> Result<Vec<f64>, ErroreRete> {
+        testo.split_whitespace()
+            .map(|valore| Self::numero_da_testo(valore, riga))
+            .collect()
+    }
+
+    /// Legge i bias scritti da `testo_bias`.
+    fn bias_da_testo(testo: &str) -> Result<DVector<f64>, Error> {
+        let valori = testo.split_whitespace()
+            .map(|valore| valore.parse::<f64>()
+                .map_err(|_| Error::new(ErrorKind::InvalidData, format!("bias non numerico: {}", valore))))
+            .collect::<Result<Vec<f64>, Error>>()?;
+        Ok(DVector::from_vec(valori))
+    }
+
+    /// Scrive le righe di intestazione comuni ai formati di testo: tasso di apprendimento,
+    /// funzioni di attivazione (con il parametro `alfa` quando non nullo), funzione di perdita e ottimizzatore
+    /// (sigla seguita dagli eventuali parametri) e dimensioni degli strati.
+    fn scrivi_intestazione_txt(&self, file: &mut File) -> Result<(), Error> {
+        writeln!( file, "{} {}",_FILE_INFO_APPRENDIMENTO, self.tasso_apprendimento )?;
+        
+        let mut nomi_funz_attivazione = String::new();
+        
+        for funzione_attivazione in self.funzioni_attivazione.clone().into_iter()  {
+            if funzione_attivazione.alfa() == 0.0 {
+                nomi_funz_attivazione += &(funzione_attivazione.sigla().to_string()+ "; ");
+            }else{
+                nomi_funz_attivazione += &(funzione_attivazione.sigla().to_string()+ "_" + funzione_attivazione.alfa().to_string().as_str() +"; ");
+            }
+        }
+        writeln!( file, "{} {}",_FILE_INFO_ATTIVAZIONE, nomi_funz_attivazione.as_str())?;
+        if self.perdita.parametro() != 0.0 {
+            writeln!(file, "{} {} {}", _FILE_PERDITA, self.perdita.sigla(), self.perdita.parametro())?;
+        } else {
+            writeln!(file, "{} {}", _FILE_PERDITA, self.perdita.sigla())?;
+        }
+        let parametri_ottimizzatore: Vec<String> = self.ottimizzatore.parametri().iter().map(|p| p.to_string()).collect();
+        writeln!(file, "{} {} {}", _FILE_OTTIMIZZATORE, self.ottimizzatore.sigla(), parametri_ottimizzatore.join(" "))?;
+        writeln!( file, "{} {}", 
+            _FILE_INFO_RETE, 
+            format!("{:?}", self.dimensioni_strati ).replace("[", "").replace("]", "")
+        )
+    }
+
+    /// Salva la rete in un formato di testo stabile pensato per il versionamento (ad esempio in git):
+    /// dopo la stessa intestazione di `salva_pesi_txt`, ogni peso occupa una riga `strato riga colonna valore`
+    /// e ogni bias una riga `b strato neurone valore`, sempre nello stesso ordine. Modificare un peso cambia solo la sua riga, per cui i diff tra
+    /// versioni del modello restano leggibili e localizzati. Gli strati con batch normalization hanno in più una riga
+    /// `[n] strato` seguita da `γ`, `β`, media e varianza mobili. I valori sono scritti con la rappresentazione
+    /// più corta che li rilegge esattamente.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file di testo.
+    pub fn salva_diff(&self, file_path: &str) -> Result<(), Error> {
+        let mut file = File::create(file_path)?;
+        self.scrivi_intestazione_txt(&mut file)?;
+        for (k, strato) in self.strati.iter().enumerate() {
+            for (r, riga) in strato.row_iter().enumerate() {
+                for (c, valore) in riga.iter().enumerate() {
+                    writeln!(file, "{} {} {} {}", k, r, c, valore)?;
+                }
+            }
+            for (r, valore) in self.bias[k].iter().enumerate() {
+                writeln!(file, "b {} {} {}", k, r, valore)?;
+            }
+            if let Some(normalizzazione) = &self.batch_norm[k] {
+                let parametri: Vec<String> = normalizzazione.parametri().iter().map(|valore| valore.to_string()).collect();
+                writeln!(file, "{}{} {}", _FILE_BATCH_NORM, k, parametri.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Crea una rete da un file salvato con `salva_diff`. Ogni peso e ogni bias deve comparire esattamente una volta,
+    /// con coordinate coerenti con le dimensioni degli strati dichiarate nell'intestazione.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file di testo.
+    pub fn da_diff(file_path: &str) -> Result<Self, Error> {
+        let non_valido = |messaggio: String| Error::new(ErrorKind::InvalidData, messaggio);
+        let reader = BufReader::new(File::open(file_path)?);
+        let mut tasso_apprendimento: Option<f64> = None;
+        let mut funzioni_attivazione: Vec<Arc<dyn FunzioneAttivazione + Send + Sync>> = Vec::new();
+        let mut dimensioni_strati: Vec<usize> = Vec::new();
+        let mut strati: Vec<DMatrix<f64>> = Vec::new();
+        let mut assegnati: Vec<DMatrix<bool>> = Vec::new();
+        let mut bias: Vec<DVector<f64>> = Vec::new();
+        let mut bias_assegnati: Vec<DVector<bool>> = Vec::new();
+        let mut batch_norm: Vec<Option<BatchNorm>> = Vec::new();
+        let mut perdita: Arc<dyn FunzionePerdita + Send + Sync> = Arc::new(ErroreQuadraticoMedio);
+        let mut ottimizzatore = Ottimizzatore::SGD;
+
+        for (indice, line) in reader.lines().enumerate() {
+            let linea = line?;
+            if let Some(testo) = linea.strip_prefix(_FILE_PERDITA) {
+                perdita = Self::perdita_da_testo(testo, indice + 1)?;
+            } else if let Some(testo) = linea.strip_prefix(_FILE_OTTIMIZZATORE) {
+                ottimizzatore = Self::ottimizzatore_da_testo(testo, indice + 1)?;
+            } else if let Some(tasso) = linea.strip_prefix(_FILE_INFO_APPRENDIMENTO) {
+                tasso_apprendimento = Some(tasso.trim().parse::<f64>()
+                    .map_err(|_| non_valido(format!("tasso di apprendimento non valido: {}", tasso.trim())))?);
+            } else if let Some(nomi) = linea.strip_prefix(_FILE_INFO_ATTIVAZIONE) {
+                for nome in nomi.split(';').map(str::trim).filter(|nome| !nome.is_empty()) {
+                    let (sigla, alfa) = match nome.split_once('_') {
+                        Some((sigla, alfa)) => (sigla, alfa.parse::<f64>()
+                            .map_err(|_| non_valido(format!("parametro non valido: {}", nome)))?),
+                        None => (nome, 0.0),
+                    };
+                    funzioni_attivazione.push(funzione_da_sigla(sigla, alfa)
+                        .ok_or_else(|| non_valido(format!("funzione di attivazione sconosciuta: {}", sigla)))?);
+                }
+            } else if let Some(dimensioni) = linea.strip_prefix(_FILE_INFO_RETE) {
+                dimensioni_strati = dimensioni.trim()
+                    .split(", ")
+                    .map(|cifra| cifra.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| non_valido("dimensioni degli strati non valide".to_string()))?;
+                strati = dimensioni_strati.windows(2).map(|coppia| DMatrix::zeros(coppia[1], coppia[0])).collect();
+                assegnati = dimensioni_strati.windows(2).map(|coppia| DMatrix::from_element(coppia[1], coppia[0], false)).collect();
+                bias = dimensioni_strati.iter().skip(1).map(|&neuroni| DVector::zeros(neuroni)).collect();
+                bias_assegnati = dimensioni_strati.iter().skip(1).map(|&neuroni| DVector::from_element(neuroni, false)).collect();
+                batch_norm = vec![None; strati.len()];
+            } else if let Some(riga_batch_norm) = linea.strip_prefix(_FILE_BATCH_NORM) {
+                let mut campi = riga_batch_norm.split_whitespace();
+                let k = campi.next().and_then(|k| k.parse::<usize>().ok())
+                    .ok_or_else(|| non_valido(format!("riga di batch normalization non valida: {}", linea)))?;
+                let parametri = campi.map(str::parse::<f64>).collect::<Result<Vec<f64>, _>>()
+                    .map_err(|_| non_valido(format!("riga di batch normalization non valida: {}", linea)))?;
+                match (batch_norm.get_mut(k), strati.get(k)) {
+                    (Some(normalizzazione), Some(pesi)) if normalizzazione.is_none() && parametri.len() == 4 * pesi.nrows() =>
+                        *normalizzazione = BatchNorm::da_parametri(&parametri),
+                    (Some(Some(_)), _) => return Err(non_valido(format!("batch normalization ripetuta: strato {}", k))),
+                    _ => return Err(non_valido(format!("batch normalization incoerente con le dimensioni della rete: {}", linea))),
+                }
+            } else if let Some(riga_bias) = linea.strip_prefix("b ") {
+                let campi: Vec<&str> = riga_bias.split_whitespace().collect();
+                let (k, r, valore) = match campi.as_slice() {
+                    [k, r, valore] => match (k.parse::<usize>(), r.parse::<usize>(), valore.parse::<f64>()) {
+                        (Ok(k), Ok(r), Ok(valore)) => (k, r, valore),
+                        _ => return Err(non_valido(format!("riga di bias non valida: {}", linea))),
+                    },
+                    _ => return Err(non_valido(format!("riga di bias non valida: {}", linea))),
+                };
+                match bias_assegnati.get_mut(k).and_then(|strato| strato.get_mut(r)) {
+                    Some(assegnato) if !*assegnato => *assegnato = true,
+                    Some(_) => return Err(non_valido(format!("bias ripetuto: strato {} neurone {}", k, r))),
+                    None => return Err(non_valido(format!("bias fuori dalle dimensioni della rete: {}", linea))),
+                }
+                bias[k][r] = valore;
+            } else if !linea.trim().is_empty() {
+                let campi: Vec<&str> = linea.split_whitespace().collect();
+                let coordinate = campi.get(..3)
+                    .and_then(|indici| indici.iter().map(|indice| indice.parse::<usize>().ok()).collect::<Option<Vec<usize>>>());
+                let valore = campi.get(3).and_then(|valore| valore.parse::<f64>().ok());
+                let (k, r, c, valore) = match (coordinate, valore) {
+                    (Some(indici), Some(valore)) if campi.len() == 4 => (indici[0], indici[1], indici[2], valore),
+                    _ => return Err(non_valido(format!("riga di peso non valida: {}", linea))),
+                };
+                match assegnati.get_mut(k).and_then(|strato| strato.get_mut((r, c))) {
+                    Some(assegnato) if !*assegnato => *assegnato = true,
+                    Some(_) => return Err(non_valido(format!("peso ripetuto: strato {} riga {} colonna {}", k, r, c))),
+                    None => return Err(non_valido(format!("peso fuori dalle dimensioni della rete: {}", linea))),
+                }
+                strati[k][(r, c)] = valore;
+            }
+        }
+
+        let tasso_apprendimento = tasso_apprendimento
+            .ok_or_else(|| non_valido("tasso di apprendimento mancante".to_string()))?;
+        if dimensioni_strati.len() < 2 || funzioni_attivazione.is_empty() {
+            return Err(non_valido("intestazione della rete incompleta".to_string()));
+        }
+        if !Self::numero_attivazioni_valido(funzioni_attivazione.len(), dimensioni_strati.len()) {
+            return Err(non_valido("numero di funzioni di attivazione diverso dal numero di strati".to_string()));
+        }
+        if assegnati.iter().any(|strato| strato.iter().any(|assegnato| !assegnato)) {
+            return Err(non_valido("pesi mancanti".to_string()));
+        }
+        if bias_assegnati.iter().any(|strato| strato.iter().any(|assegnato| !assegnato)) {
+            return Err(non_valido("bias mancanti".to_string()));
+        }
+        let mut rete = Self::da_parti(strati, funzioni_attivazione, tasso_apprendimento, dimensioni_strati);
+        rete.bias = bias;
+        rete.batch_norm = batch_norm;
+        rete.perdita = perdita;
+        rete.ottimizzatore = ottimizzatore;
+        Ok(rete)
+    }
+
+    /// Salva i pesi di un singolo strato di connessioni in un file di testo,
+    /// per riusarlo in un'altra rete (transfer learning granulare).
+    ///
+    /// Il file contiene la forma della matrice (`[#] righe, colonne`) seguita dalle righe dei pesi
+    /// e dai bias dei neuroni dello strato (`[b] `).
+    ///
+    /// # Argomenti
+    ///
+    /// * `indice` - Indice dello strato di connessioni (0 = connessioni tra input e primo strato nascosto).
+    /// * `file_path` - Il percorso del file di testo in cui salvare i pesi.
+    pub fn salva_strato(&self, indice: usize, file_path: &str) -> Result<(), Error> {
+        let strato = self.strati.get(indice)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("strato [{}] inesistente", indice)))?;
+        let mut file = File::create(file_path)?;
+        writeln!(file, "{} {}, {}", _FILE_INFO_RETE, strato.nrows(), strato.ncols())?;
+        for riga in strato.row_iter() {
+            writeln!(file, "{}", self.testo_numeri(riga.iter()))?;
+        }
+        writeln!(file, "{}{}", _FILE_BIAS, self.testo_bias(&self.bias[indice]))?;
+        Ok(())
+    }
+
+    /// Esporta in CSV le attivazioni di uno strato di neuroni per ogni esempio del set, per analizzare
+    /// offline le rappresentazioni apprese (clustering, visualizzazione t-SNE, ...).
+    ///
+    /// Il file ha una riga per esempio, nello stesso ordine del set, e una colonna per neurone dello strato,
+    /// senza riga di intestazione.
+    ///
+    /// # Argomenti
+    ///
+    /// * `dati` - Il set di esempi da elaborare (conta solo l'input).
+    /// * `strato` - Indice dello strato di neuroni (0 = input, `strati().len() - 1` = output).
+    /// * `file_path` - Il percorso del file CSV.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.esporta_attivazioni_csv(&dati_addestramento, 1, "attivazioni_nascosto.csv")?;
+    /// ```
+    pub fn esporta_attivazioni_csv(&self, dati: &[InputAddestramento], strato: usize, file_path: &str) -> Result<(), Error> {
+        if strato > self.strati.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("strato [{}] inesistente", strato)));
+        }
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_path(file_path)?;
+        for set in dati.iter() {
+            let uscite = self.propagazione_avanti(&DVector::from_vec(set.input.clone()));
+            writer.write_record(uscite[strato].iter().map(|valore| valore.to_string()))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Esporta i pesi in CSV, un file per strato di connessioni (`<prefisso>_strato0.csv`, `<prefisso>_strato1.csv`, ...),
+    /// per analizzarli con strumenti esterni (Python, fogli di calcolo). Ogni file ha una riga per neurone
+    /// di destinazione e una colonna per neurone di origine, come `pesi_connessioni`, senza riga di intestazione.
+    /// I valori usano sempre il punto decimale, indipendentemente dalla locale, e si rileggono esattamente.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.esporta_csv("analisi/pesi")?; // analisi/pesi_strato0.csv, analisi/pesi_strato1.csv, ...
+    /// ```
+    pub fn esporta_csv(&self, prefisso: &str) -> Result<(), Error> {
+        for (k, pesi) in self.strati.iter().enumerate() {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_path(format!("{}_strato{}.csv", prefisso, k))?;
+            for riga in pesi.row_iter() {
+                writer.write_record(riga.iter().map(|valore| valore.to_string()))?;
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Carica i pesi di un singolo strato di connessioni da un file creato con `salva_strato`,
+    /// verificando che la forma della matrice coincida con quella dello strato di destinazione.
+    /// Se il file non contiene i bias (salvato prima della loro introduzione) i bias dello strato restano invariati.
+    ///
+    /// # Argomenti
+    ///
+    /// * `indice` - Indice dello strato di connessioni da sostituire.
+    /// * `file_path` - Il percorso del file di testo da cui caricare i pesi.
+    pub fn carica_strato(&mut self, indice: usize, file_path: &str) -> Result<(), Error> {
+        let (righe_attese, colonne_attese) = match self.strati.get(indice) {
+            Some(strato) => (strato.nrows(), strato.ncols()),
+            None => return Err(Error::new(ErrorKind::InvalidInput, format!("strato [{}] inesistente", indice)))
+        };
+        let reader = BufReader::new(File::open(file_path)?);
+        let mut forma: Option<(usize, usize)> = None;
+        let mut valori: Vec<f64> = Vec::new();
+        let mut bias: Option<DVector<f64>> = None;
+        for line in reader.lines() {
+            let linea = line?;
+            if let Some(valori_bias) = linea.strip_prefix(_FILE_BIAS) {
+                bias = Some(Self::bias_da_testo(valori_bias)?);
+            } else if let Some(dimensioni) = linea.strip_prefix(_FILE_INFO_RETE) {
+                let dimensioni = dimensioni.trim()
+                    .split(", ")
+                    .map(|cifra| cifra.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| Error::new(ErrorKind::InvalidData, "forma dello strato non valida"))?;
+                if dimensioni.len() != 2 {
+                    return Err(Error::new(ErrorKind::InvalidData, "forma dello strato non valida"));
+                }
+                forma = Some((dimensioni[0], dimensioni[1]));
+            } else if !linea.trim().is_empty() {
+                for valore in linea.split_whitespace() {
+                    valori.push(valore.parse::<f64>()
+                        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("peso non numerico: {}", valore)))?);
+                }
+            }
+        }
+        let (righe, colonne) = forma
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "forma dello strato mancante"))?;
+        if (righe, colonne) != (righe_attese, colonne_attese) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("forma dello strato [{}] incompatibile: attesa {}x{}, trovata {}x{}", indice, righe_attese, colonne_attese, righe, colonne)
+            ));
+        }
+        if valori.len() != righe * colonne {
+            return Err(Error::new(ErrorKind::InvalidData, "numero di pesi diverso dalla forma dichiarata"));
+        }
+        if bias.as_ref().is_some_and(|bias| bias.len() != righe) {
+            return Err(Error::new(ErrorKind::InvalidData, "numero di bias diverso dai neuroni dello strato"));
+        }
+        self.strati[indice] = DMatrix::from_row_slice(righe, colonne, &valori);
+        if let Some(bias) = bias {
+            self.bias[indice] = bias;
+        }
+        Ok(())
+    }
+
+    /// Salva solo la configurazione della rete (dimensioni degli strati, funzioni di attivazione,
+    /// tasso di apprendimento, funzione di perdita e ottimizzatore), senza i pesi, in un file JSON leggibile
+    /// e modificabile a mano. Con `da_config` si ricreano reti identiche con pesi nuovi,
+    /// ad esempio per ripetere un esperimento; per salvare anche i pesi usare `salva_pesi_txt`.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file JSON.
+    pub fn salva_config(&self, file_path: &str) -> Result<(), Error> {
+        let mut file = File::create(file_path)?;
+        writeln!(file, "{}", serde_json::to_string_pretty(&Value::Object(self.campi_config()))?)
+    }
+
+    /// Campi JSON della configurazione della rete (struttura, attivazioni, tasso e perdita), senza i pesi.
+    fn campi_config(&self) -> Map<String, Value> {
+        let attivazioni: Vec<Value> = self.funzioni_attivazione.iter()
+            .map(|funzione| {
+                let mut campi = Map::new();
+                campi.insert("sigla".to_string(), json!(funzione.sigla()));
+                if funzione.alfa() != 0.0 {
+                    campi.insert("alfa".to_string(), json!(funzione.alfa()));
+                }
+                Value::Object(campi)
+            })
+            .collect();
+        let mut campi = Map::new();
+        campi.insert("dimensioni_strati".to_string(), json!(self.dimensioni_strati));
+        campi.insert("funzioni_attivazione".to_string(), Value::Array(attivazioni));
+        campi.insert("tasso_apprendimento".to_string(), json!(self.tasso_apprendimento));
+        campi.insert("perdita".to_string(), json!(self.perdita.sigla()));
+        if self.perdita.parametro() != 0.0 {
+            campi.insert("parametro_perdita".to_string(), json!(self.perdita.parametro()));
+        }
+        campi.insert("ottimizzatore".to_string(), json!({
+            "sigla": self.ottimizzatore.sigla(),
+            "parametri": self.ottimizzatore.parametri(),
+        }));
+        campi
+    }
+
+    /// Crea una nuova rete, con pesi casuali, dalla configurazione salvata con `salva_config`.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file JSON.
+    pub fn da_config(file_path: &str) -> Result<Self, Error> {
+        let config: Value = serde_json::from_str(&std::fs::read_to_string(file_path)?)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        Self::da_config_json(&config)
+    }
+
+    /// Crea una nuova rete, con pesi casuali, dai campi di configurazione di un documento JSON.
+    fn da_config_json(config: &Value) -> Result<Self, Error> {
+        let non_valido = |campo: &str| Error::new(ErrorKind::InvalidData, format!("campo '{}' mancante o non valido", campo));
+
+        let dimensioni_strati = config.get("dimensioni_strati").and_then(|v| v.as_array())
+            .ok_or_else(|| non_valido("dimensioni_strati"))?
+            .iter()
+            .map(|neuroni| neuroni.as_u64().map(|n| n as usize).ok_or_else(|| non_valido("dimensioni_strati")))
+            .collect::<Result<Vec<usize>, Error>>()?;
+        if dimensioni_strati.len() < 2 {
+            return Err(Error::new(ErrorKind::InvalidData, "la rete deve avere almeno due strati"));
+        }
+
+        let mut funzioni_attivazione = Vec::new();
+        for funzione in config.get("funzioni_attivazione").and_then(|v| v.as_array())
+            .ok_or_else(|| non_valido("funzioni_attivazione"))?
+        {
+            let sigla = funzione.get("sigla").and_then(|v| v.as_str())
+                .ok_or_else(|| non_valido("funzioni_attivazione"))?;
+            let alfa = funzione.get("alfa").and_then(|v| v.as_f64()).unwrap_or(0.0);
+            funzioni_attivazione.push(funzione_da_sigla(sigla, alfa)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("funzione di attivazione sconosciuta: {}", sigla)))?);
+        }
+        if !Self::numero_attivazioni_valido(funzioni_attivazione.len(), dimensioni_strati.len()) {
+            return Err(non_valido("funzioni_attivazione"));
+        }
+
+        let tasso_apprendimento = config.get("tasso_apprendimento").and_then(|v| v.as_f64())
+            .ok_or_else(|| non_valido("tasso_apprendimento"))?;
+        let perdita = match config.get("perdita").and_then(|v| v.as_str()) {
+            Some(sigla) => perdita_da_sigla(sigla, config.get("parametro_perdita").and_then(|v| v.as_f64()).unwrap_or(0.0))
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("funzione di perdita sconosciuta: {}", sigla)))?,
+            None => Arc::new(ErroreQuadraticoMedio),
+        };
+        let ottimizzatore = match config.get("ottimizzatore") {
+            Some(ottimizzatore) => {
+                let sigla = ottimizzatore.get("sigla").and_then(|v| v.as_str())
+                    .ok_or_else(|| non_valido("ottimizzatore"))?;
+                let parametri = ottimizzatore.get("parametri").and_then(|v| v.as_array())
+                    .ok_or_else(|| non_valido("ottimizzatore"))?
+                    .iter()
+                    .map(|parametro| parametro.as_f64().ok_or_else(|| non_valido("ottimizzatore")))
+                    .collect::<Result<Vec<f64>, Error>>()?;
+                Ottimizzatore::da_sigla(sigla, &parametri)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("ottimizzatore sconosciuto: {}", sigla)))?
+            }
+            None => Ottimizzatore::SGD,
+        };
+
+        let mut rng = StdRng::from_entropy();
+        let strati = Self::genera_pesi(&dimensioni_strati, InizializzazionePesi::Uniforme, &mut rng);
+        let bias = Self::genera_bias(&dimensioni_strati, InizializzazionePesi::Uniforme, &mut rng);
+        let mut rete = Self::da_parti(strati, funzioni_attivazione, tasso_apprendimento, dimensioni_strati);
+        rete.bias = bias;
+        rete.rng = rng;
+        rete.perdita = perdita;
+        rete.ottimizzatore = ottimizzatore;
+        Ok(rete)
+    }
+
+    /// Salva la rete completa in formato JSON: la configurazione come `salva_config` (dimensioni degli strati,
+    /// funzioni di attivazione per sigla con `alfa`, tasso di apprendimento, perdita, ottimizzatore) più i pesi di ogni strato,
+    /// come liste di righe, e i bias. Se la rete ha strati di batch normalization, il campo `batch_norm` contiene
+    /// per ogni strato `null` oppure `γ`, `β`, media e varianza mobili in un'unica lista. I numeri sono scritti
+    /// nella rappresentazione più breve che li rilegge esattamente, per cui il round-trip con `carica_json`
+    /// produce output identici.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file JSON.
+    pub fn salva_json(&self, file_path: &str) -> Result<(), Error> {
+        let mut campi = self.campi_config();
+        let pesi: Vec<Vec<Vec<f64>>> = self.strati.iter()
+            .map(|pesi| pesi.row_iter().map(|riga| riga.iter().copied().collect()).collect())
+            .collect();
+        campi.insert("pesi".to_string(), json!(pesi));
+        campi.insert("bias".to_string(), json!(self.bias.iter().map(|bias| bias.as_slice()).collect::<Vec<_>>()));
+        if self.batch_norm.iter().any(Option::is_some) {
+            let batch_norm: Vec<Option<Vec<f64>>> = self.batch_norm.iter()
+                .map(|normalizzazione| normalizzazione.as_ref().map(BatchNorm::parametri))
+                .collect();
+            campi.insert("batch_norm".to_string(), json!(batch_norm));
+        }
+        let mut file = File::create(file_path)?;
+        writeln!(file, "{}", serde_json::to_string_pretty(&Value::Object(campi))?)
+    }
+
+    /// Carica una rete salvata con `salva_json`. Pesi, bias e parametri dell'eventuale batch normalization
+    /// devono avere le dimensioni indicate da `dimensioni_strati`.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file JSON.
+    pub fn carica_json(file_path: &str) -> Result<Self, Error> {
+        let documento: Value = serde_json::from_str(&std::fs::read_to_string(file_path)?)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let mut rete = Self::da_config_json(&documento)?;
+        let non_valido = |campo: &str| Error::new(ErrorKind::InvalidData, format!("campo '{}' mancante o non valido", campo));
+        let valori = |lista: &Value, campo: &str| -> Result<Vec<f64>, Error> {
+            lista.as_array()
+                .ok_or_else(|| non_valido(campo))?
+                .iter()
+                .map(|valore| valore.as_f64().ok_or_else(|| non_valido(campo)))
+                .collect()
+        };
+
+        let pesi = documento.get("pesi").and_then(|v| v.as_array()).ok_or_else(|| non_valido("pesi"))?;
+        let bias = documento.get("bias").and_then(|v| v.as_array()).ok_or_else(|| non_valido("bias"))?;
+        if pesi.len() != rete.strati.len() || bias.len() != rete.bias.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "numero di strati dei pesi incoerente con dimensioni_strati"));
+        }
+        for (k, (righe, bias)) in pesi.iter().zip(bias.iter()).enumerate() {
+            let righe = righe.as_array().ok_or_else(|| non_valido("pesi"))?;
+            let (nrows, ncols) = rete.strati[k].shape();
+            if righe.len() != nrows {
+                return Err(Error::new(ErrorKind::InvalidData, format!("strato {}: attese {} righe di pesi, trovate {}", k, nrows, righe.len())));
+            }
+            for (r, riga) in righe.iter().enumerate() {
+                let riga = valori(riga, "pesi")?;
+                if riga.len() != ncols {
+                    return Err(Error::new(ErrorKind::InvalidData, format!("strato {}: attesi {} pesi per riga, trovati {}", k, ncols, riga.len())));
+                }
+                rete.strati[k].row_mut(r).copy_from_slice(&riga);
+            }
+            let bias = valori(bias, "bias")?;
+            if bias.len() != nrows {
+                return Err(Error::new(ErrorKind::InvalidData, format!("strato {}: attesi {} bias, trovati {}", k, nrows, bias.len())));
+            }
+            rete.bias[k] = DVector::from_vec(bias);
+        }
+        if let Some(batch_norm) = documento.get("batch_norm") {
+            let batch_norm = batch_norm.as_array().ok_or_else(|| non_valido("batch_norm"))?;
+            if batch_norm.len() != rete.strati.len() {
+                return Err(Error::new(ErrorKind::InvalidData, "numero di strati di batch_norm incoerente con dimensioni_strati"));
+            }
+            for (k, parametri) in batch_norm.iter().enumerate() {
+                if parametri.is_null() {
+                    continue;
+                }
+                let neuroni = rete.strati[k].nrows();
+                let parametri = valori(parametri, "batch_norm")?;
+                if parametri.len() != 4 * neuroni {
+                    return Err(Error::new(ErrorKind::InvalidData, format!("strato {}: attesi {} parametri di batch normalization, trovati {}", k, 4 * neuroni, parametri.len())));
+                }
+                rete.batch_norm[k] = BatchNorm::da_parametri(&parametri);
+            }
+        }
+        Ok(rete)
+    }
+
+    /// Serializza la rete in formato binario compatto little-endian, preservando i pesi bit per bit.
+    ///
+    /// Struttura: intestazione `MLPB` e versione, tasso di apprendimento, dimensioni degli strati,
+    /// funzioni di attivazione (sigla e parametro `alfa`), quindi per ogni strato i pesi `f64` grezzi
+    /// per righe seguiti dai bias e da un byte che indica la batch normalization (1) o la sua assenza (0);
+    /// se presente seguono `γ`, `β`, media e varianza mobili.
+    fn a_byte(&self) -> Vec<u8> {
+        let mut byte = Vec::new();
+        byte.extend_from_slice(_BIN_INTESTAZIONE);
+        byte.push(_BIN_VERSIONE);
+        byte.extend_from_slice(&self.tasso_apprendimento.to_le_bytes());
+
+        byte.extend_from_slice(&(self.dimensioni_strati.len() as u32).to_le_bytes());
+        for neuroni in self.dimensioni_strati.iter() {
+            byte.extend_from_slice(&(*neuroni as u32).to_le_bytes());
+        }
+
+        byte.extend_from_slice(&(self.funzioni_attivazione.len() as u32).to_le_bytes());
+        for funzione_attivazione in self.funzioni_attivazione.iter() {
+            let sigla = funzione_attivazione.sigla().as_bytes();
+            byte.extend_from_slice(&(sigla.len() as u16).to_le_bytes());
+            byte.extend_from_slice(sigla);
+            byte.extend_from_slice(&funzione_attivazione.alfa().to_le_bytes());
+        }
+
+        for ((strato, bias), normalizzazione) in self.strati.iter().zip(self.bias.iter()).zip(self.batch_norm.iter()) {
+            for riga in strato.row_iter() {
+                for valore in riga.iter() {
+                    byte.extend_from_slice(&valore.to_le_bytes());
+                }
+            }
+            for valore in bias.iter() {
+                byte.extend_from_slice(&valore.to_le_bytes());
+            }
+            match normalizzazione {
+                Some(normalizzazione) => {
+                    byte.push(1);
+                    for valore in normalizzazione.parametri() {
+                        byte.extend_from_slice(&valore.to_le_bytes());
+                    }
+                }
+                None => byte.push(0),
+            }
+        }
+        byte
+    }
+
+    /// Ricostruisce una rete dalla serializzazione binaria prodotta da `a_byte`.
+    /// Dati corrotti o troncati producono un errore `InvalidData` invece di un panic.
+    /// I dati delle versioni precedenti del formato sono ancora letti: la versione 1 senza bias (bias nulli),
+    /// le versioni 1 e 2 senza batch normalization.
+    fn da_byte(byte: &[u8]) -> Result<Self, Error> {
+        let mut lettore = LettoreByte { byte, posizione: 0 };
+        if lettore.leggi(_BIN_INTESTAZIONE.len())? != _BIN_INTESTAZIONE {
+            return Err(Error::new(ErrorKind::InvalidData, "intestazione del formato binario non valida"));
+        }
+        let versione = lettore.leggi(1)?[0];
+        if versione == 0 || versione > _BIN_VERSIONE {
+            return Err(Error::new(ErrorKind::InvalidData, format!("versione del formato binario non supportata: {}", versione)));
+        }
+        let tasso_apprendimento = lettore.leggi_f64()?;
+
+        let num_strati = lettore.leggi_u32()? as usize;
+        let mut dimensioni_strati = Vec::new();
+        for _ in 0..num_strati {
+            dimensioni_strati.push(lettore.leggi_u32()? as usize);
+        }
+        if dimensioni_strati.len() < 2 {
+            return Err(Error::new(ErrorKind::InvalidData, "la rete deve avere almeno due strati"));
+        }
+
+        let num_funzioni = lettore.leggi_u32()? as usize;
+        let mut funzioni_attivazione = Vec::new();
+        for _ in 0..num_funzioni {
+            let lunghezza = lettore.leggi_u16()? as usize;
+            let sigla = String::from_utf8(lettore.leggi(lunghezza)?.to_vec())
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "sigla della funzione di attivazione non valida"))?;
+            let alfa = lettore.leggi_f64()?;
+            let funzione = funzione_da_sigla(&sigla, alfa)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("funzione di attivazione sconosciuta: {}", sigla)))?;
+            funzioni_attivazione.push(funzione);
+        }
+        if !Self::numero_attivazioni_valido(funzioni_attivazione.len(), dimensioni_strati.len()) {
+            return Err(Error::new(ErrorKind::InvalidData, "numero di funzioni di attivazione diverso dal numero di strati"));
+        }
+
+        let mut strati = Vec::with_capacity(dimensioni_strati.len() - 1);
+        let mut bias = Vec::with_capacity(dimensioni_strati.len() - 1);
+        let mut batch_norm = Vec::with_capacity(dimensioni_strati.len() - 1);
+        for i in 0..dimensioni_strati.len() - 1 {
+            let (righe, colonne) = (dimensioni_strati[i + 1], dimensioni_strati[i]);
+            let valori = lettore.leggi_f64_multipli(righe * colonne)?;
+            strati.push(DMatrix::from_row_slice(righe, colonne, &valori));
+            if versione >= 2 {
+                bias.push(DVector::from_vec(lettore.leggi_f64_multipli(righe)?));
+            } else {
+                bias.push(DVector::zeros(righe));
+            }
+            let normalizzato = versione >= 3 && match lettore.leggi(1)?[0] {
+                0 => false,
+                1 => true,
+                flag => return Err(Error::new(ErrorKind::InvalidData, format!("strato {}: indicatore di batch normalization non valido: {}", i, flag))),
+            };
+            batch_norm.push(if normalizzato { BatchNorm::da_parametri(&lettore.leggi_f64_multipli(4 * righe)?) } else { None });
+        }
+        if lettore.posizione != byte.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "dati in eccesso dopo i pesi"));
+        }
+
+        let mut rete = Self::da_parti(strati, funzioni_attivazione, tasso_apprendimento, dimensioni_strati);
+        rete.bias = bias;
+        rete.batch_norm = batch_norm;
+        Ok(rete)
+    }
+
+    /// Salva la rete in formato binario compatto (vedi `a_byte`): pesi e bias sono scritti come `f64` grezzi
+    /// little-endian, senza perdita di precisione e in una frazione dello spazio del formato testo.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file binario.
+    pub fn salva_bin(&self, file_path: &str) -> Result<(), Error> {
+        std::fs::write(file_path, self.a_byte())
+    }
+
+    /// Carica una rete salvata con `salva_bin`. Un file troncato o corrotto produce un errore
+    /// (`UnexpectedEof` o `InvalidData`) invece di un panic.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file binario.
+    pub fn carica_bin(file_path: &str) -> Result<Self, Error> {
+        Self::da_byte(&std::fs::read(file_path)?)
+    }
+
+    /// Esporta la rete come stringa esadecimale compatta, adatta a incorporare modelli minuscoli
+    /// in una configurazione o in un QR code.
+    /// Riusa la serializzazione binaria, compressa comprimendo le sequenze di byte nulli
+    /// (frequenti nei pesi azzerati dal pruning).
+    pub fn a_hex(&self) -> String {
+        comprimi_zeri(&self.a_byte())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Ricostruisce una rete dalla stringa esadecimale prodotta da `a_hex`.
+    pub fn da_hex(testo: &str) -> Result<Self, Error> {
+        let testo = testo.trim();
+        if !testo.len().is_multiple_of(2) || !testo.is_ascii() {
+            return Err(Error::new(ErrorKind::InvalidData, "stringa esadecimale non valida"));
+        }
+        let compressi = (0..testo.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&testo[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "stringa esadecimale non valida"))?;
+        Self::da_byte(&decomprimi_zeri(&compressi)?)
+    }
+
+    /**
+     * Restituisce i singoli pesi delle connessioni tragli strati della rete neurale.
+     * I pesi sono organizzati come un vettori tridimensionale di varori in virgola mobile.
+     * Puo essere visto come un vettore delle connessioni tragli strati organizzati come matrici.
+     */
+    pub fn pesi_connessioni(&self) -> Vec<Vec<Vec<f64>>> {
+        let mut strati_rete = vec![];
+
+        for strato in &self.strati {
+            let mut connessioni = vec![];
+            for riga in strato.row_iter() {
+                let riga_connessioni = riga.iter()
+                    .copied()
+                    .collect::<Vec<f64>>();
+                connessioni.push(riga_connessioni.clone());
+            }
+            strati_rete.push(connessioni);
+           
+        }
+
+       strati_rete
+    }
+
+    /// Matrici dei pesi di ogni strato di connessioni, in sola lettura e senza copie:
+    /// la matrice `k` ha `dimensioni_strati[k + 1]` righe e `dimensioni_strati[k]` colonne.
+    pub fn matrici_pesi(&self) -> &[DMatrix<f64>] {
+        &self.strati
+    }
+
+    /// Vettori dei bias di ogni strato di connessioni, in sola lettura e senza copie:
+    /// il vettore `k` ha `dimensioni_strati[k + 1]` elementi.
+    pub fn matrici_bias(&self) -> &[DVector<f64>] {
+        &self.bias
+    }
+
+    /// Sostituisce la matrice dei pesi di uno strato di connessioni, ad esempio per inizializzazioni
+    /// personalizzate o per trasferire pesi da un'altra rete. Le dimensioni devono essere
+    /// `dimensioni_strati[indice + 1] × dimensioni_strati[indice]`; i bias restano invariati.
+    ///
+    /// # Esempio
+    /// ```
+    /// let pesi = DMatrix::from_element(rete.strati()[1], rete.strati()[0], 0.0);
+    /// rete.imposta_pesi_strato(0, pesi)?;
+    /// ```
+    pub fn imposta_pesi_strato(&mut self, indice: usize, pesi: DMatrix<f64>) -> Result<(), ErroreRete> {
+        let attese = match self.strati.get(indice) {
+            Some(strato) => strato.shape(),
+            None => return Err(ErroreRete::StrutturaIncoerente(format!("strato [{}] inesistente", indice))),
+        };
+        if pesi.shape() != attese {
+            return Err(ErroreRete::DimensioniPesi { strato: indice, attese, trovate: pesi.shape() });
+        }
+        self.strati[indice] = pesi;
+        Ok(())
+    }
+
+
+    /// Metodo che inverte una matrice formata come vettore di vettori
+    fn trasponi<T: Clone>(matrice: Vec<Vec<T>>) -> Vec<Vec<T>> {
+        if matrice.is_empty() {
+            return vec![]; // Se la matrice è vuota, ritorna una matrice vuota.
+        }
+    
+        let _numero_righe = matrice.len();
+        let numero_colonne = matrice[0].len();
+    
+        // Creiamo una nuova matrice trasposta con colonne vuote.
+        let mut matrice_trasposta: Vec<Vec<T>> = vec![vec![]; numero_colonne];
+    
+        // Per ogni riga nella matrice originale
+        for riga in matrice {
+            // Inseriamo ogni elemento nella colonna corrispondente della nuova matrice trasposta.
+            for (i, elemento) in riga.into_iter().enumerate() {
+                matrice_trasposta[i].push(elemento);
+            }
+        }
+    
+        matrice_trasposta
+    }
+
+
+    /// Carica i pesi della rete neurale da un file di testo.
+    ///
+    /// # Argomenti
+    ///
+    /// * `file_path` - Il percorso del file di testo da cui caricare i pesi.
+    ///
+    /// # Ritorna
+    ///
+    /// Un `Result` che indica se l'operazione ha avuto successo o meno: un file malformato
+    /// produce un `ErroreRete` con la riga o l'incoerenza trovata, comprese matrici dei pesi
+    /// in numero o dimensioni diversi da quelli dichiarati nell'intestazione `[#] `.
+    /// In caso di errore la rete resta com'era prima della chiamata.
+    pub fn carica_pesi_txt(&mut self, file_path: &str) -> Result<(), ErroreRete> {
+        let mut caricata = self.clone();
+        caricata.leggi_pesi_txt(file_path)?;
+        *self = caricata;
+        Ok(())
+    }
+
+    /// Lettura di `carica_pesi_txt`, che sostituisce man mano topologia, funzioni di attivazione,
+    /// pesi e iperparametri salvati: su un errore la rete può restare caricata a metà.
+    fn leggi_pesi_txt(&mut self, file_path: &str) -> Result<(), ErroreRete> {
+        let file = File::open(file_path)?;
+        let reader = BufReader::new(file);
+        let mut strati = Vec::new();
+        let mut attuale_strato:Vec<Vec<f64>> = Vec::new();
+        let mut bias = Vec::new();
+        let mut attuale_bias: Option<DVector<f64>> = None; // i file salvati prima dei bias non hanno la riga `[b] `
+        let mut batch_norm = Vec::new();
+        let mut attuale_batch_norm: Option<BatchNorm> = None;
+        let mut dimensioni_strati: Vec<usize> = Vec::new();
+
+        self.funzioni_attivazione.clear();
+        // i file salvati prima di perdita e ottimizzatore non hanno le righe `[l] ` e `[o] `
+        self.perdita = Arc::new(ErroreQuadraticoMedio);
+        self.ottimizzatore = Ottimizzatore::SGD;
+        
+        for (indice, line) in reader.lines().enumerate() {
+            let linea = line?;
+            let numero_riga = indice + 1;
+            
+            if let Some(valore) = linea.strip_prefix(_FILE_INFO_APPRENDIMENTO) {
+                let tasso = Self::numero_da_testo(valore.trim(), numero_riga)?;
+                if tasso > 0.0 {
+                     self.tasso_apprendimento = tasso;
+                }
+            } else if linea.starts_with(_FILE_INFO_ATTIVAZIONE) {
+                let nomi_funzioni = linea.replace(_FILE_INFO_ATTIVAZIONE, "").trim().to_string();
+                for nome_funzione in nomi_funzioni.split("; ") {
+                    let nome_funzione_modificato = nome_funzione.to_string().replace(";", "").replace(" ", "");
+                    let mut _nome_funzione = nome_funzione_modificato.as_str();  
+                    if _nome_funzione.trim() != "" {
+                        let (sigla, alfa) = match _nome_funzione.split_once('_') {
+                            Some((sigla, alfa)) => (sigla, Self::numero_da_testo(alfa, numero_riga)?),
+                            None => (_nome_funzione, 0.0),
+                        };
+                        let funzione_attivazione = funzione_da_sigla(sigla, alfa)
+                            .ok_or_else(|| ErroreRete::SiglaSconosciuta(sigla.to_string()))?;
+                        self.funzioni_attivazione.push(funzione_attivazione);
+                    }
+                }
+
+            } else if linea.starts_with(_FILE_INFO_RETE) {
+                let strati = linea.replace(_FILE_INFO_RETE, "").trim()
+                        .split(", ")
+                        .map(|cifra| cifra.trim().parse::<usize>()
+                            .map_err(|_| ErroreRete::ValoreNonNumerico { riga: numero_riga, valore: cifra.to_string() }))
+                        .collect::<Result<Vec<usize>, ErroreRete>>()?;
+                if !strati.is_empty() {
+                    if !Self::numero_attivazioni_valido(self.funzioni_attivazione.len(), strati.len()) {
+                        return Err(ErroreRete::StrutturaIncoerente(format!(
+                            "{} funzioni di attivazione per {} strati", self.funzioni_attivazione.len(), strati.len()
+                        )));
+                    }
+                    dimensioni_strati = strati;
+                }
+            } else if let Some(testo) = linea.strip_prefix(_FILE_PERDITA) {
+                self.perdita = Self::perdita_da_testo(testo, numero_riga)?;
+            } else if let Some(testo) = linea.strip_prefix(_FILE_OTTIMIZZATORE) {
+                self.ottimizzatore = Self::ottimizzatore_da_testo(testo, numero_riga)?;
+            } else if let Some(valori) = linea.strip_prefix(_FILE_BIAS) {
+                attuale_bias = Some(DVector::from_vec(Self::numeri_da_testo(valori, numero_riga)?));
+            } else if let Some(valori) = linea.strip_prefix(_FILE_BATCH_NORM) {
+                attuale_batch_norm = Some(BatchNorm::da_parametri(&Self::numeri_da_testo(valori, numero_riga)?)
+                    .ok_or_else(|| ErroreRete::RigaMalformata { riga: numero_riga, contenuto: linea.clone() })?);
+            } else if linea.trim() == _FILE_STRATO {
+                let num_righe = attuale_strato.len();
+                let num_colonne = match attuale_strato.first() {
+                    Some(connessioni) => connessioni.len(),
+                    None => return Err(ErroreRete::RigaMalformata { riga: numero_riga, contenuto: linea }),
+                };
+                if attuale_strato.iter().any(|connessioni| connessioni.len() != num_colonne) {
+                    return Err(ErroreRete::StrutturaIncoerente(
+                        format!("strato [{}]: righe dei pesi di lunghezza diversa", strati.len())
+                    ));
+                }
+                let dati_strato = DMatrix::from_vec(
+                    num_righe,
+                    num_colonne,
+                    Self::trasponi(attuale_strato).clone().into_iter().flatten().collect(),
+                );
+                let bias_strato = attuale_bias.take().unwrap_or_else(|| DVector::zeros(num_righe));
+                if bias_strato.len() != num_righe {
+                    return Err(ErroreRete::StrutturaIncoerente(
+                        format!("strato [{}]: {} bias per {} neuroni", strati.len(), bias_strato.len(), num_righe)
+                    ));
+                }
+                if attuale_batch_norm.as_ref().is_some_and(|normalizzazione| normalizzazione.neuroni() != num_righe) {
+                    return Err(ErroreRete::StrutturaIncoerente(
+                        format!("strato [{}]: batch normalization di dimensione diversa dai neuroni", strati.len())
+                    ));
+                }
+                strati.push(dati_strato);
+                bias.push(bias_strato);
+                batch_norm.push(attuale_batch_norm.take());
+                attuale_strato = Vec::new();
+                
+            } else {
+                attuale_strato.push(Self::numeri_da_testo(&linea, numero_riga)?);
+            }
+        }
+
+        // la struttura letta deve corrispondere all'intestazione `[#] `
+        if dimensioni_strati.len() < 2 {
+            return Err(ErroreRete::StrutturaIncoerente("intestazione `[#] ` mancante o con meno di due strati".to_string()));
+        }
+        if strati.len() != dimensioni_strati.len() - 1 {
+            return Err(ErroreRete::StrutturaIncoerente(format!(
+                "{} matrici di pesi per {} strati dichiarati", strati.len(), dimensioni_strati.len()
+            )));
+        }
+        for (i, pesi) in strati.iter().enumerate() {
+            if pesi.shape() != (dimensioni_strati[i + 1], dimensioni_strati[i]) {
+                return Err(ErroreRete::StrutturaIncoerente(format!(
+                    "strato [{}]: pesi {}x{}, attesi {}x{}",
+                    i, pesi.nrows(), pesi.ncols(), dimensioni_strati[i + 1], dimensioni_strati[i]
+                )));
+            }
+        }
+
+        self.funzioni_attivazione = Self::attivazioni_per_strato(
+            std::mem::take(&mut self.funzioni_attivazione), dimensioni_strati.len()
+        );
+        self.dimensioni_strati = dimensioni_strati;
+        self.strati_congelati = vec![false; strati.len()];
+        self.dropconnect = vec![0.0; strati.len()];
+        self.batch_norm = batch_norm;
+        self.stato_ottimizzatore = None;
+        self.strati = strati;
+        self.bias = bias;
+        Ok(())
+    }
+
+    /// Verifica che l'architettura di un'altra rete sia compatibile con questa
+    /// (stesse dimensioni degli strati e stesse funzioni di attivazione, parametri inclusi),
+    /// requisito delle operazioni che combinano i pesi di più reti.
+    ///
+    /// # Ritorna
+    ///
+    /// `Ok(())` se le reti sono compatibili, altrimenti un errore che descrive la prima differenza trovata.
+    pub fn compatibile_con(&self, altra: &ReteNeurale) -> Result<(), IncompatibilitaRete> {
+        if self.dimensioni_strati.len() != altra.dimensioni_strati.len() {
+            return Err(IncompatibilitaRete::NumeroStrati {
+                atteso: self.dimensioni_strati.len(),
+                trovato: altra.dimensioni_strati.len()
+            });
+        }
+        for (strato, (attesa, trovata)) in self.dimensioni_strati.iter().zip(altra.dimensioni_strati.iter()).enumerate() {
+            if attesa != trovata {
+                return Err(IncompatibilitaRete::DimensioneStrato { strato, attesa: *attesa, trovata: *trovata });
+            }
+        }
+        if self.funzioni_attivazione.len() != altra.funzioni_attivazione.len() {
+            return Err(IncompatibilitaRete::NumeroFunzioniAttivazione {
+                atteso: self.funzioni_attivazione.len(),
+                trovato: altra.funzioni_attivazione.len()
+            });
+        }
+        for (indice, (attesa, trovata)) in self.funzioni_attivazione.iter().zip(altra.funzioni_attivazione.iter()).enumerate() {
+            if attesa.sigla() != trovata.sigla() || attesa.alfa() != trovata.alfa() {
+                return Err(IncompatibilitaRete::FunzioneAttivazione {
+                    indice,
+                    attesa: attesa.sigla().to_string(),
+                    trovata: trovata.sigla().to_string()
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Vero se le due reti hanno le stesse dimensioni degli strati e le stesse funzioni di attivazione
+    /// (parametri inclusi), indipendentemente dai pesi; per conoscere la prima differenza usare `compatibile_con`.
+    pub fn stessa_struttura(&self, altra: &ReteNeurale) -> bool {
+        self.compatibile_con(altra).is_ok()
+    }
+
+    /// Vero se le due reti hanno la stessa struttura e ogni peso e bias differisce al più di `tolleranza`
+    /// in valore assoluto, ad esempio per verificare il round-trip di salvataggio e caricamento.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.salva_pesi_txt("rete.txt")?;
+    /// assert!(rete.pesi_uguali(&ReteNeurale::carica("rete.txt")?, 1e-12));
+    /// ```
+    pub fn pesi_uguali(&self, altra: &ReteNeurale, tolleranza: f64) -> bool {
+        let vicini = |a: &[f64], b: &[f64]| a.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() <= tolleranza);
+        self.stessa_struttura(altra)
+            && self.strati.iter().zip(altra.strati.iter()).all(|(a, b)| vicini(a.as_slice(), b.as_slice()))
+            && self.bias.iter().zip(altra.bias.iter()).all(|(a, b)| vicini(a.as_slice(), b.as_slice()))
+    }
+
+    /// Applica in-place una funzione elemento per elemento a tutti i pesi della rete.
+    /// È la base per operazioni come clipping personalizzato, quantizzazione sperimentale o aggiunta di rumore.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.mappa_pesi(|peso| peso.clamp(-5.0, 5.0));
+    /// ```
+    pub fn mappa_pesi(&mut self, f: impl Fn(f64) -> f64) {
+        for pesi in self.strati.iter_mut() {
+            pesi.apply(|peso| *peso = f(*peso));
+        }
+    }
+
+    /// Profilo 1D della superficie di perdita lungo una direzione nello spazio dei pesi: i pesi vengono
+    /// spostati di `t · d/||d||` per `passi` valori di `t` equispaziati in `[-raggio, raggio]` e per ciascuno
+    /// si misura la perdita media sul set. Un profilo piatto attorno a `t = 0` indica un minimo ampio,
+    /// di solito associato a una migliore generalizzazione. La rete non viene modificata
+    /// (le perturbazioni sono applicate a una copia).
+    ///
+    /// # Argomenti
+    ///
+    /// * `dati` - Il set su cui misurare la perdita.
+    /// * `direzione` - Un valore per ogni peso, strato per strato nell'ordine di memorizzazione delle matrici
+    ///   (per colonne); viene normalizzata a norma unitaria.
+    /// * `passi` - Numero di punti del profilo (con un solo passo si valuta solo `t = 0`).
+    /// * `raggio` - Spostamento massimo lungo la direzione.
+    ///
+    /// # Ritorna
+    ///
+    /// Le coppie `(t, perdita)` in ordine di `t` crescente.
+    ///
+    /// # Panics
+    ///
+    /// Se la lunghezza di `direzione` è diversa dal numero di pesi della rete.
+    ///
+    /// # Esempio
+    /// ```
+    /// let direzione: Vec<f64> = (0..numero_pesi).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    /// for (t, perdita) in rete.profilo_perdita(&dati_addestramento, &direzione, 21, 1.0) {
+    ///     println!("{:+.2}\t{}", t, perdita);
+    /// }
+    /// ```
+    pub fn profilo_perdita(&self, dati: &[InputAddestramento], direzione: &[f64], passi: usize, raggio: f64) -> Vec<(f64, f64)> {
+        let numero_pesi: usize = self.strati.iter().map(|pesi| pesi.len()).sum();
+        assert_eq!(direzione.len(), numero_pesi, "la direzione deve avere un valore per ogni peso");
+        let norma = direzione.iter().map(|d| d * d).sum::<f64>().sqrt();
+        let versore: Vec<f64> = direzione.iter().map(|d| if norma > 0.0 { d / norma } else { 0.0 }).collect();
+
+        let mut copia = self.clone();
+        (0..passi)
+            .map(|k| {
+                let t = if passi > 1 { -raggio + 2.0 * raggio * k as f64 / (passi - 1) as f64 } else { 0.0 };
+                let mut componenti = versore.iter();
+                for (perturbati, originali) in copia.strati.iter_mut().zip(self.strati.iter()) {
+                    for (peso, originale) in perturbati.iter_mut().zip(originali.iter()) {
+                        *peso = originale + t * componenti.next().copied().unwrap_or(0.0);
+                    }
+                }
+                (t, copia.perdita_media(dati))
+            })
+            .collect()
+    }
+
+    /// Crea una copia della rete con i pesi invariati, in cui ogni occorrenza della funzione di attivazione
+    /// con sigla `vecchia_sigla` è sostituita da `nuova`, per valutare l'effetto di un'altra attivazione
+    /// senza riaddestrare da zero (come baseline o punto di partenza per il fine-tuning).
+    /// La funzione nulla dello strato di input non viene toccata. Se la sigla non è presente
+    /// la copia è identica all'originale.
+    ///
+    /// # Esempio
+    /// ```
+    /// let variante = rete.sostituisci_attivazione("Sigmoide", Arc::new(Tanh));
+    /// println!("{} -> {}", rete.perdita_media(&dati_validazione), variante.perdita_media(&dati_validazione));
+    /// ```
+    pub fn sostituisci_attivazione(&self, vecchia_sigla: &str, nuova: Arc<dyn FunzioneAttivazione + Send + Sync>) -> ReteNeurale {
+        let mut copia = self.clone();
+        for funzione in copia.funzioni_attivazione.iter_mut().skip(1) { // salta la funzione nulla dell'input
+            if funzione.sigla() == vecchia_sigla {
+                *funzione = nuova.clone();
+            }
+        }
+        copia
+    }
+
+    /// Copia della rete con gli stessi pesi, bias e configurazione, ma con lo stato dell'ottimizzatore
+    /// azzerato (velocità e momenti di Momentum/Adam, contatore dei passi).
+    /// A differenza di `clone`, la copia riparte come appena caricata: utile per diramare più esperimenti
+    /// da un checkpoint senza che ereditino l'inerzia dell'addestramento precedente.
+    pub fn clona_solo_pesi(&self) -> ReteNeurale {
+        let mut copia = self.clone();
+        copia.stato_ottimizzatore = None;
+        copia
+    }
+
+    /// Rigenera pesi e bias di tutti gli strati secondo la strategia di inizializzazione, con il generatore
+    /// casuale della rete, mantenendo architettura e funzioni di attivazione. Le eventuali batch normalization
+    /// ripartono da `BatchNorm::nuova` e lo stato dell'ottimizzatore viene azzerato: la rete è come appena creata,
+    /// utile per ripetere un esperimento con pesi nuovi senza ricostruirla.
+    ///
+    /// # Esempio
+    /// ```
+    /// for seme in 0..5 {
+    ///     rete.imposta_seme(seme);
+    ///     rete.reinizializza(InizializzazionePesi::He);
+    ///     rete.addestra_epoche(&dati_addestramento, 100, OpzioniAddestramento::default())?;
+    /// }
+    /// ```
+    pub fn reinizializza(&mut self, strategia: InizializzazionePesi) {
+        self.strati = Self::genera_pesi(&self.dimensioni_strati, strategia, &mut self.rng);
+        self.bias = Self::genera_bias(&self.dimensioni_strati, strategia, &mut self.rng);
+        for normalizzazione in self.batch_norm.iter_mut().flatten() {
+            *normalizzazione = BatchNorm::nuova(normalizzazione.neuroni());
+        }
+        self.stato_ottimizzatore = None;
+    }
+
+    /// Sparsità della rete: frazione dei pesi delle connessioni esattamente uguali a zero sul totale.
+    /// Utile per quantificare la compressione ottenuta dopo il pruning (0.0 se la rete non ha pesi).
+    pub fn sparsita(&self) -> f64 {
+        let totale: usize = self.strati.iter().map(|pesi| pesi.len()).sum();
+        if totale == 0 {
+            return 0.0;
+        }
+        let nulli: usize = self.strati.iter()
+            .map(|pesi| pesi.iter().filter(|&&valore| valore == 0.0).count())
+            .sum();
+        nulli as f64 / totale as f64
+    }
+
+    /// Stima dei byte occupati dai parametri della rete (matrici dei pesi e bias) e, dopo il primo passo
+    /// di addestramento, dai momenti dell'ottimizzatore, utile per valutare la fattibilità su hardware
+    /// con memoria limitata. Considera solo i valori numerici (`f64`), non le strutture di contorno.
+    pub fn memoria_stimata(&self) -> usize {
+        let momenti = self.stato_ottimizzatore.as_ref().map_or(0, |stato| {
+            let batch_norm: usize = stato.primo_batch_norm.iter().chain(stato.secondo_batch_norm.iter()).map(|momento| momento.len()).sum();
+            stato.primo.numero_valori() + stato.secondo.numero_valori() + batch_norm
+        });
+        (self.numero_parametri() + momenti) * std::mem::size_of::<f64>()
+    }
+
+    /// Numero totale di parametri addestrabili, pesi e bias, calcolato dalle dimensioni degli strati:
+    /// `n_i * n_(i+1) + n_(i+1)` per ogni coppia di strati consecutivi (es. 65 per una rete `[2, 16, 1]`,
+    /// 48 pesi e 17 bias). Utile per confrontare le architetture e stimare la capacità del modello.
+    pub fn numero_parametri(&self) -> usize {
+        let normalizzazioni: usize = self.batch_norm.iter().flatten().map(|batch_norm| 2 * batch_norm.neuroni()).sum();
+        self.dimensioni_strati.windows(2)
+            .map(|coppia| coppia[0] * coppia[1] + coppia[1])
+            .sum::<usize>() + normalizzazioni
+    }
+
+    /// Dimensione dei vari strati.
+    pub fn strati (&self) ->  Vec<usize> {
+        self.dimensioni_strati.to_vec()
+    }
+
+    /// Nome della funzione di attivazione degli strati
+    /// 
+    /// # Argomenti
+    ///
+    /// * `indice` - l'indice dello strato (0 = input, con la funzione nulla)
+    /// 
+    pub fn funzione_attivazione (&self,indice:usize) ->  &str {
+        self.funzioni_attivazione[indice].nome()
+    }
+
+    ///Lista di tutte le funzioni di attivazione per strati, dall'input verso l'output.
+    /// 
+    pub fn lista_funzioni_attivazioni(&self) -> Vec<String> {
+        let mut lista: Vec<String> =  vec![];
+        for funzione_attivazione in self.funzioni_attivazione.clone().into_iter()  {
+            lista.push(funzione_attivazione.sigla().to_string());
+        }
+        lista
+    }
+
+    /// Congela in blocco i primi `n` strati di connessioni (dall'input verso l'output),
+    /// che non verranno più aggiornati in retropropagazione; gli strati successivi tornano addestrabili.
+    /// L'errore continua comunque a propagarsi all'indietro attraverso gli strati congelati.
+    ///
+    /// Per il fine-tuning graduale basta richiamare il metodo con `n` decrescente,
+    /// scongelando progressivamente gli strati dall'alto (`n = 0` scongela tutto).
+    ///
+    /// # Argomenti
+    ///
+    /// * `n` - Numero di strati di connessioni da congelare (limitato al numero di strati presenti).
+    pub fn congela_fino_a(&mut self, n: usize) {
+        for (i, congelato) in self.strati_congelati.iter_mut().enumerate() {
+            *congelato = i < n;
+        }
+    }
+
+    /// Congela un singolo strato di connessioni: i suoi pesi e bias non vengono più aggiornati
+    /// in retropropagazione, ma l'errore continua a propagarsi all'indietro attraverso di esso.
+    ///
+    /// # Esempio
+    /// ```
+    /// // transfer learning: si riaddestra solo l'ultimo strato
+    /// for indice in 0..rete.strati().len() - 2 {
+    ///     rete.congela_strato(indice)?;
+    /// }
+    /// ```
+    pub fn congela_strato(&mut self, indice: usize) -> Result<(), Error> {
+        self.imposta_congelato(indice, true)
+    }
+
+    /// Rende di nuovo addestrabile uno strato di connessioni congelato.
+    pub fn scongela_strato(&mut self, indice: usize) -> Result<(), Error> {
+        self.imposta_congelato(indice, false)
+    }
+
+    fn imposta_congelato(&mut self, indice: usize, congelato: bool) -> Result<(), Error> {
+        match self.strati_congelati.get_mut(indice) {
+            Some(stato) => {
+                *stato = congelato;
+                Ok(())
+            }
+            None => Err(Error::new(ErrorKind::InvalidInput, format!("strato [{}] inesistente", indice))),
+        }
+    }
+
+    /// Rinizializza con un seme il generatore casuale della rete, da cui derivano tutte le scelte casuali
+    /// dell'addestramento (campionamento dei sottoinsiemi, mescolamento, ...), per risultati riproducibili.
+    pub fn imposta_seme(&mut self, seme: u64) {
+        self.rng = StdRng::seed_from_u64(seme);
+    }
+
+    /// Attiva o disattiva la modalità di debug che traccia la propagazione in avanti:
+    /// per ogni strato viene registrata una riga con min/max/media della pre-attivazione e dell'attivazione,
+    /// utile per capire dove i valori esplodono o si saturano.
+    /// Con la traccia disattivata la propagazione non ha alcun costo aggiuntivo.
+    pub fn imposta_traccia(&mut self, attiva: bool) {
+        self.traccia = attiva;
+    }
+
+    /// Attiva o disattiva la weight normalization: i pesi in ingresso a ogni neurone sono riparametrizzati
+    /// come `g * w/||w||`, separando la direzione (di norma unitaria) dalla magnitudine `g`,
+    /// entrambe addestrabili. Spesso velocizza la convergenza.
+    /// I pesi memorizzati restano quelli effettivi, per cui salvataggio e inferenza non cambiano.
+    pub fn imposta_weight_norm(&mut self, attiva: bool) {
+        self.weight_norm = attiva;
+    }
+
+    /// Imposta il formato di pesi e bias in `salva_pesi_txt` e `salva_strato`. Con `None` (default) ogni valore
+    /// è scritto nella rappresentazione decimale più breve che lo rilegge esattamente; con `Some(cifre)` in
+    /// notazione scientifica con il numero di cifre significative indicato, ad esempio per file più compatti
+    /// con meno cifre. Da 17 cifre in su il round-trip di un `f64` è comunque esatto, bit per bit.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.imposta_cifre_significative(Some(17))?; // 1.2345678901234567e-1
+    /// rete.salva_pesi_txt("rete.txt")?;
+    /// ```
+    pub fn imposta_cifre_significative(&mut self, cifre: Option<usize>) -> Result<(), Error> {
+        if cifre == Some(0) {
+            return Err(Error::new(ErrorKind::InvalidInput, "serve almeno una cifra significativa"));
+        }
+        self.cifre_significative = cifre;
+        Ok(())
+    }
+
+    /// Imposta l'ottimizzatore usato in addestramento (default `SGD`), azzerando i momenti accumulati.
+    ///
+    /// # Argomenti
+    ///
+    /// * `ottimizzatore` - L'ottimizzatore, con i coefficienti `beta` in [0, 1) ed `epsilon` positivo.
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.imposta_ottimizzatore(Ottimizzatore::adam())?;
+    /// ```
+    pub fn imposta_ottimizzatore(&mut self, ottimizzatore: Ottimizzatore) -> Result<(), Error> {
+        let (beta, epsilon) = match ottimizzatore {
+            Ottimizzatore::SGD => (vec![], 1.0),
+            Ottimizzatore::Momentum { beta } => (vec![beta], 1.0),
+            Ottimizzatore::Adam { beta1, beta2, epsilon } => (vec![beta1, beta2], epsilon),
+        };
+        if beta.iter().any(|beta| !(0.0..1.0).contains(beta)) || epsilon.is_nan() || epsilon <= 0.0 {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("parametri dell'ottimizzatore non validi: {:?}", ottimizzatore)));
+        }
+        self.ottimizzatore = ottimizzatore;
+        self.stato_ottimizzatore = None;
+        Ok(())
+    }
+
+    /// L'ottimizzatore usato in addestramento.
+    pub fn ottimizzatore(&self) -> Ottimizzatore {
+        self.ottimizzatore
+    }
+
+    /// Passi di aggiornamento eseguiti dall'ottimizzatore dall'ultimo azzeramento del suo stato
+    /// (cambio di ottimizzatore, caricamento dei pesi, `clona_solo_pesi`).
+    pub fn passi_ottimizzatore(&self) -> usize {
+        self.stato_ottimizzatore.as_ref().map_or(0, |stato| stato.passi as usize)
+    }
+
+    /// Imposta il momentum classico come ottimizzatore: ogni passo accumula una frazione `momentum`
+    /// dello spostamento precedente, accelerando la discesa lungo valli strette della superficie di perdita.
+    /// Con 0 l'aggiornamento torna SGD puro.
+    ///
+    /// # Argomenti
+    ///
+    /// * `momentum` - Coefficiente nell'intervallo [0, 1), tipicamente 0.9.
+    pub fn imposta_momentum(&mut self, momentum: f64) -> Result<(), Error> {
+        if momentum == 0.0 {
+            self.imposta_ottimizzatore(Ottimizzatore::SGD)
+        } else {
+            self.imposta_ottimizzatore(Ottimizzatore::Momentum { beta: momentum })
+        }
+    }
+
+    /// Imposta la regolarizzazione L1 sui pesi (non sui bias): a ogni aggiornamento ogni peso si avvicina
+    /// a zero di `tasso * lambda_l1`, portando esattamente a zero i pesi poco utili e producendo reti
+    /// più sparse e interpretabili (vedi `sparsita`). Con 0 (default) è disattivata.
+    ///
+    /// # Argomenti
+    ///
+    /// * `lambda_l1` - Coefficiente della regolarizzazione, non negativo.
+    pub fn imposta_l1(&mut self, lambda_l1: f64) -> Result<(), Error> {
+        if !lambda_l1.is_finite() || lambda_l1 < 0.0 {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("coefficiente L1 non valido: {}", lambda_l1)));
+        }
+        self.lambda_l1 = lambda_l1;
+        Ok(())
+    }
+
+    /// Imposta il gradient clipping: prima di ogni aggiornamento, se la norma del gradiente di uno strato
+    /// (pesi e bias insieme) supera `massimo` viene riscalato a quella norma. Evita che gradienti esplosivi
+    /// (ReLU, tassi alti, reti profonde) facciano divergere l'addestramento. `None` (default) lo disattiva.
+    ///
+    /// # Argomenti
+    ///
+    /// * `massimo` - Norma massima del gradiente per strato, positiva.
+    pub fn imposta_clip_gradiente(&mut self, massimo: Option<f64>) -> Result<(), Error> {
+        if let Some(massimo) = massimo.filter(|massimo| massimo.is_nan() || *massimo <= 0.0) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("norma massima del gradiente non valida: {}", massimo)));
+        }
+        self.clip_gradiente = massimo;
+        Ok(())
+    }
+
+    /// Imposta DropConnect per ogni matrice dei pesi (0 = connessioni tra input e primo strato nascosto):
+    /// durante l'addestramento ogni peso viene azzerato con la probabilità indicata, estraendo una nuova maschera
+    /// a ogni esempio, come regolarizzazione alternativa al dropout sulle attivazioni.
+    /// In inferenza tutti i pesi sono attivi e l'output è deterministico. Con tutte le probabilità a 0
+    /// (default) DropConnect è disattivato.
+    ///
+    /// # Argomenti
+    ///
+    /// * `probabilita` - Probabilità di azzeramento per ogni matrice dei pesi, nell'intervallo [0, 1).
+    ///
+    /// # Esempio
+    /// ```
+    /// rete.imposta_dropconnect(vec![0.2, 0.0])?; // solo le connessioni input-nascosto
+    /// ```
+    pub fn imposta_dropconnect(&mut self, probabilita: Vec<f64>) -> Result<(), Error> {
+        if probabilita.len() != self.strati.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("attese {} probabilità DropConnect, trovate {}", self.strati.len(), probabilita.len())
+            ));
+        }
+        if let Some(p) = probabilita.iter().find(|p| !(0.0..1.0).contains(*p)) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("probabilità DropConnect non valida: {}", p)));
+        }
+        self.dropconnect = probabilita;
+        Ok(())
+    }
+
+    /// Direzioni dei pesi in ingresso a ogni neurone (righe normalizzate a norma unitaria), strato per strato.
+    /// Con la weight normalization sono le direzioni `w/||w||` della riparametrizzazione.
+    pub fn direzioni_pesi(&self) -> Vec<DMatrix<f64>> {
+        self.strati.iter()
+            .map(|pesi| {
+                let mut direzioni = pesi.clone();
+                for mut riga in direzioni.row_iter_mut() {
+                    let norma = riga.norm();
+                    if norma > 0.0 {
+                        riga /= norma;
+                    }
+                }
+                direzioni
+            })
+            .collect()
+    }
+
+    /// Restituisce le righe di traccia registrate finora e svuota il registro.
+    /// Ogni rete ha il proprio registro: i cloni partono con un registro vuoto e non vedono le righe dell'originale.
+    pub fn preleva_traccia(&self) -> Vec<String> {
+        match self.registro_traccia.lock() {
+            Ok(mut registro) => std::mem::take(&mut *registro),
+            Err(_) => vec![]
+        }
+    }
+
+    /// Imposta la funzione di perdita minimizzata in addestramento (default `ErroreQuadraticoMedio`).
+    pub fn imposta_perdita(&mut self, perdita: Arc<dyn FunzionePerdita + Send + Sync>) {
+        self.perdita = perdita;
+    }
+
+    /// Nome della funzione di perdita.
+    pub fn funzione_perdita(&self) -> &str {
+        self.perdita.nome()
+    }
+
+    /// Funzione di perdita minimizzata in addestramento.
+    pub fn perdita(&self) -> Arc<dyn FunzionePerdita + Send + Sync> {
+        self.perdita.clone()
+    }
+
+    /// Funzioni di attivazione di ogni strato, dall'input (funzione nulla) all'output.
+    pub fn funzioni_attivazione(&self) -> &[Arc<dyn FunzioneAttivazione + Send + Sync>] {
+        &self.funzioni_attivazione
+    }
+
+    /// Riscala il tasso di apprendimento per una nuova dimensione del batch con la regola di scaling lineare:
+    /// `tasso = tasso * dimensione_batch / batch_riferimento`, dove il riferimento è la dimensione per cui
+    /// il tasso attuale è stato scelto (inizialmente 1, addestramento online).
+    /// La nuova dimensione diventa il riferimento, per cui chiamate successive non si accumulano.
+    /// La regola è opzionale: se non si chiama il metodo il tasso resta invariato.
+    ///
+    /// # Argomenti
+    ///
+    /// * `dimensione_batch` - La nuova dimensione del batch (valori nulli sono ignorati).
+    pub fn adatta_tasso_a_batch(&mut self, dimensione_batch: usize) {
+        if dimensione_batch == 0 {
+            return;
+        }
+        self.tasso_apprendimento *= dimensione_batch as f64 / self.batch_riferimento as f64;
+        self.batch_riferimento = dimensione_batch;
+    }
+
+    /// Tasso di apprendimento.
+    pub fn tasso_apprendimento (&self) ->  f64 {
+        self.tasso_apprendimento
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strato(neuroni: usize, funzione_attivazione: Arc<dyn FunzioneAttivazione + Send + Sync>) -> Strato {
+        Strato { neuroni, funzione_attivazione }
+    }
+
+    fn dati_xor() -> Vec<InputAddestramento> {
+        vec![
+            InputAddestramento { input: vec![0.0, 0.0], output: vec![0.0] },
+            InputAddestramento { input: vec![0.0, 1.0], output: vec![1.0] },
+            InputAddestramento { input: vec![1.0, 0.0], output: vec![1.0] },
+            InputAddestramento { input: vec![1.0, 1.0], output: vec![0.0] },
+        ]
+    }
+
+    /// Rete `[2, 4, 1]` con Sigmoide, deterministica a parità di seme.
+    fn rete_xor(seme: u64) -> ReteNeurale {
+        ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.5, seme)
+    }
+
+    /// Percorso di un file temporaneo, distinto per test e per processo.
+    fn file_temporaneo(nome: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("rete_neurale_mlp_{}_{}", std::process::id(), nome))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Punti di una griglia 5×5 in [0, 1]², di classe 1 sopra la diagonale.
+    fn dati_diagonale() -> Vec<InputAddestramento> {
+        (0..25)
+            .map(|i| {
+                let (x, y) = ((i % 5) as f64 / 4.0, (i / 5) as f64 / 4.0);
+                InputAddestramento { input: vec![x, y], output: vec![f64::from(u8::from(y > x))] }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pre_addestramento_riduce_la_perdita() {
+        let dati = dati_diagonale();
+        let mut rete = rete_xor(1);
+        let iniziale = rete.perdita_media(&dati);
+        rete.pre_addestra(&dati, 0.5, 200);
+        assert!(rete.perdita_media(&dati) < iniziale);
+    }
+
+    #[test]
+    fn congela_fino_a_blocca_solo_i_primi_strati() {
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(3, Arc::new(Sigmoide)),
+            strato(3, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.5, 2);
+        let iniziali = rete.matrici_pesi().to_vec();
+        rete.congela_fino_a(2);
+        rete.addestra_epoche(&dati_xor(), 10, OpzioniAddestramento::default()).unwrap();
+        assert_eq!(rete.matrici_pesi()[0], iniziali[0]);
+        assert_eq!(rete.matrici_pesi()[1], iniziali[1]);
+        assert_ne!(rete.matrici_pesi()[2], iniziali[2]);
+
+        rete.congela_fino_a(0);
+        rete.addestra_epoche(&dati_xor(), 10, OpzioniAddestramento::default()).unwrap();
+        assert_ne!(rete.matrici_pesi()[0], iniziali[0]);
+    }
+
+    #[test]
+    fn traccia_una_riga_per_strato() {
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(3, Arc::new(ReLU)),
+            strato(3, Arc::new(Tanh)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.1, 3);
+        rete.imposta_traccia(true);
+        rete.elabora(vec![0.5, -0.5]);
+        let righe = rete.preleva_traccia();
+        assert_eq!(righe.len(), 3);
+        for (k, riga) in righe.iter().enumerate() {
+            assert!(riga.starts_with(&format!("strato [{}]", k + 1)), "{}", riga);
+        }
+        assert!(rete.preleva_traccia().is_empty());
+
+        let copia = rete.clone();
+        copia.elabora(vec![0.5, -0.5]);
+        assert!(rete.preleva_traccia().is_empty());
+        assert_eq!(copia.preleva_traccia().len(), 3);
+    }
+
+    #[test]
+    fn distribuzione_classi_di_un_set_sbilanciato() {
+        let etichette = [0, 0, 0, 0, 0, 1, 2, 2];
+        let dati: Vec<InputAddestramento> = etichette.iter()
+            .map(|&classe| InputAddestramento::da_etichetta(vec![0.0], classe, 3).unwrap())
+            .collect();
+        assert_eq!(distribuzione_classi(&dati), vec![5, 1, 2]);
+
+        let binari: Vec<InputAddestramento> = [0.0, 1.0, 1.0, 0.9, 0.1].iter()
+            .map(|&target| InputAddestramento { input: vec![0.0], output: vec![target] })
+            .collect();
+        assert_eq!(distribuzione_classi(&binari), vec![2, 3]);
+        assert!(distribuzione_classi(&[]).is_empty());
+    }
+
+    #[test]
+    fn durata_massima_interrompe_presto() {
+        let mut rete = rete_xor(4);
+        let opzioni = OpzioniAddestramento { durata_massima: Some(Duration::from_millis(1)), ..Default::default() };
+        let inizio = Instant::now();
+        let perdite = rete.addestra_epoche(&dati_xor(), 1_000_000, opzioni).unwrap();
+        assert!(!perdite.is_empty());
+        assert!(perdite.len() < 1_000_000);
+        assert!(inizio.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn weight_norm_direzioni_di_norma_unitaria() {
+        let mut rete = rete_xor(5);
+        rete.imposta_weight_norm(true);
+        let iniziale = rete.perdita_media(&dati_xor());
+        rete.addestra_epoche(&dati_xor(), 200, OpzioniAddestramento::default()).unwrap();
+        assert!(rete.perdita_media(&dati_xor()) < iniziale);
+        for direzioni in rete.direzioni_pesi() {
+            for riga in direzioni.row_iter() {
+                assert!((riga.norm() - 1.0).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn incertezza_con_deviazione_positiva() {
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(1, Arc::new(Nessuna)),
+            strato(8, Arc::new(Tanh)),
+            strato(2, Arc::new(Lineare)),
+        ], 0.01, 6);
+        rete.imposta_perdita(Arc::new(NLLGaussiana));
+        let dati: Vec<InputAddestramento> = (0..20)
+            .map(|i| {
+                let x = i as f64 / 19.0;
+                let rumore = if i % 2 == 0 { 0.1 } else { -0.1 };
+                InputAddestramento { input: vec![x], output: vec![x + rumore] }
+            })
+            .collect();
+        rete.addestra_epoche(&dati, 100, OpzioniAddestramento::default()).unwrap();
+        for set in dati.iter() {
+            let previsioni = rete.elabora_con_incertezza(set.input.clone());
+            assert_eq!(previsioni.len(), 1);
+            assert!(previsioni[0].1 > 0.0 && previsioni[0].1.is_finite());
+        }
+    }
+
+    #[test]
+    fn sparsita_dopo_il_pruning() {
+        let mut rete = rete_xor(7);
+        assert_eq!(rete.sparsita(), 0.0);
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(4, 2, &[0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0])).unwrap();
+        rete.imposta_pesi_strato(1, DMatrix::from_row_slice(1, 4, &[0.0, 0.3, 0.4, 0.5])).unwrap();
+        assert!((rete.sparsita() - 5.0 / 12.0).abs() < 1e-12);
+
+        let mut potata = rete_xor(7);
+        let soglia = 0.5;
+        let piccoli = potata.matrici_pesi().iter().flat_map(|pesi| pesi.iter()).filter(|peso| peso.abs() < soglia).count();
+        potata.mappa_pesi(|peso| if peso.abs() < soglia { 0.0 } else { peso });
+        assert!((potata.sparsita() - piccoli as f64 / 12.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn elabora_matrice_con_batch_di_dimensione_variabile() {
+        let rete = rete_xor(8);
+        for campioni in [1, 10, 1000] {
+            let input = DMatrix::from_fn(2, campioni, |r, c| ((r + 1) * c) as f64 / campioni as f64);
+            let uscite = rete.elabora_matrice(&input);
+            assert_eq!(uscite.shape(), (1, campioni));
+            for (c, colonna) in input.column_iter().enumerate() {
+                let attesa = rete.elabora(colonna.iter().copied().collect());
+                assert!((uscite[(0, c)] - attesa[0]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_esadecimale() {
+        let mut rete = rete_xor(9);
+        rete.mappa_pesi(|peso| if peso.abs() < 0.3 { 0.0 } else { peso });
+        let testo = rete.a_hex();
+        assert!(testo.chars().all(|c| c.is_ascii_hexdigit()));
+        let ricaricata = ReteNeurale::da_hex(&testo).unwrap();
+        assert!(rete.pesi_uguali(&ricaricata, 0.0));
+        assert_eq!(rete.elabora(vec![1.0, 0.0]), ricaricata.elabora(vec![1.0, 0.0]));
+        assert!(ReteNeurale::da_hex("zz").is_err());
+        assert!(ReteNeurale::da_hex(&testo[..testo.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn campionamento_segue_le_probabilita() {
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(1, Arc::new(Nessuna)),
+            strato(3, Arc::new(Lineare)),
+        ], 0.1, InizializzazionePesi::Xavier); // bias nulli
+        rete.imposta_pesi_strato(0, DMatrix::from_column_slice(3, 1, &[0.0, 2f64.ln(), 3f64.ln()])).unwrap();
+        let attese = [1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0];
+        let estrazioni = 6000;
+        let mut conteggi = [0usize; 3];
+        for seme in 0..estrazioni {
+            conteggi[rete.campiona(vec![1.0], 1.0, seme).unwrap()] += 1;
+        }
+        for (conteggio, attesa) in conteggi.iter().zip(attese.iter()) {
+            assert!((*conteggio as f64 / estrazioni as f64 - attesa).abs() < 0.03, "{:?}", conteggi);
+        }
+    }
+
+    #[test]
+    fn campiona_rifiuta_temperature_non_positive() {
+        let rete = ReteNeurale::nuova_con_seme(vec![strato(1, Arc::new(Nessuna)), strato(3, Arc::new(Lineare))], 0.1, 3);
+        for temperatura in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let errore = rete.campiona(vec![1.0], temperatura, 0).unwrap_err();
+            assert_eq!(errore.kind(), ErrorKind::InvalidInput);
+        }
+        assert!(rete.campiona(vec![1.0], 0.5, 0).unwrap() < 3);
+    }
+
+    #[test]
+    fn compatibilita_tra_reti() {
+        let rete = rete_xor(10);
+        assert_eq!(rete.compatibile_con(&rete_xor(11)), Ok(()));
+
+        let piu_larga = ReteNeurale::nuova(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(5, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.5);
+        assert_eq!(
+            rete.compatibile_con(&piu_larga),
+            Err(IncompatibilitaRete::DimensioneStrato { strato: 1, attesa: 4, trovata: 5 })
+        );
+
+        let piu_profonda = ReteNeurale::nuova(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(Sigmoide)),
+            strato(4, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.5);
+        assert_eq!(rete.compatibile_con(&piu_profonda), Err(IncompatibilitaRete::NumeroStrati { atteso: 3, trovato: 4 }));
+
+        let altra_attivazione = rete.sostituisci_attivazione("Sigmoide", Arc::new(Tanh));
+        assert!(matches!(
+            rete.compatibile_con(&altra_attivazione),
+            Err(IncompatibilitaRete::FunzioneAttivazione { indice: 1, .. })
+        ));
+
+        let leaky = |alpha| ReteNeurale::nuova(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(1, Arc::new(LeakyReLU { alpha })),
+        ], 0.5);
+        assert!(leaky(0.1).compatibile_con(&leaky(0.2)).is_err());
+    }
+
+    #[test]
+    fn perdita_contrastiva_avvicina_le_coppie_simili() {
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(3, Arc::new(Tanh)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.1, 12);
+        let (a, b) = (vec![1.0, 0.0], vec![0.0, 1.0]);
+        let distanza = |rete: &ReteNeurale| {
+            let embedding = rete.embedding(&[a.clone(), b.clone()], 1).unwrap();
+            (embedding.row(0) - embedding.row(1)).norm()
+        };
+        let iniziale = distanza(&rete);
+        let mut perdite = Vec::new();
+        for _ in 0..50 {
+            perdite.push(rete.addestra_coppia(a.clone(), b.clone(), true).unwrap());
+        }
+        assert!(distanza(&rete) < iniziale);
+        assert!(perdite.last() < perdite.first());
+
+        let mut senza_nascosti = ReteNeurale::nuova(vec![strato(2, Arc::new(Nessuna)), strato(1, Arc::new(Sigmoide))], 0.1);
+        assert!(senza_nascosti.addestra_coppia(a.clone(), b.clone(), true).is_err());
+    }
+
+    #[test]
+    fn statistiche_gradienti_svaniscono_verso_l_input() {
+        let mut info_strati = vec![strato(2, Arc::new(Nessuna))];
+        info_strati.extend((0..6).map(|_| strato(4, Arc::new(Sigmoide) as Arc<dyn FunzioneAttivazione + Send + Sync>)));
+        info_strati.push(strato(1, Arc::new(Sigmoide)));
+        let rete = ReteNeurale::nuova_con_seme(info_strati, 0.1, 13);
+        let statistiche = rete.statistiche_gradienti(&[1.0, 0.0], &[1.0]);
+        let gradienti = rete.calcola_gradienti(&[1.0, 0.0], &[1.0]);
+        assert_eq!(statistiche.len(), 7);
+        for (stat, gradiente) in statistiche.iter().zip(gradienti.iter()) {
+            assert!((stat.norma - gradiente.norm()).abs() < 1e-15);
+            assert!(stat.min <= stat.media && stat.media <= stat.max);
+        }
+        // con la Sigmoide (derivata al più 1/4) il gradiente si attenua strato dopo strato
+        assert!(statistiche[0].norma < statistiche[6].norma);
+    }
+
+    #[test]
+    fn trasferimento_di_uno_strato() {
+        let origine = rete_xor(14);
+        let mut destinazione = rete_xor(15);
+        let file = file_temporaneo("strato.txt");
+        origine.salva_strato(0, &file).unwrap();
+        destinazione.carica_strato(0, &file).unwrap();
+        assert_eq!(destinazione.matrici_pesi()[0], origine.matrici_pesi()[0]);
+        assert_eq!(destinazione.matrici_bias()[0], origine.matrici_bias()[0]);
+        assert_ne!(destinazione.matrici_pesi()[1], origine.matrici_pesi()[1]);
+
+        assert!(destinazione.carica_strato(1, &file).is_err()); // forma 1x4 contro 4x2
+        assert!(origine.salva_strato(2, &file).is_err());
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn isru_limiti_e_derivata_in_zero() {
+        let isru = ISRU { alpha: 4.0 };
+        assert!((isru.attiva(1e8) - 0.5).abs() < 1e-9);
+        assert!((isru.attiva(-1e8) + 0.5).abs() < 1e-9);
+        assert_eq!(isru.attiva(0.0), 0.0);
+        assert_eq!(isru.derivata(0.0), 1.0);
+        let h = 1e-6;
+        for x in [-2.0, -0.3, 0.7, 3.0] {
+            let numerica = (isru.attiva(x + h) - isru.attiva(x - h)) / (2.0 * h);
+            assert!((isru.derivata(x) - numerica).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn addestramento_deterministico_con_seme() {
+        let addestra = || {
+            let mut rete = rete_xor(16);
+            rete.imposta_dropconnect(vec![0.2, 0.1]).unwrap();
+            let opzioni = OpzioniAddestramento { seme: Some(99), mescola: true, ..Default::default() };
+            let perdite = rete.addestra_epoche(&dati_xor(), 50, opzioni).unwrap();
+            (rete, perdite)
+        };
+        let (a, perdite_a) = addestra();
+        let (b, perdite_b) = addestra();
+        assert!(a.pesi_uguali(&b, 0.0));
+        assert_eq!(perdite_a, perdite_b);
+    }
+
+    /// Set di regressione `y = sin(x0) * x1` di `n` esempi.
+    fn dati_regressione(n: usize) -> Vec<InputAddestramento> {
+        (0..n)
+            .map(|i| {
+                let (x0, x1) = (i as f64 * 0.37 % 3.0, (i % 7) as f64 / 7.0);
+                InputAddestramento { input: vec![x0, x1], output: vec![x0.sin() * x1] }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn perdita_parallela_uguale_alla_seriale() {
+        let rete = rete_xor(17);
+        for n in [0, 1, 3, 1000] {
+            let dati = dati_regressione(n);
+            let seriale = rete.perdita_media(&dati);
+            let parallela = rete.perdita_media_parallela(&dati);
+            assert!((seriale - parallela).abs() <= 1e-12 * seriale.abs().max(1.0), "{} {}", seriale, parallela);
+        }
+    }
+
+    /// Benchmark informale: `cargo test --release -- --ignored --nocapture benchmark_perdita_parallela`.
+    #[test]
+    #[ignore]
+    fn benchmark_perdita_parallela() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(64, Arc::new(Tanh)),
+            strato(64, Arc::new(Tanh)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.1, 18);
+        let dati = dati_regressione(100_000);
+        let inizio = Instant::now();
+        let seriale = rete.perdita_media(&dati);
+        let tempo_seriale = inizio.elapsed();
+        let inizio = Instant::now();
+        let parallela = rete.perdita_media_parallela(&dati);
+        let tempo_parallelo = inizio.elapsed();
+        println!("seriale: {:?} ({}), parallela: {:?} ({})", tempo_seriale, seriale, tempo_parallelo, parallela);
+    }
+
+    #[test]
+    fn feature_hashing_deterministico() {
+        let valori = ["colore=rosso", "taglia=M", "citta=Napoli"];
+        let a = hash_features(&valori, 16);
+        assert_eq!(a.len(), 16);
+        assert_eq!(a, hash_features(&valori, 16));
+        assert!(a.iter().map(|v| v.abs()).sum::<f64>() <= 3.0);
+        assert!(a.iter().all(|v| v.fract() == 0.0));
+        assert_eq!(hash_features(&valori, 7).len(), 7);
+        assert!(hash_features(&valori, 0).is_empty());
+        assert_eq!(hash_features(&[], 4), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn cerca_larghezza_valida_su_un_holdout() {
+        // Scacchiera 2x2 su una griglia 6x6: come lo XOR, un solo neurone nascosto non basta.
+        let dati: Vec<InputAddestramento> = (0..36)
+            .map(|i| {
+                let (x, y) = ((i % 6) as f64 / 5.0, (i / 6) as f64 / 5.0);
+                InputAddestramento { input: vec![x, y], output: vec![f64::from(u8::from((x > 0.5) != (y > 0.5)))] }
+            })
+            .collect();
+        let larghezza = ReteNeurale::cerca_larghezza(&dati, 0.75, 1, 4, 1000, 2.0, 7).unwrap();
+        assert!((2..=4).contains(&larghezza), "{}", larghezza);
+        assert_eq!(ReteNeurale::cerca_larghezza(&dati, 0.75, 1, 4, 1000, 2.0, 7).unwrap(), larghezza);
+    }
+
+    #[test]
+    fn cerca_larghezza_rifiuta_divisioni_vuote() {
+        let dati = dati_diagonale();
+        for frazione in [0.0, 1.0, -0.5, 1.5, f64::NAN] {
+            let errore = ReteNeurale::cerca_larghezza(&dati, frazione, 1, 2, 10, 0.5, 7).unwrap_err();
+            assert_eq!(errore.kind(), ErrorKind::InvalidInput);
+        }
+        // con tre esempi, il 90% lascia vuoto il set di validazione e il 10% quello di addestramento
+        for frazione in [0.9, 0.1] {
+            assert!(ReteNeurale::cerca_larghezza(&dati[..3], frazione, 1, 2, 10, 0.5, 7).is_err());
+        }
+        assert!(ReteNeurale::cerca_larghezza(&[], 0.5, 1, 2, 10, 0.5, 7).is_err());
+    }
+
+    #[test]
+    fn elabora_stream_uguale_a_elabora_su_ogni_elemento() {
+        let rete = rete_xor(3);
+        let input: Vec<Vec<f64>> = dati_xor().into_iter().map(|set| set.input).collect();
+        let uscite: Vec<Vec<f64>> = rete.elabora_stream(input.clone().into_iter()).collect();
+        assert_eq!(uscite.len(), input.len());
+        for (uscita, input) in uscite.iter().zip(input) {
+            assert_eq!(*uscita, rete.elabora(input));
+        }
+    }
+
+    #[test]
+    fn adatta_tasso_a_batch_scala_linearmente() {
+        let mut rete = rete_xor(1);
+        rete.adatta_tasso_a_batch(8);
+        assert!((rete.tasso_apprendimento() - 4.0).abs() < 1e-12);
+        // Il riferimento è ora 8: passare a 2 divide per 4, senza accumulare le chiamate precedenti.
+        rete.adatta_tasso_a_batch(2);
+        assert!((rete.tasso_apprendimento() - 1.0).abs() < 1e-12);
+        rete.adatta_tasso_a_batch(0);
+        assert!((rete.tasso_apprendimento() - 1.0).abs() < 1e-12);
+        rete.adatta_tasso_a_batch(1);
+        assert!((rete.tasso_apprendimento() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn da_config_ricrea_l_architettura() {
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(5, Arc::new(LeakyReLU { alpha: 0.1 })),
+            strato(4, Arc::new(Tanh)),
+            strato(2, Arc::new(Sigmoide)),
+        ], 0.05, 11);
+        rete.imposta_perdita(Arc::new(Huber { delta: 0.5 }));
+        rete.imposta_ottimizzatore(Ottimizzatore::adam()).unwrap();
+        let percorso = file_temporaneo("config.json");
+        rete.salva_config(&percorso).unwrap();
+        let copia = ReteNeurale::da_config(&percorso).unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        assert_eq!(copia.strati(), vec![3, 5, 4, 2]);
+        assert_eq!(copia.lista_funzioni_attivazioni(), rete.lista_funzioni_attivazioni());
+        assert_eq!(copia.funzioni_attivazione()[1].alfa(), 0.1);
+        assert_eq!(copia.tasso_apprendimento(), 0.05);
+        assert_eq!(copia.perdita().sigla(), rete.perdita().sigla());
+        assert_eq!(copia.perdita().parametro(), 0.5);
+        assert_eq!(copia.ottimizzatore(), Ottimizzatore::adam());
+        assert_eq!(copia.elabora(vec![0.1, 0.2, 0.3]).len(), 2);
+    }
+
+    #[test]
+    fn memoria_stimata_conta_i_momenti_dell_ottimizzatore() {
+        // [2, 4, 1]: 2*4 + 4 + 4*1 + 1 = 17 parametri da 8 byte
+        let mut rete = rete_xor(5);
+        rete.imposta_ottimizzatore(Ottimizzatore::adam()).unwrap();
+        assert_eq!(rete.memoria_stimata(), 17 * 8);
+        rete.addestra_epoche(&dati_xor(), 1, OpzioniAddestramento::default()).unwrap();
+        // Adam aggiunge i momenti primo e secondo, con le stesse dimensioni dei parametri.
+        assert_eq!(rete.memoria_stimata(), 3 * 17 * 8);
+    }
+
+    #[test]
+    fn inizializzazione_identita_approssima_l_identita() {
+        let rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(4, Arc::new(Nessuna)),
+            strato(4, Arc::new(Lineare)),
+            strato(4, Arc::new(Lineare)),
+        ], 0.1, InizializzazionePesi::Identita);
+        let input = vec![0.5, -1.0, 2.0, 0.25];
+        let uscita = rete.elabora(input.clone());
+        // Due strati con rumore di ampiezza RUMORE_IDENTITA su 4 pesi per riga.
+        for (uscita, atteso) in uscita.iter().zip(input.iter()) {
+            assert!((uscita - atteso).abs() < 0.2, "uscita {} invece di {}", uscita, atteso);
+        }
+    }
+
+    #[test]
+    fn mappa_pesi_raddoppia_solo_i_pesi() {
+        let mut rete = rete_xor(8);
+        let pesi = rete.matrici_pesi().to_vec();
+        let bias = rete.matrici_bias().to_vec();
+        rete.mappa_pesi(|peso| 2.0 * peso);
+        for (dopo, prima) in rete.matrici_pesi().iter().zip(pesi.iter()) {
+            assert_eq!(*dopo, prima * 2.0);
+        }
+        assert_eq!(rete.matrici_bias(), bias.as_slice());
+    }
+
+    #[test]
+    fn da_etichetta_codifica_one_hot() {
+        let set = InputAddestramento::da_etichetta(vec![0.2, 0.7], 2, 3).unwrap();
+        assert_eq!(set.input, vec![0.2, 0.7]);
+        assert_eq!(set.output, vec![0.0, 0.0, 1.0]);
+        let errore = InputAddestramento::da_etichetta(vec![0.2, 0.7], 3, 3).err().unwrap();
+        assert_eq!(errore.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn diagnostica_convergenza_riconosce_ogni_andamento() {
+        let convergente: Vec<f64> = (0..20).map(|i| 1.0 / (1.0 + i as f64)).collect();
+        let divergente: Vec<f64> = (0..20).map(|i| 0.1 * 1.5f64.powi(i)).collect();
+        let oscillante: Vec<f64> = (0..20).map(|i| if i % 2 == 0 { 0.5 } else { 0.8 }).collect();
+        let plateau = vec![0.25; 20];
+        assert_eq!(diagnostica_convergenza(&convergente), Diagnostica::Convergente);
+        assert_eq!(diagnostica_convergenza(&divergente), Diagnostica::Divergente);
+        assert_eq!(diagnostica_convergenza(&[0.5, 0.4, f64::NAN]), Diagnostica::Divergente);
+        assert_eq!(diagnostica_convergenza(&oscillante), Diagnostica::Oscillante);
+        assert_eq!(diagnostica_convergenza(&plateau), Diagnostica::Plateau);
+        assert_eq!(diagnostica_convergenza(&[0.5]), Diagnostica::DatiInsufficienti);
+        assert_eq!(diagnostica_convergenza(&[]), Diagnostica::DatiInsufficienti);
+    }
+
+    #[test]
+    fn multi_sorgente_bilancia_le_sorgenti() {
+        let grande: Vec<InputAddestramento> = (0..30).map(|i| InputAddestramento { input: vec![i as f64 / 30.0, 0.0], output: vec![0.0] }).collect();
+        let piccola: Vec<InputAddestramento> = (0..6).map(|i| InputAddestramento { input: vec![0.0, i as f64 / 6.0], output: vec![1.0] }).collect();
+        let vuota: Vec<InputAddestramento> = Vec::new();
+        let sorgenti: [&[InputAddestramento]; 3] = [&grande, &piccola, &vuota];
+        let mut rete = rete_xor(4);
+        // 36 esempi per epoca divisi tra le due sorgenti non vuote
+        assert_eq!(rete.addestra_multi_sorgente(&sorgenti, 5, BilanciamentoSorgenti::Uguale), vec![90, 90, 0]);
+        assert_eq!(rete.addestra_multi_sorgente(&sorgenti, 5, BilanciamentoSorgenti::Proporzionale), vec![150, 30, 0]);
+    }
+
+    #[test]
+    fn esporta_attivazioni_csv_una_riga_per_esempio_e_una_colonna_per_neurone() {
+        let rete = rete_xor(6);
+        let percorso = file_temporaneo("attivazioni.csv");
+        rete.esporta_attivazioni_csv(&dati_xor(), 1, &percorso).unwrap();
+        let testo = std::fs::read_to_string(&percorso).unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        let righe: Vec<&str> = testo.lines().collect();
+        assert_eq!(righe.len(), 4);
+        assert!(righe.iter().all(|riga| riga.split(',').count() == 4));
+        assert!(rete.esporta_attivazioni_csv(&dati_xor(), 3, &percorso).is_err());
+    }
+
+    #[test]
+    fn dropconnect_deterministico_in_inferenza_e_casuale_in_addestramento() {
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(8, Arc::new(Nessuna)),
+            strato(16, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.1, 13);
+        let input = vec![0.5; 8];
+        let senza = rete.elabora(input.clone());
+        rete.imposta_dropconnect(vec![0.5, 0.0]).unwrap();
+        assert_eq!(rete.elabora(input.clone()), senza);
+        assert_eq!(rete.elabora(input), senza);
+
+        let prima = rete.maschere_dropconnect().unwrap();
+        let seconda = rete.maschere_dropconnect().unwrap();
+        assert_ne!(prima[0], seconda[0]);
+        assert!(prima[0].iter().any(|&m| m == 0.0) && prima[0].iter().any(|&m| m == 2.0));
+        assert!(prima[1].iter().all(|&m| m == 1.0));
+    }
+
+    #[test]
+    fn profilo_perdita_con_minimo_locale_nei_pesi_ottimi() {
+        // y = 2 x0 - x1: con i pesi ottimi la perdita è nulla in t = 0 e cresce in entrambi i versi.
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(1, Arc::new(Lineare)),
+        ], 0.1, InizializzazionePesi::Xavier);
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(1, 2, &[2.0, -1.0])).unwrap();
+        let dati: Vec<InputAddestramento> = (0..10)
+            .map(|i| {
+                let (x0, x1) = (i as f64 / 10.0, 1.0 - i as f64 / 5.0);
+                InputAddestramento { input: vec![x0, x1], output: vec![2.0 * x0 - x1] }
+            })
+            .collect();
+        let profilo = rete.profilo_perdita(&dati, &[3.0, 4.0], 21, 1.0);
+        assert_eq!(profilo.len(), 21);
+        assert!((profilo[0].0 + 1.0).abs() < 1e-12 && (profilo[20].0 - 1.0).abs() < 1e-12);
+        let (t_minimo, perdita_minima) = profilo.iter().copied().fold((0.0, f64::INFINITY), |a, b| if b.1 < a.1 { b } else { a });
+        assert!(t_minimo.abs() < 1e-12);
+        assert!(perdita_minima < 1e-20);
+        assert!(profilo[9].1 > perdita_minima && profilo[11].1 > perdita_minima);
+        assert_eq!(rete.matrici_pesi()[0], DMatrix::from_row_slice(1, 2, &[2.0, -1.0]));
+    }
+
+    #[test]
+    fn checkpoint_salva_la_rete_migliore_per_una_metrica_combinata() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let percorso = file_temporaneo("checkpoint.txt");
+        let migliore: Rc<RefCell<(f64, Option<ReteNeurale>)>> = Rc::new(RefCell::new((f64::NEG_INFINITY, None)));
+        let registro = migliore.clone();
+        let dati = dati_xor();
+        // Accuratezza meno perdita, penalizzata nelle epoche pari: la metrica sale e scende nel tempo.
+        let mut epoca = 0;
+        let metrica = move |rete: &ReteNeurale| {
+            epoca += 1;
+            let valore = rete.accuratezza(&dati, 0.5) - rete.perdita_media(&dati) - if epoca % 2 == 0 { 1.0 } else { 0.0 };
+            let mut migliore = registro.borrow_mut();
+            if valore > migliore.0 {
+                *migliore = (valore, Some(rete.clone()));
+            }
+            valore
+        };
+        let mut rete = rete_xor(21);
+        let opzioni = OpzioniAddestramento {
+            checkpoint: Some(Checkpoint { file_path: percorso.clone(), metrica: Box::new(metrica) }),
+            ..Default::default()
+        };
+        assert_eq!(rete.addestra_epoche(&dati_xor(), 20, opzioni).unwrap().len(), 20);
+        let salvata = ReteNeurale::carica(&percorso).unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        let attesa = migliore.borrow_mut().1.take().unwrap();
+        assert!(salvata.pesi_uguali(&attesa, 1e-12));
+        assert!(!salvata.pesi_uguali(&rete, 1e-12));
+    }
+
+    #[test]
+    fn errore_del_checkpoint_interrompe_l_addestramento() {
+        let mut rete = rete_xor(21);
+        let opzioni = OpzioniAddestramento {
+            checkpoint: Some(Checkpoint {
+                file_path: std::env::temp_dir().to_string_lossy().into_owned(), // una cartella, non un file
+                metrica: Box::new(|rete: &ReteNeurale| -rete.perdita_media(&dati_xor())),
+            }),
+            ..Default::default()
+        };
+        let errore = rete.addestra_epoche(&dati_xor(), 20, opzioni).err().unwrap();
+        assert!(errore.to_string().contains("checkpoint non salvato"));
+    }
+
+    #[test]
+    fn distillazione_avvicina_l_allieva_all_insegnante() {
+        let insegnante = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(8, Arc::new(Tanh)),
+            strato(3, Arc::new(Lineare)),
+        ], 0.1, 30);
+        let mut allieva = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(Tanh)),
+            strato(3, Arc::new(Lineare)),
+        ], 0.1, 31);
+        let dati: Vec<InputAddestramento> = (0..25)
+            .map(|i| {
+                let input = vec![(i % 5) as f64 / 2.0 - 1.0, (i / 5) as f64 / 2.0 - 1.0];
+                let classe = classe_di(&insegnante.elabora(input.clone()));
+                InputAddestramento::da_etichetta(input, classe, 3).unwrap()
+            })
+            .collect();
+        let softmax = |logits: Vec<f64>| Softmax.attiva_vettore(&DVector::from_vec(logits));
+        let distanza = |allieva: &ReteNeurale| -> f64 {
+            dati.iter()
+                .map(|set| (softmax(allieva.elabora(set.input.clone())) - softmax(insegnante.elabora(set.input.clone()))).norm())
+                .sum()
+        };
+
+        let iniziale = distanza(&allieva);
+        let prima = allieva.addestra_distillazione(&insegnante, &dati, 2.0, 0.0);
+        let mut ultima = prima;
+        for _ in 0..300 {
+            ultima = allieva.addestra_distillazione(&insegnante, &dati, 2.0, 0.0);
+        }
+        assert!(ultima < prima * 0.5, "perdita di distillazione da {} a {}", prima, ultima);
+        assert!(distanza(&allieva) < iniziale * 0.5, "distanza dall'insegnante da {} a {}", iniziale, distanza(&allieva));
+    }
+
+    #[test]
+    #[should_panic(expected = "dimensione dell'input diversa dai neuroni di input")]
+    fn elabora_con_contesto_rifiuta_input_di_dimensione_errata() {
+        let rete = rete_xor(2);
+        let mut contesto = ContestoInferenza::per_rete(&rete);
+        rete.elabora_con_contesto(&mut contesto, &[1.0, 0.0, 1.0]);
+    }
+
+    /// Benchmark informale: `cargo test --release -- --ignored --nocapture benchmark_elabora_con_contesto`.
+    #[test]
+    #[ignore]
+    fn benchmark_elabora_con_contesto() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(32, Arc::new(Nessuna)),
+            strato(64, Arc::new(Tanh)),
+            strato(64, Arc::new(Tanh)),
+            strato(4, Arc::new(Sigmoide)),
+        ], 0.1, 19);
+        let input = vec![0.5; 32];
+        let ripetizioni = 100_000;
+        let mut contesto = ContestoInferenza::per_rete(&rete);
+        // Nessuna allocazione dopo la creazione del contesto: i buffer restano gli stessi.
+        let buffer: Vec<*const f64> = contesto.attivazioni.iter().map(|attivazione| attivazione.as_ptr()).collect();
+
+        let inizio = Instant::now();
+        let mut somma = 0.0;
+        for _ in 0..ripetizioni {
+            somma += rete.elabora(input.clone())[0];
+        }
+        let tempo_elabora = inizio.elapsed();
+        let inizio = Instant::now();
+        let mut somma_contesto = 0.0;
+        for _ in 0..ripetizioni {
+            somma_contesto += rete.elabora_con_contesto(&mut contesto, &input)[0];
+        }
+        let tempo_contesto = inizio.elapsed();
+
+        assert!(contesto.attivazioni.iter().map(|attivazione| attivazione.as_ptr()).eq(buffer.into_iter()));
+        assert!((somma - somma_contesto).abs() < 1e-6 * ripetizioni as f64);
+        println!("elabora: {:?}, elabora_con_contesto: {:?}", tempo_elabora, tempo_contesto);
+    }
+
+    #[test]
+    fn sostituisci_attivazione_cambia_solo_la_sigla_indicata() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(Sigmoide)),
+            strato(3, Arc::new(ReLU)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.1, 24);
+        let sigla_sigmoide = Sigmoide.sigla().to_string();
+        let variante = rete.sostituisci_attivazione(&sigla_sigmoide, Arc::new(Tanh));
+        let sigle = variante.lista_funzioni_attivazioni();
+        assert_eq!(sigle[0], Nessuna.sigla());
+        assert_eq!(sigle[1], Tanh.sigla());
+        assert_eq!(sigle[2], ReLU.sigla());
+        assert_eq!(sigle[3], Tanh.sigla());
+        assert_eq!(variante.matrici_pesi(), rete.matrici_pesi());
+        assert_eq!(variante.matrici_bias(), rete.matrici_bias());
+        assert_eq!(rete.lista_funzioni_attivazioni()[1], sigla_sigmoide);
+        let identica = rete.sostituisci_attivazione("assente", Arc::new(Tanh));
+        assert_eq!(identica.lista_funzioni_attivazioni(), rete.lista_funzioni_attivazioni());
+    }
+
+    #[test]
+    fn classifica_con_rifiuto_si_astiene_se_incerta() {
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.1, InizializzazionePesi::Xavier);
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(1, 2, &[10.0, 0.0])).unwrap();
+        assert_eq!(rete.classifica_con_rifiuto(vec![1.0, 0.0], 0.9), Some(1));
+        assert_eq!(rete.classifica_con_rifiuto(vec![-1.0, 0.0], 0.9), Some(0));
+        assert_eq!(rete.classifica_con_rifiuto(vec![0.01, 0.0], 0.9), None);
+
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(3, Arc::new(Softmax)),
+        ], 0.1, InizializzazionePesi::Xavier);
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(3, 2, &[0.0, 0.0, 8.0, 0.0, 0.0, 8.0])).unwrap();
+        assert_eq!(rete.classifica_con_rifiuto(vec![1.0, 0.0], 0.9), Some(1));
+        assert_eq!(rete.classifica_con_rifiuto(vec![0.0, 1.0], 0.9), Some(2));
+        assert_eq!(rete.classifica_con_rifiuto(vec![0.0, 0.0], 0.9), None);
+    }
+
+    #[test]
+    fn round_trip_formato_diff_e_modifica_localizzata() {
+        let mut rete = rete_xor(25);
+        let percorso = file_temporaneo("rete.diff");
+        rete.salva_diff(&percorso).unwrap();
+        let letta = ReteNeurale::da_diff(&percorso).unwrap();
+        assert!(letta.pesi_uguali(&rete, 0.0));
+        assert_eq!(letta.tasso_apprendimento(), rete.tasso_apprendimento());
+        let prima = std::fs::read_to_string(&percorso).unwrap();
+
+        let mut pesi = rete.matrici_pesi()[1].clone();
+        pesi[(0, 2)] += 0.125;
+        rete.imposta_pesi_strato(1, pesi).unwrap();
+        rete.salva_diff(&percorso).unwrap();
+        let dopo = std::fs::read_to_string(&percorso).unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        let diverse: Vec<(&str, &str)> = prima.lines().zip(dopo.lines()).filter(|(a, b)| a != b).collect();
+        assert_eq!(prima.lines().count(), dopo.lines().count());
+        assert_eq!(diverse.len(), 1);
+        assert!(diverse[0].1.starts_with("1 0 2 "));
+    }
+
+    #[test]
+    fn autoencoder_impara_l_identita() {
+        // Campioni su un piano di R⁴: due neuroni nascosti bastano a ricostruirli.
+        let campioni: Vec<Vec<f64>> = (0..16)
+            .map(|i| {
+                let (a, b) = ((i % 4) as f64 / 6.0 - 0.25, (i / 4) as f64 / 6.0 - 0.25);
+                vec![a, b, a + b, a - b]
+            })
+            .collect();
+        let mut autoencoder = ReteNeurale::nuova_con_seme(vec![
+            strato(4, Arc::new(Nessuna)),
+            strato(2, Arc::new(Tanh)),
+            strato(4, Arc::new(Lineare)),
+        ], 0.05, 26);
+        let errore = |rete: &ReteNeurale| -> f64 {
+            campioni.iter()
+                .map(|x| rete.elabora(x.clone()).iter().zip(x).map(|(y, x)| (y - x).powi(2)).sum::<f64>())
+                .sum::<f64>() / campioni.len() as f64
+        };
+        let iniziale = errore(&autoencoder);
+        autoencoder.addestra_autoencoder(&campioni, 1500).unwrap();
+        let finale = errore(&autoencoder);
+        assert!(finale < 1e-3 && finale < iniziale * 0.01, "errore di ricostruzione da {} a {}", iniziale, finale);
+        assert!(autoencoder.addestra_autoencoder(&[vec![0.0; 3]], 1).is_err());
+    }
+
+    #[test]
+    fn embedding_una_riga_per_esempio_e_una_colonna_per_neurone() {
+        let rete = rete_xor(27);
+        let input: Vec<Vec<f64>> = dati_xor().into_iter().map(|set| set.input).collect();
+        for (strato, neuroni) in [(0, 2), (1, 4), (2, 1)] {
+            let matrice = rete.embedding(&input, strato).unwrap();
+            assert_eq!(matrice.shape(), (4, neuroni));
+        }
+        let nascosto = rete.embedding(&input, 1).unwrap();
+        for (r, x) in input.iter().enumerate() {
+            let attese = &rete.elabora_con_attivazioni(x.clone())[1];
+            for (c, atteso) in attese.iter().enumerate() {
+                assert!((nascosto[(r, c)] - atteso).abs() < 1e-12);
+            }
+        }
+        assert!(rete.embedding(&input, 3).is_err());
+        assert!(rete.embedding(&[vec![1.0]], 1).is_err());
+    }
+
+    #[test]
+    fn huber_piu_robusta_dell_errore_quadratico_agli_outlier() {
+        // y = 2x su 10 punti, più un outlier a y = 20
+        let mut dati: Vec<InputAddestramento> = (0..10)
+            .map(|i| InputAddestramento { input: vec![i as f64 / 9.0], output: vec![2.0 * i as f64 / 9.0] })
+            .collect();
+        dati.push(InputAddestramento { input: vec![0.5], output: vec![20.0] });
+        let strati = || vec![strato(1, Arc::new(Nessuna)), strato(1, Arc::new(Lineare))];
+        let errore_retta = |rete: &ReteNeurale| -> f64 {
+            [0.0, 0.5, 1.0].iter().map(|&x| (rete.elabora(vec![x])[0] - 2.0 * x).abs()).sum()
+        };
+
+        let mut quadratico = ReteNeurale::nuova_con_seme(strati(), 0.02, 28);
+        let mut huber = ReteNeurale::nuova_con_seme(strati(), 0.02, 28);
+        huber.imposta_perdita(Arc::new(Huber { delta: 0.5 }));
+        quadratico.addestra_epoche(&dati, 2000, OpzioniAddestramento::default()).unwrap();
+        huber.addestra_epoche(&dati, 2000, OpzioniAddestramento::default()).unwrap();
+        let (errore_quadratico, errore_huber) = (errore_retta(&quadratico), errore_retta(&huber));
+        assert!(errore_huber < errore_quadratico * 0.5, "Huber {} contro MSE {}", errore_huber, errore_quadratico);
+    }
+
+    #[test]
+    fn rollback_stabilizza_un_tasso_troppo_alto() {
+        let dati: Vec<InputAddestramento> = (0..10)
+            .map(|i| InputAddestramento { input: vec![i as f64 / 3.0], output: vec![1.0 - i as f64 / 3.0] })
+            .collect();
+        let strati = || vec![strato(1, Arc::new(Nessuna)), strato(1, Arc::new(Lineare))];
+        let mut senza = ReteNeurale::nuova_con_seme(strati(), 2.0, 29);
+        let perdite_senza = senza.addestra_epoche(&dati, 30, OpzioniAddestramento::default()).unwrap();
+        assert!(!perdite_senza.last().unwrap().is_finite() || perdite_senza.last().unwrap() > &perdite_senza[0]);
+
+        let mut con = ReteNeurale::nuova_con_seme(strati(), 2.0, 29);
+        let iniziale = con.perdita_media(&dati);
+        let opzioni = OpzioniAddestramento { rollback: Some(Rollback::default()), ..Default::default() };
+        let perdite = con.addestra_epoche(&dati, 30, opzioni).unwrap();
+        assert!(perdite.iter().all(|perdita| perdita.is_finite()));
+        assert!(perdite.windows(2).all(|coppia| coppia[1] <= coppia[0]));
+        assert!(*perdite.last().unwrap() < iniziale * 0.1, "perdita da {} a {:?}", iniziale, perdite.last());
+        assert!(con.tasso_apprendimento() < 2.0);
+    }
+
+    #[test]
+    fn rollback_riduce_il_tasso_anche_con_uno_scheduler() {
+        let dati: Vec<InputAddestramento> = (0..10)
+            .map(|i| InputAddestramento { input: vec![i as f64 / 3.0], output: vec![1.0 - i as f64 / 3.0] })
+            .collect();
+        let mut rete = ReteNeurale::nuova_con_seme(vec![strato(1, Arc::new(Nessuna)), strato(1, Arc::new(Lineare))], 0.1, 29);
+        let iniziale = rete.perdita_media(&dati);
+        let opzioni = OpzioniAddestramento {
+            rollback: Some(Rollback { riduzione: 0.25, ..Default::default() }),
+            scheduler: Some(Box::new(Costante { tasso: 2.0 })),
+            ..Default::default()
+        };
+        let perdite = rete.addestra_epoche(&dati, 30, opzioni).unwrap();
+        assert!(perdite.windows(2).all(|coppia| coppia[1] <= coppia[0]));
+        assert!(*perdite.last().unwrap() < iniziale * 0.1, "perdita da {} a {:?}", iniziale, perdite.last());
+        // il tasso resta quello dello scheduler ridotto dai rollback, che tra un'epoca e l'altra non si perdono
+        let riduzioni = (2.0 / rete.tasso_apprendimento()).log(4.0);
+        assert!(riduzioni >= 1.0 && (riduzioni - riduzioni.round()).abs() < 1e-9, "tasso {}", rete.tasso_apprendimento());
+    }
+
+    #[test]
+    fn rollback_ripristina_lo_stato_di_adam() {
+        let dati = dati_diagonale();
+        let mut rete = rete_xor(30);
+        rete.imposta_ottimizzatore(Ottimizzatore::adam()).unwrap();
+        rete.addestra_epoche(&dati, 3, OpzioniAddestramento::default()).unwrap();
+        let prima = rete.clone();
+
+        // un'epoca con un tasso enorme peggiora la perdita e viene annullata
+        let opzioni = OpzioniAddestramento {
+            rollback: Some(Rollback::default()),
+            scheduler: Some(Box::new(Costante { tasso: 1e3 })),
+            ..Default::default()
+        };
+        assert_eq!(rete.addestra_epoche(&dati, 1, opzioni).unwrap(), vec![prima.perdita_media(&dati)]);
+        assert!(rete.pesi_uguali(&prima, 0.0));
+        assert_eq!(rete.passi_ottimizzatore(), prima.passi_ottimizzatore());
+
+        // anche i momenti di Adam sono quelli di prima: riprendendo, le due reti restano identiche
+        let mut prima = prima;
+        for rete in [&mut rete, &mut prima] {
+            let opzioni = OpzioniAddestramento { scheduler: Some(Box::new(Costante { tasso: 0.01 })), ..Default::default() };
+            rete.addestra_epoche(&dati, 1, opzioni).unwrap();
+        }
+        assert!(rete.pesi_uguali(&prima, 0.0));
+    }
+
+    /// Tipo di feature dell'applicazione convertito in input dalla rete.
+    struct Misura {
+        temperatura: f64,
+        umidita: f64,
+    }
+
+    impl ConvertibileInInput for Misura {
+        fn in_input(&self) -> Vec<f64> {
+            vec![self.temperatura / 40.0, self.umidita]
+        }
+    }
+
+    #[test]
+    fn elabora_tramite_un_tipo_personalizzato() {
+        let rete = rete_xor(30);
+        let misura = Misura { temperatura: 20.0, umidita: 0.4 };
+        assert_eq!(rete.elabora_tramite(&misura), rete.elabora(vec![0.5, 0.4]));
+        assert_eq!(rete.elabora_tramite(&[0.5, 0.4]), rete.elabora(vec![0.5, 0.4]));
+    }
+
+    #[test]
+    fn funzione_affine_appresa_grazie_al_bias() {
+        // y = 0.5 x + 3: senza bias un neurone lineare passerebbe per l'origine.
+        let dati: Vec<InputAddestramento> = (0..11)
+            .map(|i| InputAddestramento { input: vec![i as f64 / 10.0], output: vec![0.5 * i as f64 / 10.0 + 3.0] })
+            .collect();
+        let mut rete = ReteNeurale::nuova_con_seme(vec![strato(1, Arc::new(Nessuna)), strato(1, Arc::new(Lineare))], 0.1, 31);
+        rete.addestra_epoche(&dati, 2000, OpzioniAddestramento::default()).unwrap();
+        assert!((rete.matrici_bias()[0][0] - 3.0).abs() < 1e-3);
+        assert!((rete.matrici_pesi()[0][(0, 0)] - 0.5).abs() < 1e-3);
+        assert!((rete.elabora(vec![0.0])[0] - 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn xor_converge_con_batch_da_uno_e_da_quattro() {
+        let dati = dati_xor();
+        let mut online = rete_xor(32);
+        let mut batch = rete_xor(32);
+        let iniziale = online.perdita_media(&dati);
+        for _ in 0..5000 {
+            online.addestra_per_batch(&dati, 1);
+        }
+        // Con il batch intero si fa un solo aggiornamento per epoca, con il tasso riscalato linearmente.
+        batch.adatta_tasso_a_batch(4);
+        for _ in 0..5000 {
+            batch.addestra_per_batch(&dati, 4);
+        }
+        for rete in [&online, &batch] {
+            assert!(rete.perdita_media(&dati) < iniziale * 0.1, "perdita da {} a {}", iniziale, rete.perdita_media(&dati));
+            assert_eq!(rete.accuratezza(&dati, 0.5), 1.0);
+        }
+    }
+
+    #[test]
+    fn softmax_somma_a_uno_e_stabile_con_input_grandi() {
+        let p = Softmax.attiva_vettore(&DVector::from_vec(vec![0.5, -1.0, 2.0, 0.0]));
+        assert!((p.sum() - 1.0).abs() < 1e-12);
+        assert!(p.iter().all(|&p| p > 0.0 && p < 1.0));
+        assert_eq!(classe_di(p.as_slice()), 2);
+
+        let p = Softmax.attiva_vettore(&DVector::from_vec(vec![1000.0, 1001.0]));
+        assert!(p.iter().all(|p| p.is_finite()));
+        let e = std::f64::consts::E;
+        assert!((p[0] - 1.0 / (1.0 + e)).abs() < 1e-12);
+        assert!((p[1] - e / (1.0 + e)).abs() < 1e-12);
+
+        // Sul valore isolato: softmax di un vettore di un elemento, costante.
+        assert_eq!(Softmax.attiva_vettore(&DVector::from_vec(vec![3.0]))[0], Softmax.attiva(3.0));
+        assert_eq!(Softmax.derivata(3.0), 0.0);
+    }
+
+    /// Gradiente della perdita rispetto all'output per differenze finite centrate.
+    fn gradiente_numerico(perdita: &dyn FunzionePerdita, previsto: &DVector<f64>, target: &DVector<f64>) -> DVector<f64> {
+        let h = 1e-6;
+        DVector::from_fn(previsto.len(), |i, _| {
+            let (mut piu, mut meno) = (previsto.clone(), previsto.clone());
+            piu[i] += h;
+            meno[i] -= h;
+            (perdita.calcola(&piu, target) - perdita.calcola(&meno, target)) / (2.0 * h)
+        })
+    }
+
+    #[test]
+    fn gradiente_delle_perdite_uguale_a_quello_numerico() {
+        let casi: [(&dyn FunzionePerdita, Vec<f64>, Vec<f64>); 6] = [
+            (&ErroreQuadraticoMedio, vec![0.3, -1.2, 2.0], vec![0.0, 1.0, 1.5]),
+            (&EntropiaIncrociata, vec![0.2, 0.5, 0.3], vec![0.0, 1.0, 0.0]),
+            (&EntropiaIncrociata, vec![0.7], vec![1.0]),
+            (&EntropiaIncrociata, vec![0.7], vec![0.0]),
+            (&NLLGaussiana, vec![0.4, -0.3, 0.2, -0.5], vec![1.0, 0.1]),
+            (&Huber { delta: 0.5 }, vec![0.1, 2.0, -3.0], vec![0.0, 0.0, 0.0]),
+        ];
+        for (perdita, previsto, target) in casi {
+            let (previsto, target) = (DVector::from_vec(previsto), DVector::from_vec(target));
+            let analitico = perdita.gradiente(&previsto, &target);
+            let numerico = gradiente_numerico(perdita, &previsto, &target);
+            assert!((&analitico - &numerico).norm() < 1e-6, "{}: {} contro {}", perdita.nome(), analitico, numerico);
+        }
+    }
+
+    #[test]
+    fn perdita_di_validazione_bassa_dopo_l_addestramento() {
+        let dati = dati_xor();
+        let nuova = rete_xor(33);
+        let mut addestrata = rete_xor(33);
+        addestrata.addestra_epoche(&dati, 5000, OpzioniAddestramento::default()).unwrap();
+        assert!(addestrata.perdita_media(&dati) < 0.01);
+        assert!(nuova.perdita_media(&dati) > 5.0 * addestrata.perdita_media(&dati));
+        assert_eq!(addestrata.perdita_media(&[]), 0.0);
+    }
+
+    #[test]
+    fn deviazione_standard_dei_pesi_per_inizializzazione() {
+        // fan_in 200: Xavier ~ 1/sqrt(200), He ~ sqrt(2/200), uniforme in [-1, 1) ~ 1/sqrt(3)
+        let deviazione = |inizializzazione| {
+            let rete = ReteNeurale::nuova_con_inizializzazione(vec![
+                strato(200, Arc::new(Nessuna)),
+                strato(100, Arc::new(ReLU)),
+            ], 0.1, inizializzazione);
+            let pesi = &rete.matrici_pesi()[0];
+            let media = pesi.mean();
+            (pesi.iter().map(|p| (p - media).powi(2)).sum::<f64>() / pesi.len() as f64).sqrt()
+        };
+        for (inizializzazione, attesa) in [
+            (InizializzazionePesi::Xavier, (1.0f64 / 200.0).sqrt()),
+            (InizializzazionePesi::He, (2.0f64 / 200.0).sqrt()),
+            (InizializzazionePesi::Uniforme, (1.0f64 / 3.0).sqrt()),
+        ] {
+            let misurata = deviazione(inizializzazione);
+            assert!((misurata - attesa).abs() < 0.05 * attesa, "{:?}: {} invece di {}", inizializzazione, misurata, attesa);
+        }
+    }
+
+    #[test]
+    fn stesso_seme_stessi_pesi_iniziali() {
+        assert_eq!(rete_xor(34).pesi_connessioni(), rete_xor(34).pesi_connessioni());
+        assert_eq!(rete_xor(34).matrici_bias(), rete_xor(34).matrici_bias());
+        assert_ne!(rete_xor(34).pesi_connessioni(), rete_xor(35).pesi_connessioni());
+    }
+
+    /// Epoche necessarie perché la rete porti la perdita sullo XOR sotto la soglia (al più `massimo`).
+    fn epoche_per_soglia(rete: &mut ReteNeurale, soglia: f64, massimo: usize) -> usize {
+        let dati = dati_xor();
+        let opzioni = OpzioniAddestramento { progresso: Some(Box::new(move |_, perdita| perdita > soglia)), ..Default::default() };
+        rete.addestra_epoche(&dati, massimo, opzioni).unwrap().len()
+    }
+
+    #[test]
+    fn momentum_converge_prima_della_discesa_semplice() {
+        let mut semplice = rete_xor(36);
+        let mut con_momentum = rete_xor(36);
+        semplice.imposta_ottimizzatore(Ottimizzatore::Momentum { beta: 0.0 }).unwrap();
+        con_momentum.imposta_ottimizzatore(Ottimizzatore::Momentum { beta: 0.9 }).unwrap();
+        let epoche_semplice = epoche_per_soglia(&mut semplice, 0.02, 20000);
+        let epoche_momentum = epoche_per_soglia(&mut con_momentum, 0.02, 20000);
+        assert!(epoche_momentum * 2 < epoche_semplice, "momentum {} epoche, senza {}", epoche_momentum, epoche_semplice);
+    }
+
+    #[test]
+    fn adam_converge_molto_prima_della_discesa_semplice() {
+        let mut sgd = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.05, 37);
+        let mut adam = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.05, 37);
+        adam.imposta_ottimizzatore(Ottimizzatore::adam()).unwrap();
+        let epoche_sgd = epoche_per_soglia(&mut sgd, 0.02, 20000);
+        let epoche_adam = epoche_per_soglia(&mut adam, 0.02, 20000);
+        assert!(epoche_adam * 5 < epoche_sgd, "Adam {} epoche, SGD {}", epoche_adam, epoche_sgd);
+    }
+
+    #[test]
+    fn round_trip_json_di_una_rete_a_piu_strati() {
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(6, Arc::new(LeakyReLU { alpha: 0.05 })),
+            strato(5, Arc::new(Tanh)),
+            strato(4, Arc::new(ReLU)),
+            strato(2, Arc::new(Softmax)),
+        ], 0.01, 38);
+        rete.imposta_perdita(Arc::new(EntropiaIncrociata));
+        rete.imposta_ottimizzatore(Ottimizzatore::Momentum { beta: 0.8 }).unwrap();
+        let percorso = file_temporaneo("rete.json");
+        rete.salva_json(&percorso).unwrap();
+        let letta = ReteNeurale::carica_json(&percorso).unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        assert!(letta.pesi_uguali(&rete, 0.0));
+        assert_eq!(letta.strati(), vec![3, 6, 5, 4, 2]);
+        assert_eq!(letta.tasso_apprendimento(), 0.01);
+        assert_eq!(letta.perdita().sigla(), EntropiaIncrociata.sigla());
+        assert_eq!(letta.ottimizzatore(), Ottimizzatore::Momentum { beta: 0.8 });
+        let input = vec![0.3, -0.7, 1.1];
+        assert_eq!(letta.elabora(input.clone()), rete.elabora(input));
+    }
+
+    #[test]
+    fn carica_json_rifiuta_un_documento_non_valido() {
+        let percorso = file_temporaneo("non_valido.json");
+        std::fs::write(&percorso, "{ \"dimensioni_strati\": [2, 1").unwrap();
+        let errore = ReteNeurale::carica_json(&percorso).err().unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        assert_eq!(errore.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn round_trip_binario_e_file_troncati() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(5, Arc::new(LeakyReLU { alpha: 0.1 })),
+            strato(2, Arc::new(Sigmoide)),
+        ], 0.2, 39);
+        let byte = rete.a_byte();
+        let letta = ReteNeurale::da_byte(&byte).unwrap();
+        assert!(letta.pesi_uguali(&rete, 0.0));
+        assert_eq!(letta.tasso_apprendimento(), rete.tasso_apprendimento());
+
+        let percorso = file_temporaneo("rete.bin");
+        rete.salva_bin(&percorso).unwrap();
+        assert!(ReteNeurale::carica_bin(&percorso).unwrap().pesi_uguali(&rete, 0.0));
+        // Ogni troncamento, anche a metà di un valore, è un errore e non un panic.
+        for lunghezza in 0..byte.len() {
+            std::fs::write(&percorso, &byte[..lunghezza]).unwrap();
+            assert!(ReteNeurale::carica_bin(&percorso).is_err(), "file di {} byte accettato", lunghezza);
+        }
+        std::fs::remove_file(&percorso).unwrap();
+        assert!(ReteNeurale::da_byte(&[byte.as_slice(), &[0]].concat()).is_err());
+    }
+
+    /// Salva la rete dello XOR, applica `modifica` al testo del file e lo ricarica con `carica`.
+    fn carica_modificato(nome: &str, modifica: impl Fn(String) -> String) -> Result<ReteNeurale, ErroreRete> {
+        let percorso = file_temporaneo(nome);
+        rete_xor(40).salva_pesi_txt(&percorso).unwrap();
+        std::fs::write(&percorso, modifica(std::fs::read_to_string(&percorso).unwrap())).unwrap();
+        let risultato = ReteNeurale::carica(&percorso);
+        std::fs::remove_file(&percorso).unwrap();
+        risultato
+    }
+
+    #[test]
+    fn carica_segnala_righe_corrotte() {
+        // Un separatore di strato subito dopo un altro: strato senza righe di pesi.
+        let errore = carica_modificato("corrotto.txt", |testo| testo.replacen(_FILE_STRATO, &format!("{}\n{}", _FILE_STRATO, _FILE_STRATO), 1));
+        assert!(matches!(errore, Err(ErroreRete::RigaMalformata { .. })), "{:?}", errore.err());
+    }
+
+    #[test]
+    fn carica_segnala_valori_non_numerici() {
+        let errore = carica_modificato("non_numerico.txt", |testo| {
+            let righe: Vec<String> = testo.lines()
+                .map(|riga| if riga.starts_with(_FILE_INFO_APPRENDIMENTO) { format!("{}abc", _FILE_INFO_APPRENDIMENTO) } else { riga.to_string() })
+                .collect();
+            righe.join("\n")
+        });
+        assert!(matches!(errore, Err(ErroreRete::ValoreNonNumerico { ref valore, .. }) if valore == "abc"), "{:?}", errore.err());
+    }
+
+    #[test]
+    fn carica_segnala_sigle_sconosciute() {
+        let errore = carica_modificato("sigla.txt", |testo| testo.replace(Sigmoide.sigla(), "Ignota"));
+        assert!(matches!(errore, Err(ErroreRete::SiglaSconosciuta(ref sigla)) if sigla == "Ignota"), "{:?}", errore.err());
+    }
+
+    #[test]
+    fn carica_un_file_valido() {
+        let rete = carica_modificato("valido.txt", |testo| testo).unwrap();
+        assert!(rete.pesi_uguali(&rete_xor(40), 1e-12));
+        assert!(matches!(ReteNeurale::carica(&file_temporaneo("inesistente.txt")), Err(ErroreRete::Io(_))));
+    }
+
+    #[test]
+    fn elabora_batch_uguale_a_elabora_su_ogni_input() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(7, Arc::new(Tanh)),
+            strato(2, Arc::new(Softmax)),
+        ], 0.1, 41);
+        let input: Vec<Vec<f64>> = (0..9).map(|i| vec![i as f64 / 9.0, 1.0 - i as f64 / 4.0, (i % 3) as f64]).collect();
+        let uscite = rete.elabora_batch(&input);
+        assert_eq!(uscite.len(), input.len());
+        for (uscita, input) in uscite.iter().zip(input.iter()) {
+            let attesa = rete.elabora(input.clone());
+            assert!(uscita.iter().zip(attesa.iter()).all(|(a, b)| (a - b).abs() < 1e-12));
+        }
+        assert!(rete.elabora_batch(&[]).is_empty());
+    }
+
+    /// Benchmark informale: `cargo test --release -- --ignored --nocapture benchmark_elabora_batch`.
+    #[test]
+    #[ignore]
+    fn benchmark_elabora_batch() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(32, Arc::new(Nessuna)),
+            strato(128, Arc::new(Tanh)),
+            strato(128, Arc::new(Tanh)),
+            strato(4, Arc::new(Sigmoide)),
+        ], 0.1, 42);
+        let input: Vec<Vec<f64>> = (0..10_000).map(|i| vec![(i % 100) as f64 / 100.0; 32]).collect();
+        let inizio = Instant::now();
+        let singoli: Vec<Vec<f64>> = input.iter().map(|x| rete.elabora(x.clone())).collect();
+        let tempo_singoli = inizio.elapsed();
+        let inizio = Instant::now();
+        let batch = rete.elabora_batch(&input);
+        let tempo_batch = inizio.elapsed();
+        assert_eq!(singoli.len(), batch.len());
+        println!("elabora: {:?}, elabora_batch: {:?}", tempo_singoli, tempo_batch);
+    }
+
+    #[test]
+    fn numero_parametri_di_una_rete_2_16_1() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(16, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.5, 1);
+        // 2*16 + 16*1 = 48 pesi, 16 + 1 = 17 bias
+        assert_eq!(rete.numero_parametri(), 65);
+        let conteggio: usize = rete.matrici_pesi().iter().map(|pesi| pesi.len()).sum::<usize>()
+            + rete.matrici_bias().iter().map(|bias| bias.len()).sum::<usize>();
+        assert_eq!(conteggio, 65);
+    }
+
+    #[test]
+    fn accuratezza_su_xor_addestrato_e_su_rete_casuale() {
+        let dati = dati_xor();
+        let casuale = rete_xor(7);
+        let corretti = dati.iter()
+            .filter(|set| (casuale.elabora(set.input.clone())[0] >= 0.5) == (set.output[0] >= 0.5))
+            .count();
+        assert_eq!(casuale.accuratezza(&dati, 0.5), corretti as f64 / 4.0);
+        assert_eq!(casuale.accuratezza(&[], 0.5), 0.0);
+
+        let mut rete = rete_xor(7);
+        rete.addestra_epoche(&dati, 5000, OpzioniAddestramento::default()).unwrap();
+        assert_eq!(rete.accuratezza(&dati, 0.5), 1.0);
+    }
+
+    #[test]
+    fn matrice_confusione_conta_le_celle_vera_prevista() {
+        // [1, 0] -> classe 1, [0, 1] -> classe 2, [0, 0] -> uscite uguali, argmax 0
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(3, Arc::new(Softmax)),
+        ], 0.1, InizializzazionePesi::Xavier);
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(3, 2, &[0.0, 0.0, 8.0, 0.0, 0.0, 8.0])).unwrap();
+        let esempio = |input: [f64; 2], classe: usize| InputAddestramento {
+            input: input.to_vec(),
+            output: (0..3).map(|k| if k == classe { 1.0 } else { 0.0 }).collect(),
+        };
+        let dati = vec![
+            esempio([0.0, 0.0], 0),
+            esempio([1.0, 0.0], 0),
+            esempio([1.0, 0.0], 1),
+            esempio([1.0, 0.0], 1),
+            esempio([0.0, 1.0], 1),
+            esempio([0.0, 1.0], 2),
+            esempio([0.0, 0.0], 2),
+        ];
+        assert_eq!(rete.matrice_confusione(&dati, 3), vec![
+            vec![1, 1, 0],
+            vec![0, 2, 1],
+            vec![1, 0, 1],
+        ]);
+        // gli esempi con classe vera o prevista oltre num_classi non vengono contati
+        assert_eq!(rete.matrice_confusione(&dati, 2), vec![vec![1, 1], vec![0, 2]]);
+    }
+
+    #[test]
+    fn scheduler_tasso_alle_epoche() {
+        let costante = Costante { tasso: 0.3 };
+        assert_eq!(costante.tasso(0), 0.3);
+        assert_eq!(costante.tasso(1000), 0.3);
+
+        let esponenziale = DecadimentoEsponenziale { iniziale: 0.1, gamma: 0.5 };
+        assert_eq!(esponenziale.tasso(0), 0.1);
+        assert_eq!(esponenziale.tasso(1), 0.05);
+        assert_eq!(esponenziale.tasso(3), 0.0125);
+
+        let gradini = DecadimentoAGradini { iniziale: 0.8, step: 3, fattore: 0.5 };
+        let tassi: Vec<f64> = (0..8).map(|epoca| gradini.tasso(epoca)).collect();
+        assert_eq!(tassi, vec![0.8, 0.8, 0.8, 0.4, 0.4, 0.4, 0.2, 0.2]);
+        assert_eq!(DecadimentoAGradini { step: 0, ..gradini }.tasso(100), 0.8);
+
+        // l'addestramento usa il tasso dello scheduler per l'epoca corrente: dopo 5 epoche quello dell'epoca 4
+        let mut rete = rete_xor(3);
+        let opzioni = OpzioniAddestramento { scheduler: Some(Box::new(gradini)), ..Default::default() };
+        rete.addestra_epoche(&dati_xor(), 5, opzioni).unwrap();
+        assert_eq!(rete.tasso_apprendimento(), 0.4);
+    }
+
+    #[test]
+    fn mescola_cambia_l_ordine_di_visita_a_ogni_epoca() {
+        let dati = dati_regressione(8);
+        // ordini attesi: lo stesso mescolamento che l'addestramento fa con il generatore della rete
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut ordine: Vec<usize> = (0..dati.len()).collect();
+        let ordini: Vec<Vec<usize>> = (0..4)
+            .map(|_| {
+                ordine.shuffle(&mut rng);
+                ordine.clone()
+            })
+            .collect();
+        assert!(ordini.windows(2).all(|coppia| coppia[0] != coppia[1]));
+
+        let mut attesa = rete_xor(11);
+        for ordine in ordini.iter() {
+            for &i in ordine.iter() {
+                attesa.addestra(dati[i].input.clone(), dati[i].output.clone());
+            }
+        }
+        let mut mescolata = rete_xor(11);
+        let opzioni = OpzioniAddestramento { seme: Some(5), mescola: true, ..Default::default() };
+        mescolata.addestra_epoche(&dati, 4, opzioni).unwrap();
+        assert!(mescolata.pesi_uguali(&attesa, 0.0));
+
+        let mut in_ordine = rete_xor(11);
+        let opzioni = OpzioniAddestramento { seme: Some(5), ..Default::default() };
+        in_ordine.addestra_epoche(&dati, 4, opzioni).unwrap();
+        assert!(!in_ordine.pesi_uguali(&mescolata, 1e-9));
+    }
+
+    #[test]
+    fn early_stopping_si_ferma_prima_dell_overfitting() {
+        // pochi esempi di y = x con rumore alternato: una rete ampia finisce per inseguire il rumore
+        let addestramento: Vec<InputAddestramento> = (0..6)
+            .map(|i| {
+                let x = i as f64 / 5.0 - 0.5;
+                InputAddestramento { input: vec![x], output: vec![x + if i % 2 == 0 { 0.3 } else { -0.3 }] }
+            })
+            .collect();
+        let validazione: Vec<InputAddestramento> = (0..40)
+            .map(|i| {
+                let x = i as f64 / 39.0 - 0.5;
+                InputAddestramento { input: vec![x], output: vec![x] }
+            })
+            .collect();
+        let nuova = || ReteNeurale::nuova_con_seme(vec![
+            strato(1, Arc::new(Nessuna)),
+            strato(32, Arc::new(Tanh)),
+            strato(32, Arc::new(Tanh)),
+            strato(1, Arc::new(Lineare)),
+        ], 0.02, 8);
+        let mut rete = nuova();
+        let (epoca, perdita) = rete.addestra_con_early_stopping(&addestramento, &validazione, 20, 1500);
+        assert!(epoca > 0 && epoca < 200, "epoca migliore {}", epoca);
+        // vengono ripristinati i pesi dell'epoca migliore
+        assert_eq!(rete.perdita_media(&validazione), perdita);
+
+        let mut senza = nuova();
+        for _ in 0..1500 {
+            for set in addestramento.iter() {
+                senza.addestra(set.input.clone(), set.output.clone());
+            }
+        }
+        assert!(senza.perdita_media(&addestramento) < rete.perdita_media(&addestramento));
+        assert!(senza.perdita_media(&validazione) > 3.0 * perdita,
+            "validazione {} con overfitting, {} con early stopping", senza.perdita_media(&validazione), perdita);
+    }
+
+    #[test]
+    fn l1_produce_piu_pesi_quasi_nulli() {
+        // solo il primo dei quattro input conta: L1 spinge a zero i pesi degli altri
+        let dati: Vec<InputAddestramento> = (0..16)
+            .map(|i| {
+                let x: Vec<f64> = (0..4).map(|k| ((i * (k + 3)) % 7) as f64 / 7.0).collect();
+                InputAddestramento { output: vec![0.8 * x[0] + 0.1], input: x }
+            })
+            .collect();
+        let addestrata = |lambda_l1: f64| {
+            let mut rete = ReteNeurale::nuova_con_seme(vec![
+                strato(4, Arc::new(Nessuna)),
+                strato(8, Arc::new(Tanh)),
+                strato(1, Arc::new(Lineare)),
+            ], 0.05, 13);
+            rete.imposta_l1(lambda_l1).unwrap();
+            rete.addestra_epoche(&dati, 300, OpzioniAddestramento::default()).unwrap();
+            rete
+        };
+        let quasi_nulli = |rete: &ReteNeurale| rete.matrici_pesi().iter().flat_map(|pesi| pesi.iter()).filter(|peso| peso.abs() < 1e-3).count();
+        let senza = addestrata(0.0);
+        let con_l1 = addestrata(0.01);
+        assert!(quasi_nulli(&con_l1) > quasi_nulli(&senza) + 10, "{} con L1, {} senza", quasi_nulli(&con_l1), quasi_nulli(&senza));
+        assert!(con_l1.sparsita() > senza.sparsita());
+        assert!(con_l1.perdita_media(&dati) < 0.05);
+    }
+
+    #[test]
+    fn clipping_limita_la_norma_del_gradiente_di_ogni_strato() {
+        let nuova = || ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(6, Arc::new(ReLU)),
+            strato(2, Arc::new(Lineare)),
+        ], 0.1, 21);
+        let (input, target) = (vec![20.0, -15.0, 30.0], vec![100.0, -80.0]);
+        let norma = |pesi: &DMatrix<f64>, bias: &DVector<f64>| (pesi.norm_squared() + bias.norm_squared()).sqrt();
+
+        let gradienti = nuova().gradienti_esempio(input.clone(), target.clone());
+        let limitati = gradienti.limitati(1.0);
+        for k in 0..2 {
+            assert!(norma(&gradienti.pesi[k], &gradienti.bias[k]) > 1.0);
+            assert!(norma(&limitati.pesi[k], &limitati.bias[k]) <= 1.0 + 1e-12);
+        }
+        // sotto la soglia il gradiente resta invariato
+        let larghi = gradienti.limitati(f64::MAX);
+        assert_eq!(larghi.pesi, gradienti.pesi);
+        assert_eq!(larghi.bias, gradienti.bias);
+
+        // con la discesa semplice ogni strato si sposta al più di tasso * massimo
+        let mut rete = nuova();
+        rete.imposta_clip_gradiente(Some(0.5)).unwrap();
+        let iniziale = nuova();
+        rete.addestra(input, target);
+        for k in 0..2 {
+            let spostamento = norma(
+                &(&rete.matrici_pesi()[k] - &iniziale.matrici_pesi()[k]),
+                &(&rete.matrici_bias()[k] - &iniziale.matrici_bias()[k]),
+            );
+            assert!(spostamento > 0.0 && spostamento <= 0.1 * 0.5 + 1e-12, "spostamento {}", spostamento);
+        }
+    }
+
+    #[test]
+    fn tasso_enorme_segnala_la_divergenza() {
+        let nuova = |tasso: f64| ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(8, Arc::new(ReLU)),
+            strato(1, Arc::new(Lineare)),
+        ], tasso, 6);
+        let dati = dati_regressione(12);
+        let addestra = |rete: &mut ReteNeurale| -> Result<(), ErroreRete> {
+            for _ in 0..50 {
+                for set in dati.iter() {
+                    rete.addestra_controllato(set.input.clone(), set.output.clone())?;
+                }
+            }
+            Ok(())
+        };
+
+        let mut rete = nuova(1e6);
+        match addestra(&mut rete) {
+            Err(ErroreRete::Divergenza { strato }) => {
+                assert!(strato < 2);
+                let mut valori = rete.matrici_pesi()[strato].iter().chain(rete.matrici_bias()[strato].iter());
+                assert!(valori.any(|valore| !valore.is_finite()));
+                assert!(ErroreRete::Divergenza { strato }.to_string().contains("divergente"));
+            }
+            altro => panic!("attesa divergenza, ottenuto {:?}", altro),
+        }
+        assert!(addestra(&mut nuova(0.01)).is_ok());
+    }
+
+    /// Derivata numerica centrale di un'attivazione, per confrontarla con quella analitica.
+    fn derivata_numerica(funzione: &dyn FunzioneAttivazione, x: f64) -> f64 {
+        let h = 1e-6;
+        (funzione.attiva(x + h) - funzione.attiva(x - h)) / (2.0 * h)
+    }
+
+    #[test]
+    fn elu_valori_e_derivate_in_zero_positivi_e_negativi() {
+        let elu = ELU { alpha: 1.5 };
+        assert_eq!(elu.attiva(0.0), 0.0);
+        assert_eq!(elu.derivata(0.0), 1.5);
+        assert_eq!(elu.attiva(2.0), 2.0);
+        assert_eq!(elu.derivata(2.0), 1.0);
+        assert!((elu.attiva(-1.0) - 1.5 * ((-1f64).exp() - 1.0)).abs() < 1e-15);
+        assert!((elu.derivata(-1.0) - 1.5 * (-1f64).exp()).abs() < 1e-15);
+        // satura a -alpha per valori molto negativi
+        assert!((elu.attiva(-50.0) + 1.5).abs() < 1e-12);
+        for x in [-2.0, -0.3, 0.7, 3.0] {
+            assert!((elu.derivata(x) - derivata_numerica(&elu, x)).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn gelu_valori_di_riferimento() {
+        // valori dell'approssimazione tanh, entro 2e-4 dalla GELU esatta 0.5 * x * (1 + erf(x / √2))
+        let riferimenti = [
+            (0.0, 0.0),
+            (0.5, 0.345_714_009_825_143_94),
+            (1.0, 0.841_191_990_608_276_8),
+            (-1.0, -0.158_808_009_391_723_24),
+            (2.0, 1.954_597_694_087_775),
+        ];
+        for (x, atteso) in riferimenti {
+            assert!((GELU.attiva(x) - atteso).abs() < 1e-12, "GELU({}) = {}", x, GELU.attiva(x));
+        }
+        assert_eq!(GELU.derivata(0.0), 0.5);
+        for x in [-3.0, -1.0, 0.0, 0.5, 2.0] {
+            assert!((GELU.derivata(x) - derivata_numerica(&GELU, x)).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn mish_valori_derivate_e_stabilita_per_x_molto_negativi() {
+        assert_eq!(Mish.attiva(0.0), 0.0);
+        // mish'(0) = tanh(ln 2) = 0.6
+        assert!((Mish.derivata(0.0) - 0.6).abs() < 1e-15);
+        assert!((Mish.attiva(1.0) - 0.865_098_388_267_310_3).abs() < 1e-12);
+        assert!((Mish.attiva(-1.0) + 0.303_401_461_374_108_95).abs() < 1e-12);
+        for x in [-4.0, -1.0, 0.3, 2.0] {
+            assert!((Mish.derivata(x) - derivata_numerica(&Mish, x)).abs() < 1e-8);
+        }
+        for x in [-40.0, -1000.0, -1e300] {
+            let (valore, derivata) = (Mish.attiva(x), Mish.derivata(x));
+            assert!(valore.is_finite() && valore <= 0.0 && valore > -1e-12, "mish({}) = {}", x, valore);
+            assert!(derivata.is_finite() && derivata.abs() < 1e-12, "mish'({}) = {}", x, derivata);
+        }
+        assert_eq!(Mish.attiva(1000.0), 1000.0);
+        assert_eq!(Mish.derivata(1000.0), 1.0);
+    }
+
+    #[test]
+    fn selu_rami_positivo_e_negativo() {
+        let (lambda, alpha) = (SELU::LAMBDA, SELU::ALPHA);
+        // ramo positivo: lineare con pendenza lambda
+        assert_eq!(SELU.attiva(2.0), lambda * 2.0);
+        assert_eq!(SELU.derivata(2.0), lambda);
+        // ramo negativo (0 compreso): esponenziale saturato a -lambda * alpha
+        assert_eq!(SELU.attiva(0.0), 0.0);
+        assert_eq!(SELU.derivata(0.0), lambda * alpha);
+        assert!((SELU.attiva(-1.0) - lambda * alpha * ((-1f64).exp() - 1.0)).abs() < 1e-15);
+        assert!((SELU.derivata(-1.0) - lambda * alpha * (-1f64).exp()).abs() < 1e-15);
+        assert!((SELU.attiva(-50.0) + lambda * alpha).abs() < 1e-12);
+        for x in [-3.0, -0.5, 0.5, 3.0] {
+            assert!((SELU.derivata(x) - derivata_numerica(&SELU, x)).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn round_trip_txt_con_perdita_e_ottimizzatore_non_di_default() {
+        let mut rete = rete_xor(44);
+        rete.imposta_perdita(Arc::new(Huber { delta: 0.7 }));
+        let adam = Ottimizzatore::Adam { beta1: 0.8, beta2: 0.95, epsilon: 1e-7 };
+        rete.imposta_ottimizzatore(adam).unwrap();
+        let percorso = file_temporaneo("perdita_ottimizzatore.txt");
+        rete.salva_pesi_txt(&percorso).unwrap();
+        let letta = ReteNeurale::carica(&percorso).unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        assert_eq!(letta.perdita().sigla(), "Huber");
+        assert_eq!(letta.perdita().parametro(), 0.7);
+        assert_eq!(letta.ottimizzatore(), adam);
+        assert!(letta.pesi_uguali(&rete, 1e-12));
+    }
+
+    #[test]
+    fn ogni_strato_applica_la_propria_attivazione() {
+        let attivazioni: [Arc<dyn FunzioneAttivazione + Send + Sync>; 5] =
+            [Arc::new(Nessuna), Arc::new(Tanh), Arc::new(ReLU), Arc::new(ELU { alpha: 0.5 }), Arc::new(Sigmoide)];
+        let rete = ReteNeurale::nuova_con_seme(
+            [2, 8, 8, 8, 1].into_iter().zip(attivazioni.iter()).map(|(neuroni, funzione)| strato(neuroni, funzione.clone())).collect(),
+            0.1, 19,
+        );
+        let uscite = rete.elabora_con_attivazioni(vec![0.7, -1.3]);
+        assert_eq!(uscite.len(), 5);
+        for k in 1..uscite.len() {
+            let pre_attivazione = &rete.matrici_pesi()[k - 1] * DVector::from_column_slice(&uscite[k - 1]) + &rete.matrici_bias()[k - 1];
+            let attesa: Vec<f64> = pre_attivazione.iter().map(|&z| attivazioni[k].attiva(z)).collect();
+            assert!(uscite[k].iter().zip(attesa.iter()).all(|(a, b)| (a - b).abs() < 1e-12), "strato {}", k);
+            // con un'altra attivazione lo strato darebbe un risultato diverso
+            let con_tanh: Vec<f64> = pre_attivazione.iter().map(|&z| Tanh.attiva(z)).collect();
+            assert_eq!(k == 1, uscite[k].iter().zip(con_tanh.iter()).all(|(a, b)| (a - b).abs() < 1e-12), "strato {}", k);
+        }
+        assert_eq!(rete.lista_funzioni_attivazioni(), ["Null", "Tanh", "ReLU", "ELU", "Sigmoide"].map(str::to_string));
+    }
+
+    #[test]
+    fn carica_strati_uguali_all_intestazione() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(5, Arc::new(Tanh)),
+            strato(4, Arc::new(ReLU)),
+            strato(2, Arc::new(Sigmoide)),
+        ], 0.1, 23);
+        let percorso = file_temporaneo("intestazione.txt");
+        rete.salva_pesi_txt(&percorso).unwrap();
+        let testo = std::fs::read_to_string(&percorso).unwrap();
+        let letta = ReteNeurale::carica(&percorso).unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        let intestazione: Vec<usize> = testo.lines()
+            .find_map(|linea| linea.strip_prefix(_FILE_INFO_RETE))
+            .unwrap()
+            .split(',')
+            .map(|n| n.trim().parse().unwrap())
+            .collect();
+        assert_eq!(intestazione, vec![3, 5, 4, 2]);
+        assert_eq!(letta.strati(), intestazione);
+    }
+
+    #[test]
+    fn valuta_aggrega_perdita_e_accuratezza() {
+        // uscita = primo input (bias nulli con Xavier), perdita (previsto - target)² / 2
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(1, Arc::new(Lineare)),
+        ], 0.1, InizializzazionePesi::Xavier);
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(1, 2, &[1.0, 0.0])).unwrap();
+        let esempio = |x: f64, target: f64| InputAddestramento { input: vec![x, 5.0], output: vec![target] };
+        let dati = vec![esempio(0.9, 1.0), esempio(0.2, 0.0), esempio(0.6, 0.0), esempio(0.4, 1.0)];
+        let valutazione = rete.valuta(&dati);
+        assert_eq!(valutazione.numero_esempi, 4);
+        assert!((valutazione.perdita_media - (0.005 + 0.02 + 0.18 + 0.18) / 4.0).abs() < 1e-12);
+        assert_eq!(valutazione.accuratezza, 0.5);
+        assert!((valutazione.perdita_media - rete.perdita_media(&dati)).abs() < 1e-15);
+        assert_eq!(valutazione.accuratezza, rete.accuratezza(&dati, 0.5));
+        assert_eq!(valutazione.to_string(), "esempi: 4, perdita media: 0.096250, accuratezza: 50.00%");
+
+        let vuota = rete.valuta(&[]);
+        assert_eq!((vuota.numero_esempi, vuota.perdita_media, vuota.accuratezza), (0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn esporta_csv_rilegge_dimensioni_e_valori() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(5, Arc::new(Tanh)),
+            strato(2, Arc::new(Sigmoide)),
+        ], 0.1, 27);
+        let prefisso = file_temporaneo("csv");
+        rete.esporta_csv(&prefisso).unwrap();
+        for (k, pesi) in rete.matrici_pesi().iter().enumerate() {
+            let percorso = format!("{}_strato{}.csv", prefisso, k);
+            let mut reader = csv::ReaderBuilder::new().has_headers(false).from_path(&percorso).unwrap();
+            let righe: Vec<Vec<f64>> = reader.records()
+                .map(|record| record.unwrap().iter().map(|valore| valore.parse().unwrap()).collect())
+                .collect();
+            std::fs::remove_file(&percorso).unwrap();
+            assert_eq!(righe.len(), pesi.nrows());
+            for (i, riga) in righe.iter().enumerate() {
+                assert_eq!(riga.len(), pesi.ncols());
+                for (j, &valore) in riga.iter().enumerate() {
+                    assert_eq!(valore, pesi[(i, j)]);
+                }
+            }
+        }
+        assert!(!std::path::Path::new(&format!("{}_strato2.csv", prefisso)).exists());
+    }
+
+    #[test]
+    fn matrici_pesi_e_bias_con_le_dimensioni_degli_strati() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(4, Arc::new(Nessuna)),
+            strato(7, Arc::new(ReLU)),
+            strato(3, Arc::new(Tanh)),
+            strato(2, Arc::new(Softmax)),
+        ], 0.1, 29);
+        let dimensioni = rete.strati();
+        assert_eq!(rete.matrici_pesi().len(), dimensioni.len() - 1);
+        assert_eq!(rete.matrici_bias().len(), dimensioni.len() - 1);
+        for (k, coppia) in dimensioni.windows(2).enumerate() {
+            assert_eq!(rete.matrici_pesi()[k].shape(), (coppia[1], coppia[0]));
+            assert_eq!(rete.matrici_bias()[k].len(), coppia[1]);
+        }
+    }
+
+    #[test]
+    fn imposta_pesi_strato_verifica_le_dimensioni() {
+        let mut rete = rete_xor(31);
+        let pesi = DMatrix::from_fn(4, 2, |i, j| (i * 2 + j) as f64 / 10.0);
+        rete.imposta_pesi_strato(0, pesi.clone()).unwrap();
+        assert_eq!(rete.matrici_pesi()[0], pesi);
+
+        let errore = rete.imposta_pesi_strato(1, DMatrix::zeros(4, 1));
+        assert!(matches!(errore, Err(ErroreRete::DimensioniPesi { strato: 1, attese: (1, 4), trovate: (4, 1) })), "{:?}", errore);
+        let errore = rete.imposta_pesi_strato(2, DMatrix::zeros(1, 1));
+        assert!(matches!(errore, Err(ErroreRete::StrutturaIncoerente(_))), "{:?}", errore);
+        // dopo un errore la rete resta invariata
+        assert_eq!(rete.matrici_pesi()[0], pesi);
+        assert_eq!(rete.matrici_pesi()[1], rete_xor(31).matrici_pesi()[1]);
+    }
+
+    #[test]
+    fn strato_congelato_resta_invariato() {
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(Tanh)),
+            strato(4, Arc::new(Tanh)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.5, 33);
+        rete.imposta_ottimizzatore(Ottimizzatore::adam()).unwrap();
+        let (pesi, bias) = (rete.matrici_pesi().to_vec(), rete.matrici_bias().to_vec());
+        rete.congela_strato(1).unwrap();
+        assert!(rete.congela_strato(3).is_err());
+        rete.addestra_epoche(&dati_xor(), 20, OpzioniAddestramento::default()).unwrap();
+        assert_eq!(rete.matrici_pesi()[1], pesi[1]);
+        assert_eq!(rete.matrici_bias()[1], bias[1]);
+        // l'errore attraversa lo strato congelato: anche il primo strato si aggiorna
+        assert_ne!(rete.matrici_pesi()[0], pesi[0]);
+        assert_ne!(rete.matrici_pesi()[2], pesi[2]);
+
+        rete.scongela_strato(1).unwrap();
+        rete.addestra_epoche(&dati_xor(), 1, OpzioniAddestramento::default()).unwrap();
+        assert_ne!(rete.matrici_pesi()[1], pesi[1]);
+    }
+
+    #[test]
+    fn elabora_con_attivazioni_dall_input_all_output() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(5, Arc::new(ReLU)),
+            strato(4, Arc::new(Tanh)),
+            strato(2, Arc::new(Softmax)),
+        ], 0.1, 35);
+        let input = vec![0.3, -0.8, 1.5];
+        let attivazioni = rete.elabora_con_attivazioni(input.clone());
+        assert_eq!(attivazioni.iter().map(Vec::len).collect::<Vec<_>>(), rete.strati());
+        assert_eq!(attivazioni[0], input);
+        assert_eq!(attivazioni[attivazioni.len() - 1], rete.elabora(input));
+    }
+
+    #[test]
+    fn calcola_gradienti_uguale_alla_differenza_finita() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(3, Arc::new(Tanh)),
+            strato(2, Arc::new(Sigmoide)),
+        ], 0.1, 37);
+        let (input, target) = ([0.4, -0.9], [1.0, 0.0]);
+        let esempio = [InputAddestramento { input: input.to_vec(), output: target.to_vec() }];
+        let gradienti = rete.calcola_gradienti(&input, &target);
+        assert_eq!(gradienti.len(), 2);
+        let h = 1e-6;
+        for (k, gradiente) in gradienti.iter().enumerate() {
+            assert_eq!(gradiente.shape(), rete.matrici_pesi()[k].shape());
+            for indice in 0..gradiente.len() {
+                let perturbata = |delta: f64| {
+                    let mut copia = rete.clone();
+                    let mut pesi = copia.matrici_pesi()[k].clone();
+                    pesi[indice] += delta;
+                    copia.imposta_pesi_strato(k, pesi).unwrap();
+                    copia.perdita_media(&esempio)
+                };
+                let numerico = (perturbata(h) - perturbata(-h)) / (2.0 * h);
+                assert!((gradiente[indice] - numerico).abs() < 1e-8, "strato {} indice {}: {} vs {}", k, indice, gradiente[indice], numerico);
+            }
+        }
+    }
+
+    /// Tanh con una derivata sbagliata, per verificare che il controllo numerico se ne accorga.
+    struct TanhDerivataErrata;
+
+    impl FunzioneAttivazione for TanhDerivataErrata {
+        fn attiva(&self, x: f64) -> f64 {
+            x.tanh()
+        }
+        fn derivata(&self, _x: f64) -> f64 {
+            1.0
+        }
+        fn nome(&self) -> &str {
+            "Tanh con derivata errata"
+        }
+        fn sigla(&self) -> &str {
+            "TanhErr"
+        }
+        fn alfa(&self) -> f64 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn verifica_gradiente_su_una_rete_piccola() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(4, Arc::new(Tanh)),
+            strato(2, Arc::new(Sigmoide)),
+        ], 0.1, 39);
+        let (input, target) = ([0.5, -1.2, 0.8], [0.0, 1.0]);
+        assert!(rete.verifica_gradiente(&input, &target, 1e-5) < 1e-6);
+
+        let errata = ReteNeurale::nuova_con_seme(vec![
+            strato(3, Arc::new(Nessuna)),
+            strato(4, Arc::new(TanhDerivataErrata)),
+            strato(2, Arc::new(Sigmoide)),
+        ], 0.1, 39);
+        assert!(errata.verifica_gradiente(&input, &target, 1e-5) > 1e-3);
     }
 
+    #[test]
+    fn r_quadro_vicino_a_uno_su_dati_lineari() {
+        let dati: Vec<InputAddestramento> = (0..20)
+            .map(|i| {
+                let (x0, x1) = ((i % 5) as f64 / 4.0, (i / 5) as f64 / 3.0);
+                InputAddestramento { input: vec![x0, x1], output: vec![0.5 * x0 - 0.3 * x1 + 0.2] }
+            })
+            .collect();
+        let mut rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(1, Arc::new(Lineare)),
+        ], 0.2, 43);
+        let iniziale = rete.r_quadro(&dati);
+        rete.addestra_epoche(&dati, 2000, OpzioniAddestramento::default()).unwrap();
+        let finale = rete.r_quadro(&dati);
+        assert!(finale > 0.999 && finale <= 1.0, "R² da {} a {}", iniziale, finale);
 
-    /// Crea una rete da un file contiene i pesi e le informazioni della rete, da un file txt precedentemente creato.
-    /// 
-    pub fn carica(file_txt: &str) -> Self {
-        let mut rete = Self::nuova_rete_uniforme(vec![0], 0.0, Arc::new(Sigmoide));
-        rete.carica_pesi_txt(file_txt).unwrap();
-        rete
+        // una rete che prevede sempre 0 fa peggio della media: R² negativo
+        let mut nulla = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(1, Arc::new(Lineare)),
+        ], 0.2, InizializzazionePesi::Xavier);
+        nulla.imposta_pesi_strato(0, DMatrix::zeros(1, 2)).unwrap();
+        assert!(nulla.r_quadro(&dati) < 0.0);
+        assert_eq!(nulla.r_quadro(&[]), 0.0);
     }
 
-    /// Propagazione in avanti attraverso la rete.
-    ///
-    /// # Argomenti
-    ///
-    /// * `input` - Vettore di input per la rete neurale.
-    ///
-    /// # Ritorna
-    ///
-    /// Un vettore di vettori contenenti le uscite di ogni strato.
-    fn propagazione_avanti(&self, input: &DVector<f64>) -> Vec<DVector<f64>> {
-        let mut uscite = Vec::with_capacity(self.strati.len() + 1);
-        let mut attivazione_corrente = input.clone();
-        uscite.push(attivazione_corrente.clone());
-        let mut i=1; // la prima funzione di attivazione è nulla (perché non si applica allo strato degli input [i != 0] )
-        for pesi in &self.strati {
-            let input_strato = pesi * attivazione_corrente;
-            attivazione_corrente = input_strato.map( |x| self.funzioni_attivazione[i].attiva(x) );
-            uscite.push(attivazione_corrente.clone());
+    #[test]
+    fn errore_assoluto_medio_calcolato_a_mano() {
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(1, Arc::new(Nessuna)),
+            strato(1, Arc::new(Lineare)),
+        ], 0.1, InizializzazionePesi::Xavier); // bias nulli
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(1, 1, &[2.0])).unwrap();
+        // previsioni 2, 4, 6: errori 0.5, 0 e 1
+        let dati: Vec<InputAddestramento> = [(1.0, 2.5), (2.0, 4.0), (3.0, 5.0)]
+            .iter()
+            .map(|&(x, y)| InputAddestramento { input: vec![x], output: vec![y] })
+            .collect();
+        assert!((rete.errore_assoluto_medio(&dati) - 0.5).abs() < 1e-12);
+        assert_eq!(rete.errore_assoluto_medio(&[]), 0.0);
+    }
 
-            // l'indice dipende dal numero di funzioni di attivazioni presenti 
-            if i < self.funzioni_attivazione.len() - 1 {
-                i += 1;
-            }else if self.funzioni_attivazione.len() > 1 {
-                i = 1; // salta il primo strato nullo di default (strato input)
-            }else {
-                i = 0; // si verifica nel caso in cui e stato assegnato una solo funzione di attivazione (per tugli gli strati)
-            }
-            
-        }
+    #[test]
+    fn r_quadro_con_due_output_e_la_media_dei_singoli() {
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(1, Arc::new(Nessuna)),
+            strato(2, Arc::new(Lineare)),
+        ], 0.1, InizializzazionePesi::Xavier); // bias nulli
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(2, 1, &[1.0, 2.0])).unwrap();
+        // primo output esatto (R² = 1); il secondo prevede 0, 2, 4 contro 0, 2, 5:
+        // SS_res = 1, media 7/3, SS_tot = 114/9, R² = 1 - 9/114
+        let dati: Vec<InputAddestramento> = [(0.0, 0.0), (1.0, 2.0), (2.0, 5.0)]
+            .iter()
+            .map(|&(x, y)| InputAddestramento { input: vec![x], output: vec![x, y] })
+            .collect();
+        let atteso = (1.0 + (1.0 - 9.0 / 114.0)) / 2.0;
+        assert!((rete.r_quadro(&dati) - atteso).abs() < 1e-12, "{}", rete.r_quadro(&dati));
+    }
 
-        uscite
+    #[test]
+    fn clona_solo_pesi_azzera_lo_stato_dell_ottimizzatore() {
+        let mut rete = rete_xor(45);
+        rete.imposta_ottimizzatore(Ottimizzatore::adam()).unwrap();
+        rete.addestra_epoche(&dati_xor(), 5, OpzioniAddestramento::default()).unwrap();
+        assert_eq!(rete.passi_ottimizzatore(), 20);
+        let copia = rete.clona_solo_pesi();
+        assert!(copia.pesi_uguali(&rete, 0.0));
+        assert_eq!(copia.ottimizzatore(), rete.ottimizzatore());
+        assert_eq!(copia.passi_ottimizzatore(), 0);
+        assert_eq!(rete.clone().passi_ottimizzatore(), 20);
     }
 
-    /// Metodo che interroga la Rete Neutale, elabora i dati di input
-    /// e restituisce in output il risultato.
-    /// 
-    /// # Argomenti
-    /// * `input` vettore dei dati in input
-    /// 
-    /// # Ritorna
-    ///  vettore output (risposta)
-    /// # Esempio
-    /// ```
-    ///  // usiamo gli stessi dati di addestramento per confronto ...
-    ///  let dati_addestramento = [
-    ///     InputAddestramento {input:vec![0.0, 0.0], output: vec![0.0]},
-    ///     InputAddestramento {input:vec![0.0, 1.0], output: vec![1.0]},
-    ///     InputAddestramento {input:vec![1.0, 0.0], output: vec![1.0]},
-    ///     InputAddestramento {input:vec![1.0, 1.0], output: vec![0.0]}
-    /// ];
-    /// for set in dati_addestramento.iter() {
-    ///     let uscita = rete.elabora(set.input.clone());
-    ///     println!("Input: {:?}, Previsto: {:?}, Uscita: {:?}", set.input, set.output, uscita);
-    /// }
-    /// ```
-    pub fn elabora(&self, input: Vec<f64>) -> Vec<f64> {
-        let uscite = self.propagazione_avanti(&DVector::from_vec(input));
-        uscite[uscite.len() - 1].data.as_vec().to_vec()
+    #[test]
+    fn stessa_struttura_ignora_i_pesi() {
+        let rete = rete_xor(47);
+        assert!(rete.stessa_struttura(&rete_xor(47)));
+
+        let mut poco_diversa = rete_xor(47);
+        poco_diversa.mappa_pesi(|peso| peso + 1e-9);
+        assert!(rete.stessa_struttura(&poco_diversa));
+        assert!(!rete.pesi_uguali(&poco_diversa, 0.0));
+        assert!(rete.pesi_uguali(&poco_diversa, 1e-8));
+
+        let piu_larga = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(5, Arc::new(Sigmoide)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.5, 47);
+        assert!(!rete.stessa_struttura(&piu_larga));
+        let altra_attivazione = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(Tanh)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.5, 47);
+        assert!(!rete.stessa_struttura(&altra_attivazione));
     }
 
-    /// Retropropagazione per aggiornare i pesi della rete neurale.
-    ///
-    /// # Argomenti
-    ///
-    /// * `uscite` - Le uscite di ogni strato dalla propagazione in avanti.
-    /// * `target` - Il vettore dei valori target.
-    fn _retropropagazione(&mut self, uscite: Vec<DVector<f64>>, target: &DVector<f64>) {
-        let mut j = self.funzioni_attivazione.len()-1;
-        let mut errore = target - &uscite[uscite.len() - 1];
-        let mut delta = errore.component_mul(&uscite[uscite.len() - 1].map(|x| self.funzioni_attivazione[j].derivata(x)));
-            
-        for (i, pesi) in self.strati.iter_mut().enumerate().rev() {
-            
-            // l'indice 'j' dipende dal numero di funzioni di attivazioni presenti 
-            if self.funzioni_attivazione.len() == 1 {
-                j = 0; // caso in cui vie una sola funzione di attivazione per tutti gli strati
-            }else {
-                // caso con multi funzioni di attivazione (eccetto per l'input)
-                if j > 1 { // salta la prima funzione di attivazione associata agli input di default Nulla!!
-                    j -= 1;
-                } else {
-                    j = self.funzioni_attivazione.len() - 1;
-                }
-            }
-            let uscita_precedente = &uscite[i];
-            *pesi += self.tasso_apprendimento * (&delta * uscita_precedente.transpose());
+    #[test]
+    fn salva_in_directory_annidate_mancanti() {
+        let radice = file_temporaneo("annidate");
+        let _ = std::fs::remove_dir_all(&radice);
+        let percorso = format!("{}/modelli/xor/rete.txt", radice);
+        let rete = rete_xor(49);
+        rete.salva_pesi_txt(&percorso).unwrap();
+        let letta = ReteNeurale::carica(&percorso).unwrap();
+        // una directory esistente non è un file valido
+        let errore = rete.salva_pesi_txt(&format!("{}/modelli", radice)).unwrap_err();
+        std::fs::remove_dir_all(&radice).unwrap();
+        assert!(letta.pesi_uguali(&rete, 1e-12));
+        assert_eq!(errore.kind(), ErrorKind::InvalidInput);
+    }
 
-            if i > 0 {
-                errore = pesi.transpose() * &delta;
-                delta = errore.component_mul(&uscite[i].map(|x| self.funzioni_attivazione[j].derivata(x)));
-            }
-        }
+    #[test]
+    fn diciassette_cifre_significative_rileggono_i_bit_esatti() {
+        let mut rete = rete_xor(51);
+        rete.mappa_pesi(|peso| peso / 3.0 + 1e-17);
+        rete.imposta_cifre_significative(Some(17)).unwrap();
+        assert!(rete.imposta_cifre_significative(Some(0)).is_err());
+        let percorso = file_temporaneo("diciassette.txt");
+        rete.salva_pesi_txt(&percorso).unwrap();
+        let testo = std::fs::read_to_string(&percorso).unwrap();
+        let letta = ReteNeurale::carica(&percorso).unwrap();
+        std::fs::remove_file(&percorso).unwrap();
+        assert!(testo.contains('e'));
+        let bit = |rete: &ReteNeurale| rete.matrici_pesi().iter().flat_map(|pesi| pesi.iter())
+            .chain(rete.matrici_bias().iter().flat_map(|bias| bias.iter()))
+            .map(|valore| valore.to_bits())
+            .collect::<Vec<u64>>();
+        assert_eq!(bit(&letta), bit(&rete));
     }
 
-    /// Addestra la rete neurale su un singolo esempio.
-    ///
-    /// # Argomenti
-    ///
-    /// * `input` - Vettore di input per la rete neurale.
-    /// * `target` - Vettore dei valori target per il training.
-    /// 
-    /// # Esempio
-    /// ```
-    ///  let dati_addestramento = [
-    ///     InputAddestramento {input:vec![0.0, 0.0], output: vec![0.0]},
-    ///     InputAddestramento {input:vec![0.0, 1.0], output: vec![1.0]},
-    ///     InputAddestramento {input:vec![1.0, 0.0], output: vec![1.0]},
-    ///     InputAddestramento {input:vec![1.0, 1.0], output: vec![0.0]}
-    /// ];
-    /// for _ in 0..1000000 {
-    ///     for set in dati_addestramento.iter() {
-    ///         rete.addestra(set.input.clone(), set.output.clone());
-    ///     }
-    /// }
-    /// ```
-    /// 
-    pub fn addestra(&mut self, input: Vec<f64>, target: Vec<f64>) {
-        
-        let uscite = self.propagazione_avanti(&DVector::from_vec(input));
-        self._retropropagazione(uscite,&DVector::from_vec(target));
+    /// Rete `[2, 6, 1]` con batch normalization sullo strato nascosto, addestrata per batch
+    /// così che `γ`, `β` e le statistiche mobili non siano quelle iniziali.
+    fn rete_batch_norm() -> ReteNeurale {
+        let mut rete = ReteNeurale::nuova_tipizzata(vec![
+            TipoStrato::Denso(strato(2, Arc::new(Nessuna))),
+            TipoStrato::Denso(strato(6, Arc::new(ReLU))),
+            TipoStrato::BatchNorm,
+            TipoStrato::Denso(strato(1, Arc::new(Sigmoide))),
+        ], 0.1).unwrap();
+        for _ in 0..20 {
+            rete.addestra_per_batch(&dati_xor(), 4);
+        }
+        assert_ne!(rete.batch_norm(0).unwrap(), &BatchNorm::nuova(6));
+        rete
     }
-    /// Salva i pesi della rete neurale in un file di testo.
-    ///
-    /// # Argomenti
-    ///
-    /// * `file_path` - Il percorso del file di testo in cui salvare i pesi.
-    ///
-    /// # Ritorna
-    ///
-    /// Un `Result` che indica se l'operazione ha avuto successo o meno.
-    pub fn salva_pesi_txt(&self, file_path: &str) -> Result<(), Error> {
-        let mut file = File::create(file_path)?;
-        writeln!( file, "{} {}",_FILE_INFO_APPRENDIMENTO, self.tasso_apprendimento )?;
-        
-        let mut nomi_funz_attivazione = String::new();
-        
-        for funzione_attivazione in self.funzioni_attivazione.clone().into_iter()  {
-            if funzione_attivazione.sigla() != "LeakyReLU" {
-                nomi_funz_attivazione += &(funzione_attivazione.sigla().to_string()+ "; ");
-            }else{
-                nomi_funz_attivazione += &(funzione_attivazione.sigla().to_string()+ "_" + funzione_attivazione.alfa().to_string().as_str() +"; ");
-            }
+
+    #[test]
+    fn batch_norm_conservata_da_tutti_i_formati() {
+        let rete = rete_batch_norm();
+        let (txt, diff, json, bin) = (
+            file_temporaneo("bn.txt"), file_temporaneo("bn.diff"), file_temporaneo("bn.json"), file_temporaneo("bn.bin"),
+        );
+        rete.salva_pesi_txt(&txt).unwrap();
+        rete.salva_diff(&diff).unwrap();
+        rete.salva_json(&json).unwrap();
+        rete.salva_bin(&bin).unwrap();
+        let lette = [
+            ReteNeurale::carica(&txt).unwrap(),
+            ReteNeurale::da_diff(&diff).unwrap(),
+            ReteNeurale::carica_json(&json).unwrap(),
+            ReteNeurale::carica_bin(&bin).unwrap(),
+            ReteNeurale::da_hex(&rete.a_hex()).unwrap(),
+        ];
+        for percorso in [&txt, &diff, &json, &bin] {
+            std::fs::remove_file(percorso).unwrap();
         }
-        writeln!( file, "{} {}",_FILE_INFO_ATTIVAZIONE, nomi_funz_attivazione.as_str())?;
-        writeln!( file, "{} {}", 
-            _FILE_INFO_RETE, 
-            format!("{:?}", self.dimensioni_strati ).replace("[", "").replace("]", "")
-        )?;
+        for (formato, letta) in ["txt", "diff", "json", "bin", "hex"].iter().zip(lette.iter()) {
+            assert_eq!(letta.batch_norm(0), rete.batch_norm(0), "formato {}", formato);
+            assert_eq!(letta.batch_norm(1), None, "formato {}", formato);
+            assert!(letta.pesi_uguali(&rete, 0.0), "formato {}", formato);
+            assert_eq!(letta.elabora(vec![1.0, 0.0]), rete.elabora(vec![1.0, 0.0]), "formato {}", formato);
+        }
+    }
 
-        for strato in &self.strati {
-            
-            for riga in strato.row_iter() {
-                let riga_str = riga.iter()
-                    .map(|valore| valore.to_string())
-                    .collect::<Vec<String>>()
-                    .join(" ");
-                writeln!(file, "{}", riga_str)?;
-            }
-            writeln!(file, "{}", _FILE_STRATO )?; // Separatore di strato
+    #[test]
+    fn batch_norm_mal_formata_rifiutata() {
+        let rete = rete_batch_norm();
+        let diff = file_temporaneo("bn_errata.diff");
+        rete.salva_diff(&diff).unwrap();
+        let testo = std::fs::read_to_string(&diff).unwrap();
+        let riga = testo.lines().find(|linea| linea.starts_with(_FILE_BATCH_NORM)).unwrap().to_string();
+        for modificato in [
+            testo.replace(&riga, &riga[..riga.rfind(' ').unwrap()]),
+            testo.replace(&riga, &riga.replacen("[n] 0", "[n] 1", 1)),
+            testo.replace(&riga, &format!("{}\n{}", riga, riga)),
+        ] {
+            std::fs::write(&diff, modificato).unwrap();
+            assert_eq!(ReteNeurale::da_diff(&diff).err().unwrap().kind(), ErrorKind::InvalidData);
         }
+        std::fs::remove_file(&diff).unwrap();
 
-        Ok(())
+        let mut byte = rete.a_byte();
+        // dalla fine: indicatore dello strato 1, i suoi 6 pesi e 1 bias, i 24 parametri della normalizzazione dello strato 0
+        let flag = byte.len() - 1 - (6 + 1) * 8 - 4 * 6 * 8 - 1;
+        assert_eq!(byte[flag], 1);
+        byte[flag] = 2;
+        assert!(ReteNeurale::da_byte(&byte).is_err());
     }
 
-    /**
-     * Restituisce i singoli pesi delle connessioni tragli strati della rete neurale.
-     * I pesi sono organizzati come un vettori tridimensionale di varori in virgola mobile.
-     * Puo essere visto come un vettore delle connessioni tragli strati organizzati come matrici.
-     */
-    pub fn pesi_connessioni(&self) -> Vec<Vec<Vec<f64>>> {
-        let mut strati_rete = vec![];
+    #[test]
+    fn batch_norm_in_addestramento_media_zero_varianza_uno() {
+        let campioni = 8;
+        let pre_attivazioni = DMatrix::from_fn(3, campioni, |i, j| (i as f64 + 1.0) * 10.0 * ((j * (i + 2)) as f64).sin() + 5.0 * i as f64);
+        let mut normalizzazione = BatchNorm::nuova(3);
+        let normalizzate = normalizzazione.normalizza_batch(&pre_attivazioni);
+        for riga in normalizzate.row_iter() {
+            let media = riga.sum() / campioni as f64;
+            let varianza = riga.iter().map(|v| (v - media).powi(2)).sum::<f64>() / campioni as f64;
+            assert!(media.abs() < 1e-12, "media {}", media);
+            assert!((varianza - 1.0).abs() < 1e-4, "varianza {}", varianza);
+        }
+        // le statistiche mobili si spostano verso quelle del batch
+        for (i, riga) in pre_attivazioni.row_iter().enumerate() {
+            let media = riga.sum() / campioni as f64;
+            assert!((normalizzazione.media_mobile()[i] - (1.0 - BatchNorm::MOMENTO) * media).abs() < 1e-12);
+        }
+    }
 
-        for strato in &self.strati {
-            let mut connessioni = vec![];
-            for riga in strato.row_iter() {
-                let riga_connessioni = riga.iter()
-                    .map(|valore| *valore)
-                    .collect::<Vec<f64>>();
-                connessioni.push(riga_connessioni.clone());
-            }
-            strati_rete.push(connessioni);
-           
+    #[test]
+    fn batch_norm_indietro_uguale_alla_derivata_numerica() {
+        // perdita L = Σ G ⊙ y, per cui dL/dy = G
+        let pre_attivazioni = DMatrix::from_row_slice(2, 4, &[0.3, -1.2, 0.8, 2.0, 1.5, 0.1, -0.7, 0.4]);
+        let gradiente_uscite = DMatrix::from_row_slice(2, 4, &[0.5, -0.3, 1.1, 0.2, -0.8, 0.6, 0.1, -0.4]);
+        let mut normalizzazione = BatchNorm::nuova(2);
+        normalizzazione.gamma = DVector::from_vec(vec![1.3, 0.7]);
+        normalizzazione.beta = DVector::from_vec(vec![0.2, -0.1]);
+        let perdita = |batch_norm: &BatchNorm, pre_attivazioni: &DMatrix<f64>| {
+            batch_norm.clone().avanti_batch(pre_attivazioni).0.component_mul(&gradiente_uscite).sum()
+        };
+        let (_, normalizzate, inverse) = normalizzazione.clone().avanti_batch(&pre_attivazioni);
+        let (gradiente_pre_attivazioni, gradiente_gamma, gradiente_beta) =
+            normalizzazione.indietro_batch(&gradiente_uscite, &normalizzate, &inverse);
+
+        let h = 1e-6;
+        for k in 0..pre_attivazioni.len() {
+            let (mut piu, mut meno) = (pre_attivazioni.clone(), pre_attivazioni.clone());
+            piu[k] += h;
+            meno[k] -= h;
+            let numerica = (perdita(&normalizzazione, &piu) - perdita(&normalizzazione, &meno)) / (2.0 * h);
+            assert!((gradiente_pre_attivazioni[k] - numerica).abs() < 1e-6, "z[{}]: {} contro {}", k, gradiente_pre_attivazioni[k], numerica);
         }
+        for j in 0..2 {
+            let (mut piu, mut meno) = (normalizzazione.clone(), normalizzazione.clone());
+            piu.gamma[j] += h;
+            meno.gamma[j] -= h;
+            let numerica = (perdita(&piu, &pre_attivazioni) - perdita(&meno, &pre_attivazioni)) / (2.0 * h);
+            assert!((gradiente_gamma[j] - numerica).abs() < 1e-6, "γ[{}]: {} contro {}", j, gradiente_gamma[j], numerica);
 
-       strati_rete
+            let (mut piu, mut meno) = (normalizzazione.clone(), normalizzazione.clone());
+            piu.beta[j] += h;
+            meno.beta[j] -= h;
+            let numerica = (perdita(&piu, &pre_attivazioni) - perdita(&meno, &pre_attivazioni)) / (2.0 * h);
+            assert!((gradiente_beta[j] - numerica).abs() < 1e-6, "β[{}]: {} contro {}", j, gradiente_beta[j], numerica);
+        }
+    }
+
+    #[test]
+    fn batch_norm_aggiornata_con_l_ottimizzatore_della_rete() {
+        let mut rete = ReteNeurale::nuova_tipizzata(vec![
+            TipoStrato::Denso(strato(2, Arc::new(Nessuna))),
+            TipoStrato::Denso(strato(6, Arc::new(ReLU))),
+            TipoStrato::BatchNorm,
+            TipoStrato::Denso(strato(1, Arc::new(Sigmoide))),
+        ], 0.01).unwrap();
+        rete.imposta_ottimizzatore(Ottimizzatore::adam()).unwrap();
+        rete.addestra_per_batch(&dati_xor(), 4);
+        // il primo passo di Adam sposta ogni parametro con gradiente non nullo di circa il tasso, qualunque sia il gradiente
+        let batch_norm = rete.batch_norm(0).unwrap();
+        for spostamento in (batch_norm.gamma() - DVector::from_element(6, 1.0)).iter().chain(batch_norm.beta().iter()) {
+            assert!(*spostamento == 0.0 || (spostamento.abs() - 0.01).abs() < 1e-4, "spostamento {}", spostamento);
+        }
+        assert!(batch_norm.beta().iter().any(|beta| *beta != 0.0));
+        assert_eq!(rete.memoria_stimata(), (rete.numero_parametri() * 3) * std::mem::size_of::<f64>());
     }
 
+    #[test]
+    fn formato_binario_versione_2_ancora_letto() {
+        let rete = rete_xor(53);
+        let mut byte = rete.a_byte();
+        // la versione 2 non ha gli indicatori di batch normalization: l'ultimo e quello dopo i 4 bias dello strato 0
+        let ultimo = byte.len() - 1;
+        let primo = ultimo - (4 + 1) * 8 - 1;
+        assert_eq!((byte[primo], byte[ultimo]), (0, 0));
+        byte.remove(ultimo);
+        byte.remove(primo);
+        byte[_BIN_INTESTAZIONE.len()] = 2;
+        let letta = ReteNeurale::da_byte(&byte).unwrap();
+        assert!(letta.pesi_uguali(&rete, 0.0));
+        assert_eq!(letta.batch_norm(0), None);
+    }
 
-    /// Metodo che inverte una matrice formata come vettore di vettori
-    fn trasponi<T: Clone>(matrice: Vec<Vec<T>>) -> Vec<Vec<T>> {
-        if matrice.is_empty() {
-            return vec![]; // Se la matrice è vuota, ritorna una matrice vuota.
+    #[test]
+    fn progresso_chiamato_per_ogni_epoca_richiesta() {
+        let chiamate = Arc::new(Mutex::new(Vec::new()));
+        let registrate = chiamate.clone();
+        let opzioni = OpzioniAddestramento {
+            progresso: Some(Box::new(move |epoca, perdita| {
+                registrate.lock().unwrap().push((epoca, perdita));
+                true
+            })),
+            ..Default::default()
+        };
+        let perdite = rete_xor(55).addestra_epoche(&dati_xor(), 12, opzioni).unwrap();
+        let chiamate = chiamate.lock().unwrap();
+        assert_eq!(chiamate.iter().map(|&(epoca, _)| epoca).collect::<Vec<_>>(), (0..12).collect::<Vec<_>>());
+        assert_eq!(chiamate.iter().map(|&(_, perdita)| perdita).collect::<Vec<_>>(), perdite);
+    }
+
+    #[test]
+    fn gradiente_corretto_con_tanh() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(5, Arc::new(Tanh)),
+            strato(3, Arc::new(Tanh)),
+            strato(1, Arc::new(Tanh)),
+        ], 0.1, 57);
+        for (input, target) in [([0.3, -0.7], [0.5]), ([1.5, 2.0], [-0.9])] {
+            assert!(rete.verifica_gradiente(&input, &target, 1e-5) < 1e-6);
         }
-    
-        let _numero_righe = matrice.len();
-        let numero_colonne = matrice[0].len();
-    
-        // Creiamo una nuova matrice trasposta con colonne vuote.
-        let mut matrice_trasposta: Vec<Vec<T>> = vec![vec![]; numero_colonne];
-    
-        // Per ogni riga nella matrice originale
-        for riga in matrice {
-            // Inseriamo ogni elemento nella colonna corrispondente della nuova matrice trasposta.
-            for (i, elemento) in riga.into_iter().enumerate() {
-                matrice_trasposta[i].push(elemento);
-            }
+    }
+
+    #[test]
+    fn verifica_gradiente_con_sigmoide_tanh_e_relu() {
+        let attivazioni: [Arc<dyn FunzioneAttivazione + Send + Sync>; 3] = [Arc::new(Sigmoide), Arc::new(Tanh), Arc::new(ReLU)];
+        for funzione in attivazioni {
+            let rete = ReteNeurale::nuova_con_seme(vec![
+                strato(3, Arc::new(Nessuna)),
+                strato(6, funzione.clone()),
+                strato(4, funzione.clone()),
+                strato(2, Arc::new(Lineare)),
+            ], 0.1, 59);
+            let errore = rete.verifica_gradiente(&[0.8, -0.4, 1.1], &[0.3, -0.2], 1e-5);
+            assert!(errore < 1e-6, "{}: errore {}", funzione.sigla(), errore);
         }
-    
-        matrice_trasposta
     }
 
+    #[test]
+    fn flag_di_interruzione_ferma_dopo_la_prima_epoca() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let da_impostare = flag.clone();
+        let opzioni = OpzioniAddestramento {
+            interruzione: Some(flag.clone()),
+            // simula un altro thread che chiede l'interruzione alla fine della prima epoca
+            progresso: Some(Box::new(move |_, _| {
+                da_impostare.store(true, Ordering::Relaxed);
+                true
+            })),
+            ..Default::default()
+        };
+        let mut rete = rete_xor(61);
+        assert_eq!(rete.addestra_epoche(&dati_xor(), 100, opzioni).unwrap().len(), 1);
 
-    /// Carica i pesi della rete neurale da un file di testo.
-    ///
-    /// # Argomenti
-    ///
-    /// * `file_path` - Il percorso del file di testo da cui caricare i pesi.
-    ///
-    /// # Ritorna
-    ///
-    /// Un `Result` che indica se l'operazione ha avuto successo o meno.
-    pub fn carica_pesi_txt(&mut self, file_path: &str) -> Result<(), Error> {
-        let file = File::open(file_path)?;
-        let reader = BufReader::new(file);
-        let mut strati = Vec::new();
-        let mut attuale_strato:Vec<Vec<f64>> = Vec::new();
-        
-        self.funzioni_attivazione.clear();
-        
-        for line in reader.lines() {
-            let linea = line?;
-            
-            if linea.starts_with(_FILE_INFO_APPRENDIMENTO) {
-                let tasso = linea.replace(_FILE_INFO_APPRENDIMENTO, "")
-                                    .split(" ")
-                                    .collect::<Vec<&str>>().get(1).unwrap()
-                                    .to_string().parse::<f64>().unwrap();
-                if tasso > 0.0 {
-                     self.tasso_apprendimento = tasso;
-                }
-            } else if linea.starts_with(_FILE_INFO_ATTIVAZIONE) {
-                let nomi_funzioni = linea.replace(_FILE_INFO_ATTIVAZIONE, "").trim().to_string();
-                for nome_funzione in nomi_funzioni.split("; ").into_iter() {
-                    let nome_funzione_modificato = nome_funzione.to_string().replace(";", "").replace(" ", "");
-                    let mut _nome_funzione = nome_funzione_modificato.as_str();  
-                    if _nome_funzione.trim() != "" {
-                        let funzione_attivazione: Arc<dyn FunzioneAttivazione + Send + Sync>= if _nome_funzione.starts_with("LeakyReLU_") {
-                            let alfa = _nome_funzione.split("_").collect::<Vec<&str>>()[1].parse::<f64>().unwrap();
-                            Arc::new(LeakyReLU { alpha: alfa })
-                        } else {
-                            match _nome_funzione {
-                                "Sigmoide"  => Arc::new(Sigmoide),
-                                "ReLU"      => Arc::new(ReLU),
-                                "Tanh"      => Arc::new(Tanh),
-                                "Softplus"  => Arc::new(Softplus),
-                                "Swish"     => Arc::new(Swish),
-                                "Lineare"     => Arc::new(Lineare),
-                                _           => Arc::new(Nessuna),
-                            }
-                        };
-                        self.funzioni_attivazione.push(funzione_attivazione);
-                    }
-                }
+        // con il flag già impostato non inizia nessuna epoca
+        let opzioni = OpzioniAddestramento { interruzione: Some(flag), ..Default::default() };
+        let prima = rete.clone();
+        assert!(rete.addestra_epoche(&dati_xor(), 100, opzioni).unwrap().is_empty());
+        assert!(rete.pesi_uguali(&prima, 0.0));
+    }
 
-            } else if linea.starts_with(_FILE_INFO_RETE) {
-                let strati = linea.replace(_FILE_INFO_RETE, "").trim()
-                        .split(", ")
-                        .map( |cifra| cifra.to_string().parse::<usize>().unwrap() )
-                        .collect::<Vec<usize>>();
-                if strati.len() > 0 {
-                    self.dimensioni_strati = strati;    
-                    
-                    let mut info_strati = Vec::new();
-                    let mut i: usize = 0;
-                    for neuroni_strato in  self.dimensioni_strati.clone().into_iter() {
-                        info_strati.push( 
-                            Strato {
-                                neuroni: neuroni_strato,
-                                funzione_attivazione: self.funzioni_attivazione[i].clone()
-                            }
-                        );
-                        if i < self.funzioni_attivazione.len()  {
-                            i += 1;
-                        }else {
-                            i = 0; // nel caso   self.funzioni_attivazione.len() <  self.dimensioni_strati.len()
-                        }
-                    }                                                             
-                    Self::nuova(info_strati, self.tasso_apprendimento );
-                }
-            } else if linea.trim() == _FILE_STRATO {
-                let num_righe = attuale_strato.len();
-                let connessioni =attuale_strato.get(0);
-                let mut num_colonne: usize=0;
-                
-                if let Some(x) = connessioni {
-                    num_colonne = x.len();
-                }else{
-                    return Err(Error::from(ErrorKind::Other));
-                }
-                let dati_strato = DMatrix::from_vec(
-                    num_righe,
-                    num_colonne,
-                    Self::trasponi(attuale_strato).clone().into_iter().flatten().collect(),
-                );
-                strati.push(dati_strato);
-                attuale_strato = Vec::new();
-                
-            } else {
-                let riga: Vec<f64> = linea.split_whitespace()
-                    .map(|valore| valore.parse::<f64>().unwrap())
-                    .collect();
-                attuale_strato.push(riga);
-            }
-        }
+    #[test]
+    fn display_etichetta_ogni_strato_con_la_sua_attivazione() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(3, Arc::new(ReLU)),
+            strato(2, Arc::new(Tanh)),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.1, 63);
+        let testo = format!("{}", rete);
+        let etichette: Vec<&str> = testo.lines().filter(|linea| linea.starts_with("Connessioni livello")).collect();
+        assert_eq!(etichette, vec![
+            format!("Connessioni livello [0] - [1] (attivazione livello [1]: {})", ReLU.nome()),
+            format!("Connessioni livello [1] - [2] (attivazione livello [2]: {})", Tanh.nome()),
+            format!("Connessioni livello [2] - [3] (attivazione livello [3]: {})", Sigmoide.nome()),
+        ]);
+        assert!(testo.contains("Bias livello [3]"));
 
-        self.strati = strati;
-        Ok(())
+        let testo = format!("{}", rete_batch_norm());
+        assert!(testo.contains(&format!("Connessioni livello [0] - [1] (attivazione livello [1]: {} + BatchNorm)", ReLU.nome())));
+        assert!(testo.contains(&format!("Connessioni livello [1] - [2] (attivazione livello [2]: {})\n", Sigmoide.nome())));
     }
 
-    /// Dimensione dei vari strati.
-    pub fn strati (&self) ->  Vec<usize> {
-        self.dimensioni_strati.to_vec()
+    #[test]
+    fn reinizializza_cambia_i_pesi_ma_non_le_dimensioni() {
+        let mut rete = rete_batch_norm();
+        rete.imposta_ottimizzatore(Ottimizzatore::adam()).unwrap();
+        rete.addestra_epoche(&dati_xor(), 1, OpzioniAddestramento::default()).unwrap();
+        let prima = rete.clone();
+        rete.reinizializza(InizializzazionePesi::Xavier);
+        assert_eq!(rete.strati(), prima.strati());
+        assert!(rete.stessa_struttura(&prima));
+        for (nuovi, vecchi) in rete.matrici_pesi().iter().zip(prima.matrici_pesi().iter()) {
+            assert_eq!(nuovi.shape(), vecchi.shape());
+            assert_ne!(nuovi, vecchi);
+        }
+        // con Xavier i bias ripartono da zero
+        assert!(rete.matrici_bias().iter().all(|bias| bias.iter().all(|&valore| valore == 0.0)));
+        assert_eq!(rete.batch_norm(0), Some(&BatchNorm::nuova(6)));
+        assert_eq!(rete.passi_ottimizzatore(), 0);
     }
 
-    /// Nome della funzione di attivazione degli strati
-    /// 
-    /// # Argomenti
-    ///
-    /// * `indice` - è riferito alla posizione nella lista delle funzioni di attivazione associata allo strato,
-    /// di norma corrisponde all'indice dello strato (se la lista è uguale o maggiore del numero degli strati)
-    /// 
-    pub fn funzione_attivazione (&self,indice:usize) ->  &str {
-        self.funzioni_attivazione[indice].nome()
+    #[test]
+    fn round_trip_con_elu_e_leaky_relu() {
+        let rete = ReteNeurale::nuova_con_seme(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(4, Arc::new(ELU { alpha: 0.75 })),
+            strato(3, Arc::new(LeakyReLU { alpha: 0.05 })),
+            strato(1, Arc::new(Sigmoide)),
+        ], 0.1, 65);
+        let (txt, diff) = (file_temporaneo("elu_leaky.txt"), file_temporaneo("elu_leaky.diff"));
+        rete.salva_pesi_txt(&txt).unwrap();
+        rete.salva_diff(&diff).unwrap();
+        let lette = [ReteNeurale::carica(&txt).unwrap(), ReteNeurale::da_diff(&diff).unwrap()];
+        std::fs::remove_file(&txt).unwrap();
+        std::fs::remove_file(&diff).unwrap();
+        for letta in lette.iter() {
+            let parametri: Vec<(String, f64)> = letta.funzioni_attivazione().iter()
+                .map(|funzione| (funzione.sigla().to_string(), funzione.alfa()))
+                .collect();
+            assert_eq!(parametri[1], ("ELU".to_string(), 0.75));
+            assert_eq!(parametri[2], ("LeakyReLU".to_string(), 0.05));
+            assert!(letta.pesi_uguali(&rete, 1e-12));
+            assert_eq!(letta.elabora(vec![-2.0, 0.5]), rete.elabora(vec![-2.0, 0.5]));
+        }
     }
 
-    ///Lista di tutte le funzioni di attivazione per strati, dall'input verso l'output.
-    /// 
-    pub fn lista_funzioni_attivazioni(&self) -> Vec<String> {
-        let mut lista: Vec<String> =  vec![];
-        for funzione_attivazione in self.funzioni_attivazione.clone().into_iter()  {
-            lista.push(funzione_attivazione.sigla().to_string());
+    #[test]
+    fn classifica_su_xor_e_su_tre_output() {
+        let mut rete = rete_xor(7);
+        rete.addestra_epoche(&dati_xor(), 5000, OpzioniAddestramento::default()).unwrap();
+        for set in dati_xor() {
+            let atteso = set.output[0] == 1.0;
+            assert_eq!(rete.classifica(set.input.clone(), 0.5).unwrap(), vec![atteso]);
+            assert_eq!(rete.classifica_classe(set.input).unwrap(), usize::from(atteso));
         }
-        return lista;
+
+        // [1, 0] -> classe 1, [0, 1] -> classe 2, [0, 0] -> uscite uguali (1/3), argmax 0
+        let mut rete = ReteNeurale::nuova_con_inizializzazione(vec![
+            strato(2, Arc::new(Nessuna)),
+            strato(3, Arc::new(Softmax)),
+        ], 0.1, InizializzazionePesi::Xavier);
+        rete.imposta_pesi_strato(0, DMatrix::from_row_slice(3, 2, &[0.0, 0.0, 8.0, 0.0, 0.0, 8.0])).unwrap();
+        assert_eq!(rete.classifica_classe(vec![1.0, 0.0]).unwrap(), 1);
+        assert_eq!(rete.classifica_classe(vec![0.0, 1.0]).unwrap(), 2);
+        assert_eq!(rete.classifica_classe(vec![0.0, 0.0]).unwrap(), 0);
+        assert_eq!(rete.classifica(vec![1.0, 0.0], 0.5).unwrap(), vec![false, true, false]);
+        assert_eq!(rete.classifica(vec![0.0, 0.0], 0.3).unwrap(), vec![true, true, true]);
     }
 
-    /// Tasso di apprendimento.
-    pub fn tasso_apprendimento (&self) ->  f64 {
-        self.tasso_apprendimento
+    #[test]
+    fn addestra_salva_ricarica_e_riprende() {
+        let dati = dati_xor();
+        let mut rete = rete_xor(67);
+        let iniziale = rete.perdita_media(&dati);
+        rete.addestra_epoche(&dati, 1000, OpzioniAddestramento::default()).unwrap();
+        let dopo_prima_parte = rete.perdita_media(&dati);
+        assert!(dopo_prima_parte < iniziale);
+
+        let percorso = file_temporaneo("ripresa.txt");
+        rete.salva_pesi_txt(&percorso).unwrap();
+        let mut ripresa = ReteNeurale::carica(&percorso).unwrap();
+        assert_eq!(ripresa.perdita_media(&dati), dopo_prima_parte);
+
+        // un caricamento fallito lascia la rete com'era
+        std::fs::write(&percorso, "[#]  2, x, 1\n").unwrap();
+        assert!(ripresa.carica_pesi_txt(&percorso).is_err());
+        std::fs::remove_file(&percorso).unwrap();
+        assert!(ripresa.pesi_uguali(&rete, 0.0));
+
+        let perdite = ripresa.addestra_epoche(&dati, 2000, OpzioniAddestramento::default()).unwrap();
+        assert!(perdite[0] < dopo_prima_parte);
+        assert!(perdite[perdite.len() - 1] < perdite[0]);
+        assert!(ripresa.perdita_media(&dati) < dopo_prima_parte * 0.5);
     }
 }
-